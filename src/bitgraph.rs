@@ -1,43 +1,562 @@
+use anyhow::{bail, Result};
 use fixedbitset::FixedBitSet;
-use petgraph::{EdgeType, Graph};
+use hashbrown::HashMap;
+use petgraph::{Directed, EdgeType, Graph};
 
+use crate::weight::EdgeWeights;
+
+/// Largest number of vertices stored as a dense `n * n` adjacency matrix.
+/// Larger graphs use sorted adjacency lists instead.
+pub const DENSE_MAX_NODES: usize = 1 << 14;
+
+/// Storage of the directed edges of a graph.
+#[derive(Clone, Debug)]
+enum Adjacency {
+    /// Bit matrix where edge `u -> v` is bit `u * n + v`.
+    Dense(FixedBitSet),
+
+    /// Sorted targets of the edges leaving each vertex.
+    Sparse(Vec<Vec<usize>>),
+}
+impl Adjacency {
+    fn new(n: usize, sparse: bool) -> Self {
+        if sparse {
+            Adjacency::Sparse(vec![Vec::new(); n])
+        } else {
+            Adjacency::Dense(FixedBitSet::with_capacity(n * n))
+        }
+    }
+
+    /// Builds the adjacency from a list of edges, which may contain duplicates.
+    fn from_edges<I>(n: usize, sparse: bool, edges: I) -> Self
+    where
+        I: IntoIterator<Item = (usize, usize)>,
+    {
+        let mut adj = Self::new(n, sparse);
+        match &mut adj {
+            Adjacency::Dense(bits) => edges.into_iter().for_each(|(u, v)| bits.insert(u * n + v)),
+            Adjacency::Sparse(lists) => {
+                edges.into_iter().for_each(|(u, v)| lists[u].push(v));
+                for list in lists.iter_mut() {
+                    list.sort_unstable();
+                    list.dedup();
+                }
+            }
+        }
+        adj
+    }
+
+    #[inline]
+    fn contains(&self, n: usize, u: usize, v: usize) -> bool {
+        match self {
+            Adjacency::Dense(bits) => bits.contains(u * n + v),
+            Adjacency::Sparse(lists) => lists[u].binary_search(&v).is_ok(),
+        }
+    }
+
+    fn set(&mut self, n: usize, u: usize, v: usize, enabled: bool) {
+        match self {
+            Adjacency::Dense(bits) => bits.set(u * n + v, enabled),
+            Adjacency::Sparse(lists) => {
+                if enabled {
+                    Bitgraph::insert_sorted(&mut lists[u], v);
+                } else {
+                    Bitgraph::remove_sorted(&mut lists[u], v);
+                }
+            }
+        }
+    }
+
+    /// Returns all edges `u -> v`, including self-loops.
+    fn edges(&self, n: usize) -> Vec<(usize, usize)> {
+        match self {
+            Adjacency::Dense(bits) => bits.ones().map(|idx| (idx / n, idx % n)).collect(),
+            Adjacency::Sparse(lists) => lists
+                .iter()
+                .enumerate()
+                .flat_map(|(u, list)| list.iter().map(move |v| (u, *v)))
+                .collect(),
+        }
+    }
+}
+
+/// The queries the census makes of a graph, so that it can run on other
+/// representations than `Bitgraph`.
+pub trait Neighborhood {
+    /// Checks if the graph has the edge `u -> v`.
+    fn is_connected(&self, u: usize, v: usize) -> bool;
+
+    /// The vertices connected to `u` in either direction, excluding `u`.
+    fn neighbors(&self, u: usize) -> &[usize];
+
+    fn n_nodes(&self) -> usize;
+
+    /// Checks if the edge `u -> v` may be part of an occurrence. Every edge
+    /// may be unless a view of the graph rejects some, see `HeavyEdges`.
+    #[inline]
+    fn admits_edge(&self, _u: usize, _v: usize) -> bool {
+        true
+    }
+}
+
+/// A `Neighborhood` which also knows whether its edges are directed, as
+/// needed by `Gtrie::check_directedness`.
+pub trait GraphQuery: Neighborhood {
+    fn is_dir(&self) -> bool;
+}
+
+#[derive(Clone, Debug)]
 pub struct Bitgraph {
-    adj: FixedBitSet,
+    adj: Adjacency,
     n: usize,
     is_dir: bool,
     neighbors: Vec<Vec<usize>>,
+    weights: Option<EdgeWeights>,
+}
+impl Neighborhood for Bitgraph {
+    fn is_connected(&self, u: usize, v: usize) -> bool {
+        Bitgraph::is_connected(self, u, v)
+    }
+
+    fn neighbors(&self, u: usize) -> &[usize] {
+        Bitgraph::neighbors(self, u)
+    }
+
+    fn n_nodes(&self) -> usize {
+        Bitgraph::n_nodes(self)
+    }
+}
+impl GraphQuery for Bitgraph {
+    fn is_dir(&self) -> bool {
+        Bitgraph::is_dir(self)
+    }
 }
 impl Bitgraph {
+    /// Creates a bitgraph from a petgraph graph, using sparse adjacency lists
+    /// if it has more than `DENSE_MAX_NODES` vertices.
+    ///
+    /// Parallel edges collapse into a single edge, see `from_graph_counting`
+    /// to keep their multiplicity. The edges of an undirected graph are
+    /// inserted both ways, so its adjacency is symmetric.
     pub fn from_graph<Ty: EdgeType>(graph: &Graph<(), (), Ty>) -> Self {
         let n = graph.node_count();
-        let is_dir = Ty::is_directed();
-        let mut adj = FixedBitSet::with_capacity(n * n);
-        let mut neighbors = vec![Vec::with_capacity(n); n];
-        for edge in graph.edge_indices() {
+        let edges = graph.edge_indices().flat_map(|edge| {
             let (src, dst) = graph.edge_endpoints(edge).unwrap();
-            adj.insert(src.index() * n + dst.index());
+            let (src, dst) = (src.index(), dst.index());
+            let reverse = (!Ty::is_directed()).then_some((dst, src));
+            std::iter::once((src, dst)).chain(reverse)
+        });
+        Self::from_adjacency(
+            Adjacency::from_edges(n, n > DENSE_MAX_NODES, edges),
+            n,
+            Ty::is_directed(),
+        )
+    }
+
+    /// Creates a bitgraph from a petgraph graph along with the multiplicity of
+    /// each of its edges, keyed by `(source, target)`.
+    ///
+    /// The bitgraph is the same simple graph as `from_graph`. Only edges present
+    /// in the input are in the multiplicity map.
+    pub fn from_graph_counting<Ty: EdgeType>(
+        graph: &Graph<(), (), Ty>,
+    ) -> (Self, HashMap<(usize, usize), usize>) {
+        let mut multiplicity = HashMap::new();
+        for edge in graph.raw_edges() {
+            let key = (edge.source().index(), edge.target().index());
+            *multiplicity.entry(key).or_insert(0) += 1;
         }
+        (Self::from_graph(graph), multiplicity)
+    }
 
-        for u in 0..n {
-            for v in u + 1..n {
-                if adj.contains(u * n + v) || adj.contains(v * n + u) {
-                    neighbors[u].push(v);
-                    neighbors[v].push(u);
-                }
-            }
+    /// Converts the bitgraph to a directed petgraph graph, keeping isolated vertices.
+    pub fn to_graph(&self) -> Graph<(), (), Directed> {
+        let mut graph = Graph::with_capacity(self.n, 0);
+        for _ in 0..self.n {
+            graph.add_node(());
         }
+        for (u, v) in self.edges() {
+            graph.add_edge((u as u32).into(), (v as u32).into(), ());
+        }
+        graph
+    }
 
+    /// Creates a graph with `n` vertices and no edges, using sparse adjacency
+    /// lists if `n` is more than `DENSE_MAX_NODES`.
+    #[allow(dead_code)]
+    pub fn with_capacity(n: usize, is_dir: bool) -> Self {
+        Bitgraph {
+            adj: Adjacency::new(n, n > DENSE_MAX_NODES),
+            n,
+            is_dir,
+            neighbors: vec![Vec::new(); n],
+            weights: None,
+        }
+    }
+
+    fn from_adjacency(adj: Adjacency, n: usize, is_dir: bool) -> Self {
+        let neighbors = Self::build_neighbors(&adj, n);
         Bitgraph {
             adj,
             n,
             is_dir,
             neighbors,
+            weights: None,
+        }
+    }
+
+    /// Converts the graph to sparse adjacency lists regardless of its size.
+    pub fn into_sparse(self) -> Self {
+        match self.adj {
+            Adjacency::Sparse(_) => self,
+            Adjacency::Dense(_) => Bitgraph {
+                adj: Adjacency::from_edges(self.n, true, self.adj.edges(self.n)),
+                ..self
+            },
+        }
+    }
+
+    /// Checks if the edges are stored as adjacency lists rather than a matrix.
+    pub fn is_sparse(&self) -> bool {
+        matches!(self.adj, Adjacency::Sparse(_))
+    }
+
+    /// Builds the (undirected) neighbor lists of an adjacency.
+    /// Self-loops are not considered neighbors.
+    fn build_neighbors(adj: &Adjacency, n: usize) -> Vec<Vec<usize>> {
+        match adj {
+            Adjacency::Dense(bits) => {
+                let mut neighbors = vec![Vec::with_capacity(n); n];
+                for u in 0..n {
+                    for v in u + 1..n {
+                        if bits.contains(u * n + v) || bits.contains(v * n + u) {
+                            neighbors[u].push(v);
+                            neighbors[v].push(u);
+                        }
+                    }
+                }
+                neighbors
+            }
+            Adjacency::Sparse(lists) => {
+                let mut neighbors = vec![Vec::new(); n];
+                for (u, list) in lists.iter().enumerate() {
+                    for v in list.iter().filter(|v| **v != u) {
+                        neighbors[u].push(*v);
+                        neighbors[*v].push(u);
+                    }
+                }
+                for list in neighbors.iter_mut() {
+                    list.sort_unstable();
+                    list.dedup();
+                }
+                neighbors
+            }
+        }
+    }
+
+    /// Creates the subgraph induced by the given vertices.
+    ///
+    /// Vertex `i` of the subgraph corresponds to `vertices[i]` of this graph.
+    pub fn induced_subgraph(&self, vertices: &[usize]) -> Self {
+        let n = vertices.len();
+        let mut index = vec![None; self.n];
+        vertices
+            .iter()
+            .enumerate()
+            .for_each(|(i, u)| index[*u] = Some(i));
+        let adj = if n > DENSE_MAX_NODES {
+            let edges = self
+                .adj
+                .edges(self.n)
+                .into_iter()
+                .filter_map(|(u, v)| Some((index[u]?, index[v]?)));
+            Adjacency::from_edges(n, true, edges)
+        } else {
+            let mut bits = FixedBitSet::with_capacity(n * n);
+            for (i, u) in vertices.iter().enumerate() {
+                for (j, v) in vertices.iter().enumerate() {
+                    if self.is_connected(*u, *v) {
+                        bits.insert(i * n + j);
+                    }
+                }
+            }
+            Adjacency::Dense(bits)
+        };
+        let weights = self.weights.as_ref().map(|w| w.map_vertices(|u| index[u]));
+        Self {
+            weights,
+            ..Self::from_adjacency(adj, n, self.is_dir)
+        }
+    }
+
+    /// Creates a copy of the graph where vertex `i` becomes vertex `perm[i]`.
+    pub fn relabel(&self, perm: &[usize]) -> Result<Self> {
+        if perm.len() != self.n {
+            bail!(
+                "Permutation has {} entries but the graph has {} nodes",
+                perm.len(),
+                self.n
+            );
+        }
+        let mut seen = FixedBitSet::with_capacity(self.n);
+        for p in perm {
+            if *p >= self.n || seen.put(*p) {
+                bail!("Invalid permutation: {} is out of range or repeated", p);
+            }
+        }
+
+        let edges = self
+            .adj
+            .edges(self.n)
+            .into_iter()
+            .map(|(u, v)| (perm[u], perm[v]));
+        let adj = Adjacency::from_edges(self.n, self.is_sparse(), edges);
+        let weights = self
+            .weights
+            .as_ref()
+            .map(|w| w.map_vertices(|u| Some(perm[u])));
+        Ok(Self {
+            weights,
+            ..Self::from_adjacency(adj, self.n, self.is_dir)
+        })
+    }
+
+    /// Returns the total degree (in + out, ignoring self-loops) of vertex `u`.
+    pub fn degree(&self, u: usize) -> usize {
+        self.neighbors(u)
+            .iter()
+            .map(|v| usize::from(self.is_connected(u, *v)) + usize::from(self.is_connected(*v, u)))
+            .sum()
+    }
+
+    /// Returns the number of edges leaving `u`, ignoring self-loops.
+    pub fn out_degree(&self, u: usize) -> usize {
+        self.neighbors(u)
+            .iter()
+            .filter(|v| self.is_connected(u, **v))
+            .count()
+    }
+
+    /// Returns the number of edges entering `u`, ignoring self-loops.
+    pub fn in_degree(&self, u: usize) -> usize {
+        self.neighbors(u)
+            .iter()
+            .filter(|v| self.is_connected(**v, u))
+            .count()
+    }
+
+    /// Returns all edges `u -> v` of the graph, ignoring self-loops.
+    pub fn edges(&self) -> Vec<(usize, usize)> {
+        let mut edges = self.adj.edges(self.n);
+        edges.retain(|(u, v)| u != v);
+        edges
+    }
+
+    /// Computes the k-core of the graph by iteratively removing all vertices
+    /// whose total degree is less than `k`.
+    ///
+    /// Returns the core as an induced subgraph along with the original index
+    /// of each of its vertices.
+    pub fn k_core(&self, k: usize) -> (Self, Vec<usize>) {
+        let mut degree = (0..self.n).map(|u| self.degree(u)).collect::<Vec<_>>();
+        let mut removed = FixedBitSet::with_capacity(self.n);
+        let mut queue = (0..self.n).filter(|u| degree[*u] < k).collect::<Vec<_>>();
+        queue.iter().for_each(|u| removed.insert(*u));
+
+        while let Some(u) = queue.pop() {
+            for v in self.neighbors(u) {
+                if removed.contains(*v) {
+                    continue;
+                }
+                degree[*v] -=
+                    usize::from(self.is_connected(u, *v)) + usize::from(self.is_connected(*v, u));
+                if degree[*v] < k {
+                    removed.insert(*v);
+                    queue.push(*v);
+                }
+            }
+        }
+
+        let core = (0..self.n)
+            .filter(|u| !removed.contains(*u))
+            .collect::<Vec<_>>();
+        (self.induced_subgraph(&core), core)
+    }
+
+    /// Returns the sorted vertices within `hops` of `seed` (including `seed`)
+    /// following edges in either direction.
+    pub fn neighborhood(&self, seed: usize, hops: usize) -> Vec<usize> {
+        let mut visited = FixedBitSet::with_capacity(self.n);
+        let mut frontier = vec![seed];
+        visited.insert(seed);
+        for _ in 0..hops {
+            let mut next = Vec::new();
+            for u in frontier {
+                for v in self.neighbors(u) {
+                    if !visited.put(*v) {
+                        next.push(*v);
+                    }
+                }
+            }
+            frontier = next;
+        }
+        visited.ones().collect()
+    }
+
+    /// Extracts the subgraph induced by the vertices within `hops` of `seed`,
+    /// following edges in either direction.
+    ///
+    /// Returns the subgraph along with the original index of each of its vertices.
+    pub fn ego_subgraph(&self, seed: usize, hops: usize) -> (Self, Vec<usize>) {
+        let vertices = self.neighborhood(seed, hops);
+        (self.induced_subgraph(&vertices), vertices)
+    }
+
+    /// Inserts the edge `u -> v` and returns `true` if it was not already present.
+    #[allow(dead_code)]
+    pub fn insert_edge(&mut self, u: usize, v: usize) -> bool {
+        assert!(u < self.n && v < self.n, "vertex out of range");
+        if self.is_connected(u, v) {
+            return false;
+        }
+        if u != v && !self.is_connected(v, u) {
+            Self::insert_sorted(&mut self.neighbors[u], v);
+            Self::insert_sorted(&mut self.neighbors[v], u);
+        }
+        self.adj.set(self.n, u, v, true);
+        true
+    }
+
+    /// Removes the edge `u -> v` and returns `true` if it was present.
+    #[allow(dead_code)]
+    pub fn remove_edge(&mut self, u: usize, v: usize) -> bool {
+        assert!(u < self.n && v < self.n, "vertex out of range");
+        if !self.is_connected(u, v) {
+            return false;
+        }
+        self.adj.set(self.n, u, v, false);
+        if u != v && !self.is_connected(v, u) {
+            Self::remove_sorted(&mut self.neighbors[u], v);
+            Self::remove_sorted(&mut self.neighbors[v], u);
+        }
+        true
+    }
+
+    fn insert_sorted(list: &mut Vec<usize>, x: usize) {
+        if let Err(idx) = list.binary_search(&x) {
+            list.insert(idx, x);
+        }
+    }
+
+    fn remove_sorted(list: &mut Vec<usize>, x: usize) {
+        if let Ok(idx) = list.binary_search(&x) {
+            list.remove(idx);
         }
     }
 
     #[inline]
     pub fn is_connected(&self, u: usize, v: usize) -> bool {
-        self.adj.contains(u * self.n + v)
+        self.adj.contains(self.n, u, v)
+    }
+
+    /// Checks if every vertex can reach every other vertex following edges
+    /// in their direction.
+    pub fn is_strongly_connected(&self) -> bool {
+        if self.n == 0 {
+            return true;
+        }
+        let reaches_all = |forward: bool| {
+            let mut visited = FixedBitSet::with_capacity(self.n);
+            let mut stack = vec![0];
+            visited.insert(0);
+            while let Some(u) = stack.pop() {
+                for v in self.neighbors(u) {
+                    let is_edge = if forward {
+                        self.is_connected(u, *v)
+                    } else {
+                        self.is_connected(*v, u)
+                    };
+                    if is_edge && !visited.put(*v) {
+                        stack.push(*v);
+                    }
+                }
+            }
+            visited.count_ones(..) == self.n
+        };
+        reaches_all(true) && reaches_all(false)
+    }
+
+    /// Checks if the graph has no directed cycles, ignoring self-loops.
+    pub fn is_acyclic(&self) -> bool {
+        let mut in_degree = (0..self.n).map(|u| self.in_degree(u)).collect::<Vec<_>>();
+        let mut stack = (0..self.n)
+            .filter(|u| in_degree[*u] == 0)
+            .collect::<Vec<_>>();
+        let mut n_visited = 0;
+        while let Some(u) = stack.pop() {
+            n_visited += 1;
+            for v in self.neighbors(u) {
+                if self.is_connected(u, *v) {
+                    in_degree[*v] -= 1;
+                    if in_degree[*v] == 0 {
+                        stack.push(*v);
+                    }
+                }
+            }
+        }
+        n_visited == self.n
+    }
+
+    /// Checks if every edge `u -> v` has a reciprocal edge `v -> u`.
+    pub fn is_symmetric(&self) -> bool {
+        self.adj
+            .edges(self.n)
+            .into_iter()
+            .all(|(u, v)| self.is_connected(v, u))
+    }
+
+    /// Marks a symmetric graph as undirected.
+    pub fn into_undirected(mut self) -> Self {
+        assert!(
+            self.is_symmetric(),
+            "only symmetric graphs can be undirected"
+        );
+        self.is_dir = false;
+        if let Some(weights) = &mut self.weights {
+            weights.symmetrize();
+        }
+        self
+    }
+
+    /// Reciprocates every edge and marks the graph as undirected. The reverse
+    /// edges added share the weight of the edges they mirror.
+    pub fn symmetrize(mut self) -> Self {
+        for (u, v) in self.edges() {
+            self.insert_edge(v, u);
+        }
+        self.is_dir = false;
+        if let Some(weights) = &mut self.weights {
+            weights.symmetrize();
+        }
+        self
+    }
+
+    /// Attaches weights to the edges of the graph.
+    pub fn with_weights(self, weights: EdgeWeights) -> Self {
+        Self {
+            weights: Some(weights),
+            ..self
+        }
+    }
+
+    /// The weights of the edges, if the graph has any.
+    pub fn weights(&self) -> Option<&EdgeWeights> {
+        self.weights.as_ref()
+    }
+
+    /// Returns the weight of the edge `u -> v`, 1 for an unweighted graph.
+    pub fn edge_weight(&self, u: usize, v: usize) -> f64 {
+        self.weights.as_ref().map_or(1.0, |w| w.get(u, v))
     }
 
     pub fn n_nodes(&self) -> usize {
@@ -48,12 +567,26 @@ impl Bitgraph {
         self.is_dir
     }
 
+    /// Returns the dense adjacency matrix.
+    ///
+    /// Panics if the graph is sparse.
     pub fn adjacency(&self) -> &FixedBitSet {
-        &self.adj
+        match &self.adj {
+            Adjacency::Dense(bits) => bits,
+            Adjacency::Sparse(_) => panic!("sparse graphs have no adjacency matrix"),
+        }
     }
 
+    /// Replaces the edges of the graph with a dense adjacency matrix of the
+    /// same size and rebuilds the neighbor lists to match.
     pub fn overwrite_adjacency(&mut self, adj: &FixedBitSet) {
-        self.adj = adj.clone();
+        assert_eq!(
+            adj.len(),
+            self.n * self.n,
+            "adjacency matrix of the wrong size"
+        );
+        self.adj = Adjacency::Dense(adj.clone());
+        self.neighbors = Self::build_neighbors(&self.adj, self.n);
     }
 
     pub fn neighbors(&self, u: usize) -> &Vec<usize> {
@@ -92,9 +625,9 @@ impl Bitgraph {
 #[cfg(test)]
 mod testing {
 
-    use petgraph::Directed;
-
     use super::*;
+    use crate::{gtrie::Gtrie, random::XorShift};
+    use petgraph::Undirected;
 
     fn build_graph() -> Graph<(), (), Directed> {
         let edges = vec![(1, 0), (2, 0)];
@@ -119,4 +652,243 @@ mod testing {
             }
         }
     }
+
+    #[test]
+    fn test_with_capacity() {
+        let bitgraph = Bitgraph::with_capacity(4, true);
+        assert_eq!(bitgraph.n_nodes(), 4);
+        assert!(bitgraph.is_dir());
+        assert!(bitgraph.edges().is_empty());
+        for u in 0..bitgraph.n_nodes() {
+            assert!(bitgraph.neighbors(u).is_empty());
+        }
+    }
+
+    #[test]
+    fn test_insert_edges() {
+        let edges = [(0, 1), (1, 2), (2, 0), (2, 3), (3, 2), (4, 1), (3, 3)];
+        let graph: Graph<(), (), Directed> =
+            Graph::from_edges(edges.iter().map(|(u, v)| (*u as u32, *v as u32)));
+        let expected = Bitgraph::from_graph(&graph);
+
+        let mut bitgraph = Bitgraph::with_capacity(5, true);
+        for (u, v) in edges.iter() {
+            assert!(bitgraph.insert_edge(*u, *v));
+        }
+        assert!(!bitgraph.insert_edge(0, 1));
+
+        assert_eq!(bitgraph.adjacency(), expected.adjacency());
+        for u in 0..bitgraph.n_nodes() {
+            assert_eq!(bitgraph.neighbors(u), expected.neighbors(u));
+        }
+    }
+
+    #[test]
+    fn test_remove_edges() {
+        let mut bitgraph = Bitgraph::from_graph(&build_graph());
+        bitgraph.insert_edge(0, 1);
+
+        // reciprocal edge remains so the vertices are still neighbors
+        assert!(bitgraph.remove_edge(1, 0));
+        assert!(!bitgraph.remove_edge(1, 0));
+        assert_eq!(bitgraph.neighbors(0), &vec![1, 2]);
+        assert_eq!(bitgraph.neighbors(1), &vec![0]);
+
+        assert!(bitgraph.remove_edge(0, 1));
+        assert_eq!(bitgraph.neighbors(0), &vec![2]);
+        assert!(bitgraph.neighbors(1).is_empty());
+        assert!(!bitgraph.is_connected(0, 1));
+    }
+
+    #[test]
+    fn test_is_symmetric() {
+        let graph = Bitgraph::from_graph(&build_graph());
+        assert!(!graph.is_symmetric());
+
+        let edges = vec![(0, 1), (1, 0), (1, 2), (2, 1), (2, 2)];
+        let graph: Graph<(), (), Directed> = Graph::from_edges(&edges);
+        let graph = Bitgraph::from_graph(&graph);
+        assert!(graph.is_symmetric());
+        assert!(graph.is_dir());
+        assert!(!graph.into_undirected().is_dir());
+    }
+
+    #[test]
+    fn test_undirected_symmetric() {
+        let graph: Graph<(), (), Undirected> = Graph::from_edges([(0, 1), (1, 2)]);
+        let graph = Bitgraph::from_graph(&graph);
+        assert!(!graph.is_dir());
+        assert!(graph.is_symmetric());
+        assert!(graph.is_connected(1, 0) && graph.is_connected(2, 1));
+
+        let graph = Bitgraph::from_graph(&build_graph()).symmetrize();
+        assert!(!graph.is_dir());
+        assert!(graph.is_symmetric());
+        assert_eq!(graph.edges().len(), 2 * build_graph().edge_count());
+    }
+
+    #[test]
+    fn test_k_core() {
+        let edges = vec![(0, 1), (1, 2), (2, 0), (2, 3), (3, 4)];
+        let graph: Graph<(), (), Directed> = Graph::from_edges(&edges);
+        let graph = Bitgraph::from_graph(&graph);
+        assert_eq!(graph.degree(2), 3);
+
+        let (core, mapping) = graph.k_core(2);
+        assert_eq!(mapping, vec![0, 1, 2]);
+        assert_eq!(core.n_nodes(), 3);
+        assert!(core.is_connected(2, 0));
+
+        let (core, mapping) = graph.k_core(3);
+        assert!(mapping.is_empty());
+        assert_eq!(core.n_nodes(), 0);
+
+        let (core, mapping) = graph.k_core(0);
+        assert_eq!(mapping.len(), 5);
+        assert_eq!(core.adjacency(), graph.adjacency());
+    }
+
+    #[test]
+    fn test_relabel() {
+        let edges = vec![(0, 1), (1, 2), (2, 0), (2, 3)];
+        let graph: Graph<(), (), Directed> = Graph::from_edges(&edges);
+        let graph = Bitgraph::from_graph(&graph);
+
+        let relabeled = graph.relabel(&[3, 0, 1, 2]).unwrap();
+        assert!(relabeled.is_connected(3, 0));
+        assert!(relabeled.is_connected(1, 2));
+        assert!(!relabeled.is_connected(0, 3));
+        assert_eq!(relabeled.neighbors(1), &vec![0, 2, 3]);
+        assert_eq!(relabeled.edges().len(), 4);
+
+        assert!(graph.relabel(&[0, 1, 2]).is_err());
+        assert!(graph.relabel(&[0, 1, 1, 2]).is_err());
+        assert!(graph.relabel(&[0, 1, 2, 4]).is_err());
+    }
+
+    #[test]
+    fn relabel_preserves_census() {
+        let graph = crate::io::load_numeric_graph("example/graphs/yeast.txt", true, false).unwrap();
+        let graph = Bitgraph::from_graph(&graph);
+        let mut perm = (0..graph.n_nodes()).collect::<Vec<_>>();
+        XorShift::new(3).shuffle(&mut perm);
+        let relabeled = graph.relabel(&perm).unwrap();
+
+        let mut gtrie = Gtrie::read_from_file("example/gtries/dir4.gt").unwrap();
+        gtrie.census(&graph);
+        let mut relabeled_gtrie = Gtrie::read_from_file("example/gtries/dir4.gt").unwrap();
+        relabeled_gtrie.census(&relabeled);
+        assert_eq!(relabeled_gtrie.total_subgraphs(), gtrie.total_subgraphs());
+        assert_eq!(relabeled_gtrie.get_nonzero(), gtrie.get_nonzero());
+    }
+
+    #[test]
+    fn test_strongly_connected_and_acyclic() {
+        let cycle: Graph<(), (), Directed> = Graph::from_edges([(0, 1), (1, 2), (2, 0)]);
+        let cycle = Bitgraph::from_graph(&cycle);
+        assert!(cycle.is_strongly_connected());
+        assert!(!cycle.is_acyclic());
+
+        let ffl: Graph<(), (), Directed> = Graph::from_edges([(0, 1), (1, 2), (0, 2)]);
+        let ffl = Bitgraph::from_graph(&ffl);
+        assert!(!ffl.is_strongly_connected());
+        assert!(ffl.is_acyclic());
+
+        let mutual: Graph<(), (), Directed> = Graph::from_edges([(0, 1), (1, 0), (1, 2)]);
+        let mutual = Bitgraph::from_graph(&mutual);
+        assert!(!mutual.is_strongly_connected());
+        assert!(!mutual.is_acyclic());
+    }
+
+    #[test]
+    fn sparse_matches_dense() {
+        let edges = [(0, 1), (1, 2), (2, 0), (2, 3), (3, 2), (4, 1), (3, 3)];
+        let graph: Graph<(), (), Directed> = Graph::from_edges(edges);
+        let dense = Bitgraph::from_graph(&graph);
+        let mut sparse = dense.clone().into_sparse();
+        assert!(sparse.is_sparse());
+        assert!(!dense.is_sparse());
+        assert_eq!(sparse.edges(), dense.edges());
+        for u in 0..dense.n_nodes() {
+            assert_eq!(sparse.neighbors(u), dense.neighbors(u));
+            for v in 0..dense.n_nodes() {
+                assert_eq!(sparse.is_connected(u, v), dense.is_connected(u, v));
+            }
+        }
+
+        assert!(sparse.insert_edge(0, 4));
+        assert!(!sparse.insert_edge(0, 4));
+        assert!(sparse.is_connected(0, 4));
+        assert!(sparse.remove_edge(0, 1));
+        assert!(!sparse.is_connected(0, 1));
+        assert_eq!(sparse.neighbors(0), &vec![2, 4]);
+
+        let relabeled = sparse.relabel(&[4, 3, 2, 1, 0]).unwrap();
+        assert!(relabeled.is_sparse());
+        assert!(relabeled.is_connected(4, 0));
+    }
+
+    #[test]
+    fn sparse_census_matches_dense() {
+        let graph = crate::io::load_numeric_graph("example/graphs/yeast.txt", true, false).unwrap();
+        let dense = Bitgraph::from_graph(&graph);
+        let sparse = dense.clone().into_sparse();
+
+        let mut expected = Gtrie::read_from_file("example/gtries/dir3.gt").unwrap();
+        expected.census(&dense);
+        let mut gtrie = Gtrie::read_from_file("example/gtries/dir3.gt").unwrap();
+        gtrie.census(&sparse);
+        assert_eq!(gtrie.get_nonzero(), expected.get_nonzero());
+
+        let (core, _) = sparse.k_core(3);
+        let (expected_core, _) = dense.k_core(3);
+        assert_eq!(core.edges(), expected_core.edges());
+    }
+
+    #[test]
+    fn overwrite_adjacency_updates_neighbors() {
+        let mut bitgraph = Bitgraph::from_graph(&build_graph());
+        let mut adj = FixedBitSet::with_capacity(9);
+        adj.insert(1);
+        adj.insert(2 * 3 + 1);
+        bitgraph.overwrite_adjacency(&adj);
+        assert!(bitgraph.is_connected(0, 1));
+        assert!(!bitgraph.is_connected(1, 0));
+        assert_eq!(bitgraph.neighbors(0), &vec![1]);
+        assert_eq!(bitgraph.neighbors(1), &vec![0, 2]);
+        assert_eq!(bitgraph.neighbors(2), &vec![1]);
+    }
+
+    #[test]
+    fn test_ego_subgraph() {
+        // A star around 0 with a tail 3 -> 4 -> 5.
+        let edges = [(0, 1), (2, 0), (0, 3), (3, 4), (4, 5), (1, 2)];
+        let graph: Graph<(), (), Directed> = Graph::from_edges(edges);
+        let graph = Bitgraph::from_graph(&graph);
+
+        let (ego, vertices) = graph.ego_subgraph(0, 1);
+        assert_eq!(vertices, vec![0, 1, 2, 3]);
+        assert_eq!(ego.n_nodes(), 4);
+        assert_eq!(ego.edges(), vec![(0, 1), (0, 3), (1, 2), (2, 0)]);
+
+        let (ego, vertices) = graph.ego_subgraph(4, 2);
+        assert_eq!(vertices, vec![0, 3, 4, 5]);
+        assert_eq!(ego.edges().len(), 3);
+
+        let (ego, vertices) = graph.ego_subgraph(5, 0);
+        assert_eq!(vertices, vec![5]);
+        assert!(ego.edges().is_empty());
+    }
+
+    #[test]
+    fn from_graph_counting_parallel_edges() {
+        let graph: Graph<(), (), Directed> = Graph::from_edges([(0, 1), (0, 1), (1, 2), (2, 1)]);
+        let (bgraph, multiplicity) = Bitgraph::from_graph_counting(&graph);
+        assert_eq!(bgraph.edges(), Bitgraph::from_graph(&graph).edges());
+        assert_eq!(bgraph.edges().len(), 3);
+        assert_eq!(multiplicity[&(0, 1)], 2);
+        assert_eq!(multiplicity[&(1, 2)], 1);
+        assert_eq!(multiplicity[&(2, 1)], 1);
+        assert_eq!(multiplicity.len(), 3);
+    }
 }
@@ -0,0 +1,189 @@
+use crate::{
+    bitgraph::Bitgraph,
+    isomorphism::{canonicalize_with, ApMode, CanonicalBasedNauty},
+};
+use anyhow::Result;
+use hashbrown::HashMap;
+use petgraph::{Directed, Graph};
+use serde::{Deserialize, Serialize};
+use std::io::{Read, Write};
+
+/// Leads every cache file, followed by `CACHE_VERSION` as a little-endian
+/// `u32`.
+const CACHE_MAGIC: &[u8; 8] = b"GTCANON\0";
+
+/// Version of the canonicalization and of the layout of the cached forms.
+/// Bump it whenever either changes, so that stale caches are discarded.
+const CACHE_VERSION: u32 = 1;
+
+/// The input a canonical form was computed from: a graph6 line, or its
+/// reversal if `reversed`, canonicalized with `ap_mode`.
+#[derive(Clone, Debug, PartialEq, Eq, Hash, Serialize, Deserialize)]
+struct CacheKey {
+    graph6: String,
+    reversed: bool,
+    ap_mode: ApMode,
+}
+
+/// A cache of the canonical forms computed by `canonicalize_with`, so that
+/// repeated builds over the same graph6 lines skip the nauty calls.
+///
+/// The cache is stored as MessagePack, see `CanonCache::read_from_file`.
+#[derive(Debug, Default)]
+pub struct CanonCache {
+    entries: HashMap<CacheKey, CanonicalBasedNauty>,
+    n_hits: usize,
+    n_misses: usize,
+}
+impl CanonCache {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Reads a cache written by `write_to_file`, or an empty cache if the
+    /// file does not exist or was written by another version of the cache.
+    pub fn read_from_file(path: &str) -> Result<Self> {
+        let file = match std::fs::File::open(path) {
+            Ok(file) => file,
+            Err(e) if e.kind() == std::io::ErrorKind::NotFound => return Ok(Self::new()),
+            Err(e) => return Err(e.into()),
+        };
+        let mut reader = std::io::BufReader::new(file);
+        let mut header = [0; 12];
+        if reader.read_exact(&mut header).is_err() || header != Self::header() {
+            return Ok(Self::new());
+        }
+        let entries: Vec<(CacheKey, CanonicalBasedNauty)> = rmp_serde::from_read(reader)?;
+        Ok(Self {
+            entries: entries.into_iter().collect(),
+            ..Self::default()
+        })
+    }
+
+    pub fn write_to_file(&self, path: &str) -> Result<()> {
+        let mut writer = std::io::BufWriter::new(std::fs::File::create(path)?);
+        writer.write_all(&Self::header())?;
+        let entries = self.entries.iter().collect::<Vec<_>>();
+        rmp_serde::encode::write(&mut writer, &entries)?;
+        writer.flush()?;
+        Ok(())
+    }
+
+    /// The magic bytes and version leading a cache file.
+    fn header() -> [u8; 12] {
+        let mut header = [0; 12];
+        header[..8].copy_from_slice(CACHE_MAGIC);
+        header[8..].copy_from_slice(&CACHE_VERSION.to_le_bytes());
+        header
+    }
+
+    /// Equivalent to `canonicalize_with(graph, ap_mode)` for the graph parsed
+    /// from the line `graph6`, or its reversal if `reversed`, reusing the
+    /// cached canonical form when there is one.
+    pub fn canonicalize_with(
+        &mut self,
+        graph6: &str,
+        reversed: bool,
+        graph: &Graph<(), (), Directed>,
+        ap_mode: ApMode,
+    ) -> (Bitgraph, CanonicalBasedNauty) {
+        let key = CacheKey {
+            graph6: graph6.to_string(),
+            reversed,
+            ap_mode,
+        };
+        if let Some(canon) = self.entries.get(&key) {
+            self.n_hits += 1;
+            let mut bgraph = Bitgraph::with_capacity(canon.orbits.len(), true);
+            bgraph.overwrite_adjacency(canon.adjacency());
+            return (bgraph, canon.clone());
+        }
+        self.n_misses += 1;
+        let (bgraph, canon) = canonicalize_with(graph, ap_mode);
+        self.entries.insert(key, canon.clone());
+        (bgraph, canon)
+    }
+
+    /// Number of canonical forms found in the cache.
+    pub fn n_hits(&self) -> usize {
+        self.n_hits
+    }
+
+    /// Number of canonical forms computed and added to the cache.
+    pub fn n_misses(&self) -> usize {
+        self.n_misses
+    }
+
+    pub fn len(&self) -> usize {
+        self.entries.len()
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.entries.is_empty()
+    }
+}
+
+#[cfg(test)]
+mod testing {
+    use super::*;
+    use crate::io::{iter_reprs_from_file, load_repr};
+
+    #[test]
+    fn cached_forms_match_canonicalization() {
+        let path = std::env::temp_dir().join("graphtries_canon_cache_dir3.cache");
+        let path = path.to_str().unwrap();
+        let _ = std::fs::remove_file(path);
+
+        let mut cache = CanonCache::read_from_file(path).unwrap();
+        assert!(cache.is_empty());
+        for result in iter_reprs_from_file("example/subgraphs/dir3.g6").unwrap() {
+            let (line, graph) = result.unwrap();
+            cache.canonicalize_with(&line, false, &graph, ApMode::Auto);
+        }
+        assert_eq!(cache.n_hits(), 0);
+        cache.write_to_file(path).unwrap();
+
+        let mut cache = CanonCache::read_from_file(path).unwrap();
+        std::fs::remove_file(path).unwrap();
+        for result in iter_reprs_from_file("example/subgraphs/dir3.g6").unwrap() {
+            let (line, graph) = result.unwrap();
+            let (cached_bgraph, cached) =
+                cache.canonicalize_with(&line, false, &graph, ApMode::Auto);
+            let (bgraph, canon) = canonicalize_with(&graph, ApMode::Auto);
+            assert_eq!(cached.repr(), canon.repr());
+            assert_eq!(cached.orbits, canon.orbits);
+            assert_eq!(cached_bgraph.edges(), bgraph.edges());
+            assert_eq!(
+                cached.conditions().map(|c| c.to_string()),
+                canon.conditions().map(|c| c.to_string())
+            );
+        }
+        assert_eq!(cache.n_misses(), 0);
+        assert_eq!(cache.n_hits(), cache.len());
+
+        // Another ap-mode is a different canonical form.
+        let graph = load_repr("&BC_").unwrap();
+        cache.canonicalize_with("&BC_", false, &graph, ApMode::Never);
+        assert_eq!(cache.n_misses(), 1);
+    }
+
+    #[test]
+    fn other_versions_are_discarded() {
+        let path = std::env::temp_dir().join("graphtries_canon_cache_version.cache");
+        let path = path.to_str().unwrap();
+        let mut cache = CanonCache::new();
+        let graph = load_repr("&BC_").unwrap();
+        cache.canonicalize_with("&BC_", false, &graph, ApMode::Auto);
+        cache.write_to_file(path).unwrap();
+        assert_eq!(CanonCache::read_from_file(path).unwrap().len(), 1);
+
+        // A cache of another version, and one without a header.
+        let mut bytes = std::fs::read(path).unwrap();
+        bytes[8] = bytes[8].wrapping_add(1);
+        std::fs::write(path, &bytes).unwrap();
+        assert!(CanonCache::read_from_file(path).unwrap().is_empty());
+        std::fs::write(path, &bytes[12..]).unwrap();
+        assert!(CanonCache::read_from_file(path).unwrap().is_empty());
+        std::fs::remove_file(path).unwrap();
+    }
+}
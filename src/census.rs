@@ -1,6 +1,25 @@
-use crate::{bitgraph::Bitgraph, node::GtrieNode, symmetry::Conditions};
+use crate::{
+    bitgraph::Neighborhood,
+    node::GtrieNode,
+    results::CensusReport,
+    sampling::Sampler,
+    symmetry::{Condition, Conditions},
+};
 use fixedbitset::FixedBitSet;
 
+/// A reusable set of candidate vertices for extending a partial match.
+///
+/// Candidates are inserted at most once (tracked by an internal blacklist,
+/// distinct from the blacklist of vertices already used in a match) and
+/// popped in reverse order of insertion. The internal blacklist is only reset
+/// by `clear`, so the intended lifecycle for each extension step is:
+///
+/// 1. `fill`, `fill_except`, or `insert` the candidates of the step.
+/// 2. `pop` until `None`, checking each vertex with `matches_structure`.
+/// 3. `clear` before the buffer is reused for the next step.
+///
+/// Popping alone does not allow a vertex to be inserted again, so skipping
+/// `clear` silently drops candidates from later steps.
 pub struct Candidates {
     /// Mutable list reflecting the current set of candidates.
     candidates: Vec<usize>,
@@ -24,6 +43,25 @@ impl Candidates {
         }
     }
 
+    /// Creates an empty candidate set reusing an existing buffer.
+    ///
+    /// The length of the buffer is the number of possible candidates,
+    /// i.e. the number of vertices in the graph. Its contents are ignored.
+    pub fn from_buffer(buffer: Vec<usize>) -> Self {
+        let size = buffer.len();
+        Candidates {
+            candidates: buffer,
+            blacklist: FixedBitSet::with_capacity(size),
+            n: 0,
+            size,
+        }
+    }
+
+    /// Releases the underlying buffer for reuse.
+    pub fn into_buffer(self) -> Vec<usize> {
+        self.candidates
+    }
+
     pub fn insert(&mut self, idx: usize) {
         if self.blacklist.contains(idx) {
             return;
@@ -39,6 +77,24 @@ impl Candidates {
         }
     }
 
+    /// Inserts all possible candidates which are not contained in `excluded`.
+    pub fn fill_except(&mut self, excluded: &FixedBitSet) {
+        for i in 0..self.size {
+            if !excluded.contains(i) {
+                self.insert(i);
+            }
+        }
+    }
+
+    /// Number of candidates left to pop.
+    pub fn len(&self) -> usize {
+        self.n
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.n == 0
+    }
+
     pub fn pop(&mut self) -> Option<usize> {
         if self.n == 0 {
             None
@@ -55,18 +111,142 @@ impl Candidates {
     }
 }
 
+/// The buffers of a census, allocated once and reused by every census of a
+/// loop through `Gtrie::census_with_scratch`.
+pub struct CensusScratch {
+    pub(crate) used: Vec<usize>,
+    pub(crate) candidates: Candidates,
+    pub(crate) blacklist: FixedBitSet,
+}
+impl CensusScratch {
+    /// Allocates the buffers for graphs of `n_nodes` vertices.
+    pub fn new(n_nodes: usize) -> Self {
+        CensusScratch {
+            used: Vec::new(),
+            candidates: Candidates::new(n_nodes),
+            blacklist: FixedBitSet::with_capacity(n_nodes),
+        }
+    }
+
+    /// Allocates the buffers for a census of graphs of `n_nodes` vertices that
+    /// ignores the subgraphs containing an `excluded` vertex.
+    pub(crate) fn excluding(n_nodes: usize, excluded: &FixedBitSet) -> Self {
+        let mut scratch = Self::new(n_nodes);
+        scratch.blacklist.union_with(excluded);
+        scratch
+    }
+
+    /// Empties the buffers for a census of a graph of `n_nodes` vertices,
+    /// reallocating them only if the graph has a different size.
+    pub(crate) fn prepare(&mut self, n_nodes: usize) {
+        self.used.clear();
+        if self.candidates.size == n_nodes {
+            self.candidates.clear();
+            self.blacklist.clear();
+        } else {
+            self.candidates = Candidates::new(n_nodes);
+            self.blacklist = FixedBitSet::with_capacity(n_nodes);
+        }
+    }
+}
+
 /*
  * Conditionally match a child node.
  * This is used for the census of the graph space.
  * The condition is that the node must be a graph.
 */
 
-pub fn match_child_conditionally(
+pub fn match_child_conditionally<G: Neighborhood>(
+    node: &mut GtrieNode,
+    used: &mut Vec<usize>,
+    candidates: &mut Candidates,
+    blacklist: &mut FixedBitSet,
+    graph: &G,
+    total_subgraphs: &mut usize,
+) {
+    if !used_respects_conditions(used, node.conditions()) {
+        return;
+    }
+
+    let vertices = matching_vertices_conditionally(node, used, graph, candidates, blacklist);
+    for v in vertices {
+        extend_conditionally(node, v, used, candidates, blacklist, graph, total_subgraphs);
+    }
+}
+
+/// Places `v` at the position of the node and counts every occurrence that
+/// extends the resulting partial match.
+///
+/// `v` must already be a valid match for the node, such as any vertex for a
+/// child of the root.
+pub fn extend_conditionally<G: Neighborhood>(
+    node: &mut GtrieNode,
+    v: usize,
+    used: &mut Vec<usize>,
+    candidates: &mut Candidates,
+    blacklist: &mut FixedBitSet,
+    graph: &G,
+    total_subgraphs: &mut usize,
+) {
+    used.push(v);
+    blacklist.insert(v);
+    if node.counts_occurrence(used) {
+        node.increment_frequency();
+        *total_subgraphs += 1;
+    }
+
+    // Graph nodes of tries holding several sizes continue into larger motifs.
+    for c in node.iter_children_mut() {
+        match_child_conditionally(c, used, candidates, blacklist, graph, total_subgraphs);
+    }
+    used.pop();
+    blacklist.set(v, false);
+}
+
+/// Equivalent to `match_child_conditionally` but also counts the trie nodes
+/// visited and the candidate vertices tested into `report`.
+///
+/// Kept separate so that the plain census pays nothing for the counters.
+pub fn match_child_reporting<G: Neighborhood>(
+    node: &mut GtrieNode,
+    used: &mut Vec<usize>,
+    candidates: &mut Candidates,
+    blacklist: &mut FixedBitSet,
+    graph: &G,
+    report: &mut CensusReport,
+) {
+    report.nodes_visited += 1;
+    if !used_respects_conditions(used, node.conditions()) {
+        return;
+    }
+
+    let pivot = build_candidates_conditionally(node, graph, used, candidates, blacklist);
+    report.candidates_tested += candidates.len();
+    let vertices = build_vertices(node, used, graph, candidates, pivot);
+    for v in vertices {
+        used.push(v);
+        blacklist.insert(v);
+        if node.counts_occurrence(used) {
+            node.increment_frequency();
+            report.total_subgraphs += 1;
+        }
+        for c in node.iter_children_mut() {
+            match_child_reporting(c, used, candidates, blacklist, graph, report);
+        }
+        used.pop();
+        blacklist.set(v, false);
+    }
+}
+
+/// Equivalent to `match_child_conditionally` but only extends the partial
+/// matches kept by the draws of `sampler`, which records every occurrence found.
+pub fn match_child_sampled<G: Neighborhood>(
     node: &mut GtrieNode,
     used: &mut Vec<usize>,
     candidates: &mut Candidates,
     blacklist: &mut FixedBitSet,
-    graph: &Bitgraph,
+    graph: &G,
+    sampler: &mut Sampler,
     total_subgraphs: &mut usize,
 ) {
     if !used_respects_conditions(used, node.conditions()) {
@@ -75,42 +255,102 @@ pub fn match_child_conditionally(
 
     let vertices = matching_vertices_conditionally(node, used, graph, candidates, blacklist);
     for v in vertices {
+        if !sampler.keep(node.depth()) {
+            continue;
+        }
         used.push(v);
         blacklist.insert(v);
-        if node.is_graph() {
+        if node.counts_occurrence(used) {
             node.increment_frequency();
             *total_subgraphs += 1;
-        } else {
-            for c in node.iter_children_mut() {
-                match_child_conditionally(c, used, candidates, blacklist, graph, total_subgraphs);
+            if let Some(repr) = node.repr() {
+                sampler.record(repr, node.depth());
             }
         }
+        for c in node.iter_children_mut() {
+            match_child_sampled(
+                c,
+                used,
+                candidates,
+                blacklist,
+                graph,
+                sampler,
+                total_subgraphs,
+            );
+        }
         used.pop();
         blacklist.set(v, false);
     }
 }
 
-pub fn matching_vertices_conditionally(
+/// Conditionally match a child node without mutating the trie.
+///
+/// Equivalent to `match_child_conditionally` but instead of incrementing the
+/// frequency of the matched graph nodes, each match is reported to `on_match`
+/// along with the vertices forming the occurrence.
+pub fn match_child_shared<G: Neighborhood, F: FnMut(&GtrieNode, &[usize])>(
+    node: &GtrieNode,
+    used: &mut Vec<usize>,
+    candidates: &mut Candidates,
+    blacklist: &mut FixedBitSet,
+    graph: &G,
+    on_match: &mut F,
+) {
+    if !used_respects_conditions(used, node.conditions()) {
+        return;
+    }
+
+    let vertices = matching_vertices_conditionally(node, used, graph, candidates, blacklist);
+    for v in vertices {
+        extend_shared(node, v, used, candidates, blacklist, graph, on_match);
+    }
+}
+
+/// Equivalent to `extend_conditionally` but reports each match to `on_match`
+/// without mutating the trie.
+pub fn extend_shared<G: Neighborhood, F: FnMut(&GtrieNode, &[usize])>(
+    node: &GtrieNode,
+    v: usize,
+    used: &mut Vec<usize>,
+    candidates: &mut Candidates,
+    blacklist: &mut FixedBitSet,
+    graph: &G,
+    on_match: &mut F,
+) {
+    used.push(v);
+    blacklist.insert(v);
+    if node.counts_occurrence(used) {
+        on_match(node, used);
+    }
+    for c in node.iter_children() {
+        match_child_shared(c, used, candidates, blacklist, graph, on_match);
+    }
+    used.pop();
+    blacklist.set(v, false);
+}
+
+pub fn matching_vertices_conditionally<G: Neighborhood>(
     node: &GtrieNode,
     used: &[usize],
-    graph: &Bitgraph,
+    graph: &G,
     candidates: &mut Candidates,
     blacklist: &mut FixedBitSet,
 ) -> Vec<usize> {
-    build_candidates_conditionally(node, graph, used, candidates, blacklist);
+    let pivot = build_candidates_conditionally(node, graph, used, candidates, blacklist);
 
-    build_vertices(node, used, graph, candidates)
+    build_vertices(node, used, graph, candidates, pivot)
 }
 
-fn build_vertices(
+fn build_vertices<G: Neighborhood>(
     node: &GtrieNode,
     used: &[usize],
-    graph: &Bitgraph,
+    graph: &G,
     candidates: &mut Candidates,
+    pivot: Option<usize>,
 ) -> Vec<usize> {
     let mut vertices = Vec::new();
     while let Some(v) = candidates.pop() {
-        if matches_structure(node, graph, used, v) {
+        if matches_structure_from_pivot(node, graph, used, v, pivot) {
             vertices.push(v);
         }
     }
@@ -118,18 +358,25 @@ fn build_vertices(
     vertices
 }
 
-fn build_candidates_conditionally(
+/// Fills `candidates` with the vertices that may extend `used` at the node.
+///
+/// Returns the position in `used` of the pivot whose neighbors were taken as
+/// candidates, if any.
+fn build_candidates_conditionally<G: Neighborhood>(
     node: &GtrieNode,
-    graph: &Bitgraph,
+    graph: &G,
     used: &[usize],
     candidates: &mut Candidates,
     blacklist: &mut FixedBitSet,
-) {
+) -> Option<usize> {
     let label_min = minimal_possible_index(used, node.conditions());
     if used.is_empty() {
-        candidates.fill();
+        // Vertices blacklisted before the census began are excluded from it.
+        candidates.fill_except(blacklist);
+        None
     } else {
-        let min_v = identify_minimal_connection(node, graph, used);
+        let pivot = identify_minimal_connection(node, graph, used);
+        let min_v = used[pivot];
 
         // Select all vertices that have a connection to the vertex with the
         // least number of neighbors which are not already in the used list.
@@ -140,11 +387,12 @@ fn build_candidates_conditionally(
             .for_each(|n| {
                 candidates.insert(*n);
             });
+        Some(pivot)
     }
 }
 
 /// Checks if all orbit-fixing conditions of the GtrieNode are respected by the used vertices.
-fn used_respects_conditions(used: &[usize], conditions: Option<&Conditions>) -> bool {
+pub fn used_respects_conditions(used: &[usize], conditions: Option<&Conditions>) -> bool {
     if let Some(conditions) = conditions {
         for i in 0..used.len() {
             for j in i + 1..used.len() {
@@ -159,7 +407,7 @@ fn used_respects_conditions(used: &[usize], conditions: Option<&Conditions>) ->
 
 /// Identify the minimal possible index for the next vertex in the GtrieNode based on the
 /// conditions of that GtrieNode.
-fn minimal_possible_index(used: &[usize], conditions: Option<&Conditions>) -> usize {
+pub fn minimal_possible_index(used: &[usize], conditions: Option<&Conditions>) -> usize {
     if let Some(conditions) = conditions {
         let k = used.len();
         conditions
@@ -171,37 +419,231 @@ fn minimal_possible_index(used: &[usize], conditions: Option<&Conditions>) -> us
     }
 }
 
-/// Identify the internal vertex with the least number of connections
-/// that is expected to have a connection to the next vertex in the GtrieNode.
-fn identify_minimal_connection(node: &GtrieNode, graph: &Bitgraph, used: &[usize]) -> usize {
-    let (min_v, _min_n) = node
+/// Identify the position in `used` of the internal vertex with the least number
+/// of connections that is expected to have a connection to the next vertex in
+/// the GtrieNode.
+fn identify_minimal_connection<G: Neighborhood>(
+    node: &GtrieNode,
+    graph: &G,
+    used: &[usize],
+) -> usize {
+    let (min_i, _min_n) = node
         .active_nodes()
-        .map(|i| used[*i])
-        .map(|v| (v, graph.neighbors(v).len()))
-        .fold((usize::MAX, usize::MAX), |(min_v, min_n), (v, n)| {
+        .map(|i| (*i, graph.neighbors(used[*i]).len()))
+        .fold((usize::MAX, usize::MAX), |(min_i, min_n), (i, n)| {
             if n < min_n {
-                (v, n)
+                (i, n)
             } else {
-                (min_v, min_n)
+                (min_i, min_n)
             }
         });
-    min_v
+    min_i
 }
 
 /// Check if the vertex v matches the structure of the GtrieNode.
-fn matches_structure(node: &GtrieNode, graph: &Bitgraph, used: &[usize], v: usize) -> bool {
-    used.iter().enumerate().all(|(i, u)| {
-        *u != v
-            && node.out_contains(i) == graph.is_connected(*u, v)
-            && node.in_contains(i) == graph.is_connected(v, *u)
+pub fn matches_structure<G: Neighborhood>(
+    node: &GtrieNode,
+    graph: &G,
+    used: &[usize],
+    v: usize,
+) -> bool {
+    used.iter()
+        .enumerate()
+        .all(|(i, u)| matches_position(node, graph, i, *u, v))
+}
+
+/// Equivalent to `matches_structure` but checks the vertex at position `pivot`
+/// of `used` first, returning on the first mismatch.
+///
+/// Candidates are neighbors of the pivot in either direction, so the pivot
+/// check is not implied: it still rejects the candidates connected the wrong
+/// way, more often than the checks of the other positions.
+fn matches_structure_from_pivot<G: Neighborhood>(
+    node: &GtrieNode,
+    graph: &G,
+    used: &[usize],
+    v: usize,
+    pivot: Option<usize>,
+) -> bool {
+    pivot.is_none_or(|i| matches_position(node, graph, i, used[i], v))
+        && used
+            .iter()
+            .enumerate()
+            .filter(|(i, _)| Some(*i) != pivot)
+            .all(|(i, u)| matches_position(node, graph, i, *u, v))
+}
+
+/// Check if the edges between the vertex v and the vertex u at position i of
+/// `used` match those of the GtrieNode, and are admitted by the graph.
+#[inline]
+fn matches_position<G: Neighborhood>(
+    node: &GtrieNode,
+    graph: &G,
+    i: usize,
+    u: usize,
+    v: usize,
+) -> bool {
+    u != v
+        && node.out_contains(i) == graph.is_connected(u, v)
+        && node.in_contains(i) == graph.is_connected(v, u)
+        && (!node.out_contains(i) || graph.admits_edge(u, v))
+        && (!node.in_contains(i) || graph.admits_edge(v, u))
+}
+
+/// Identifies the first structural check of `matches_structure` that the vertex v fails.
+///
+/// Returns the position in `used` and the edge that was expected (or not) as `(src, dst, expected)`.
+pub(crate) fn structure_mismatch<G: Neighborhood>(
+    node: &GtrieNode,
+    graph: &G,
+    used: &[usize],
+    v: usize,
+) -> Option<(usize, usize, usize, bool)> {
+    used.iter().enumerate().find_map(|(i, u)| {
+        if node.out_contains(i) != graph.is_connected(*u, v) {
+            Some((i, *u, v, node.out_contains(i)))
+        } else if node.in_contains(i) != graph.is_connected(v, *u) {
+            Some((i, v, *u, node.in_contains(i)))
+        } else {
+            None
+        }
     })
 }
 
+/// Identifies the first orbit-fixing condition of the GtrieNode violated by the used vertices.
+pub(crate) fn violated_condition(
+    used: &[usize],
+    conditions: Option<&Conditions>,
+) -> Option<Condition> {
+    conditions?
+        .iter()
+        .filter(|c| c.max() < used.len())
+        .find(|c| used[c.min()] >= used[c.max()])
+        .copied()
+}
+
 #[cfg(test)]
 mod testing {
 
     use super::*;
-    use crate::symmetry::Condition;
+    use crate::{bitgraph::Bitgraph, gtrie::Gtrie};
+
+    #[test]
+    fn candidates_lifecycle() {
+        let mut candidates = Candidates::from_buffer(vec![7; 4]);
+        assert_eq!(candidates.pop(), None);
+
+        candidates.insert(2);
+        candidates.insert(2);
+        candidates.insert(0);
+        assert_eq!(candidates.pop(), Some(0));
+        assert_eq!(candidates.pop(), Some(2));
+        assert_eq!(candidates.pop(), None);
+
+        // Popped candidates remain blacklisted until cleared.
+        candidates.insert(2);
+        assert_eq!(candidates.pop(), None);
+        candidates.clear();
+        candidates.fill();
+        assert_eq!(candidates.pop(), Some(3));
+        assert_eq!(candidates.into_buffer().len(), 4);
+    }
+
+    #[test]
+    fn custom_traversal_matches_census() {
+        let graph =
+            crate::io::load_numeric_graph("example/graphs/example.txt", true, false).unwrap();
+        let graph = Bitgraph::from_graph(&graph);
+        let mut gtrie = Gtrie::read_from_file("example/gtries/dir4.gt").unwrap();
+
+        let mut used = Vec::new();
+        let mut candidates = Candidates::from_buffer(vec![0; graph.n_nodes()]);
+        let mut blacklist = FixedBitSet::with_capacity(graph.n_nodes());
+        let mut total = 0;
+        for child in gtrie.root().iter_children() {
+            match_child_shared(
+                child,
+                &mut used,
+                &mut candidates,
+                &mut blacklist,
+                &graph,
+                &mut |_, _| total += 1,
+            );
+        }
+
+        gtrie.census(&graph);
+        assert_eq!(total, gtrie.total_subgraphs());
+    }
+
+    /// A graph holding only its neighbor lists and a set of its edges.
+    struct EdgeSetGraph {
+        neighbors: Vec<Vec<usize>>,
+        edges: hashbrown::HashSet<(usize, usize)>,
+    }
+    impl Neighborhood for EdgeSetGraph {
+        fn is_connected(&self, u: usize, v: usize) -> bool {
+            self.edges.contains(&(u, v))
+        }
+
+        fn neighbors(&self, u: usize) -> &[usize] {
+            &self.neighbors[u]
+        }
+
+        fn n_nodes(&self) -> usize {
+            self.neighbors.len()
+        }
+    }
+
+    #[test]
+    fn census_of_another_graph_representation() {
+        let graph =
+            crate::io::load_numeric_graph("example/graphs/example.txt", true, false).unwrap();
+        let bitgraph = Bitgraph::from_graph(&graph);
+        let edge_set = EdgeSetGraph {
+            neighbors: (0..bitgraph.n_nodes())
+                .map(|u| bitgraph.neighbors(u).to_vec())
+                .collect(),
+            edges: (0..bitgraph.n_nodes())
+                .flat_map(|u| (0..bitgraph.n_nodes()).map(move |v| (u, v)))
+                .filter(|(u, v)| bitgraph.is_connected(*u, *v))
+                .collect(),
+        };
+        let mut expected = Gtrie::read_from_file("example/gtries/dir4.gt").unwrap();
+        expected.census(&bitgraph);
+        let mut gtrie = Gtrie::read_from_file("example/gtries/dir4.gt").unwrap();
+        gtrie.census(&edge_set);
+        assert!(gtrie.total_subgraphs() > 0);
+        assert_eq!(gtrie.total_subgraphs(), expected.total_subgraphs());
+        assert_eq!(gtrie.get_nonzero(), expected.get_nonzero());
+    }
+
+    #[test]
+    fn pivot_order_matches_structure() {
+        let graph =
+            crate::io::load_numeric_graph("example/graphs/example.txt", true, false).unwrap();
+        let graph = Bitgraph::from_graph(&graph);
+        let gtrie = Gtrie::read_from_file("example/gtries/dir3.gt").unwrap();
+        let n = graph.n_nodes();
+
+        for node in gtrie
+            .root()
+            .iter_children()
+            .flat_map(|c| c.iter_children())
+            .flat_map(|c| c.iter_children())
+        {
+            for used in (0..n).flat_map(|a| (0..n).map(move |b| [a, b])) {
+                for v in 0..n {
+                    let expected = matches_structure(node, &graph, &used, v);
+                    for pivot in [None, Some(0), Some(1)] {
+                        assert_eq!(
+                            matches_structure_from_pivot(node, &graph, &used, v, pivot),
+                            expected
+                        );
+                    }
+                }
+            }
+        }
+    }
 
     #[test]
     fn conditions_used_positive_a() {
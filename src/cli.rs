@@ -1,4 +1,4 @@
-use clap::{Parser, Subcommand};
+use clap::{Parser, Subcommand, ValueEnum};
 
 #[derive(Parser, Debug)]
 pub struct Cli {
@@ -6,6 +6,8 @@ pub struct Cli {
     pub mode: Mode,
 }
 
+// Parsed once per invocation, so the size of the largest variant is irrelevant.
+#[allow(clippy::large_enum_variant)]
 #[derive(Subcommand, Debug)]
 pub enum Mode {
     /// Enumerate all subgraphs of a given size given a graph and a gtrie.
@@ -14,14 +16,307 @@ pub enum Mode {
         #[arg(short, long)]
         gtrie: String,
 
+        /// Path to the input graph, or `-` to read it from stdin. Given several
+        /// graphs, the frequencies are summed across all of them.
+        #[arg(short, long, num_args = 1.., required = true)]
+        input: Vec<String>,
+
+        /// Explain why a set of vertices (1-indexed, comma separated) is or
+        /// isn't counted instead of running the census.
+        #[arg(long, value_delimiter = ',')]
+        explain: Option<Vec<usize>>,
+
+        /// Only count subgraphs containing the edge `a -> b` (1-indexed, comma separated).
+        #[arg(long, value_delimiter = ',', conflicts_with = "timeout")]
+        require_edge: Option<Vec<usize>>,
+
+        /// Path to a file of vertex attributes (1-indexed vertex and value per
+        /// line). Only vertices with an attribute of at least `--min-attr` are
+        /// counted.
+        #[arg(long, requires = "min_attr", conflicts_with_all = ["timeout", "require_edge"])]
+        vertex_filter: Option<String>,
+
+        /// Minimum attribute of the vertices kept by `--vertex-filter`.
+        #[arg(long, requires = "vertex_filter")]
+        min_attr: Option<f64>,
+
+        /// Format of the input graph.
+        #[arg(long, value_enum, default_value = "edgelist")]
+        input_format: InputFormat,
+
+        /// Ignore the first N data lines of an edgelist input, e.g. a header
+        /// row. Comment lines starting with `#` are always skipped.
+        #[arg(long, default_value_t = 0, conflicts_with_all = ["input_format", "undirected_types"])]
+        skip_header: usize,
+
+        /// Read the vertices of an edgelist or parquet input as 0-indexed. A
+        /// 1-indexed file read this way has every vertex shifted by one.
+        #[arg(long, conflicts_with = "undirected_types")]
+        zero_indexed: bool,
+
+        /// Column of the source vertices of a parquet input.
+        #[arg(long, default_value = "src")]
+        src_col: String,
+
+        /// Column of the target vertices of a parquet input.
+        #[arg(long, default_value = "dst")]
+        dst_col: String,
+
+        /// Read the type of each edge from a third column of the input graph and
+        /// treat the edges of these types (comma separated) as undirected.
+        #[arg(long, value_delimiter = ',', conflicts_with = "input_format")]
+        undirected_types: Option<Vec<String>>,
+
+        /// Count undirected subgraphs if every edge of the input graph is reciprocated.
+        #[arg(long)]
+        auto_undirected: bool,
+
+        /// Treat every edge of the input graph as undirected and count
+        /// undirected subgraphs.
+        #[arg(long, conflicts_with_all = ["auto_undirected", "undirected_types"])]
+        undirected: bool,
+
+        /// Only count subgraphs within the k-core (by total degree) of the input graph.
+        #[arg(long)]
+        kcore: Option<usize>,
+
+        /// Abort the census after this many seconds, reporting partial counts.
+        /// Partial counts are headed by a `# timed_out: true` line, and the
+        /// JSON format becomes an object of the `results` and whether the
+        /// census `timed_out`.
+        #[arg(long)]
+        timeout: Option<f64>,
+
+        /// Only count subgraphs within `--hops` of this vertex (1-indexed).
+        #[arg(long)]
+        ego: Option<usize>,
+
+        /// Radius of the neighborhood kept by `--ego`, following edges in either direction.
+        #[arg(long, default_value = "1", requires = "ego")]
+        hops: usize,
+
+        /// Also report the number of embeddings of each motif (its frequency
+        /// times the size of its automorphism group), as counted by tools like FANMOD.
+        #[arg(long, conflicts_with_all = ["by_size", "format"])]
+        embeddings: bool,
+
+        /// Report the motifs of a mixed-size gtrie in a section per size, each
+        /// with its total and its motifs by decreasing frequency.
+        #[arg(long, conflicts_with = "format")]
+        by_size: bool,
+
+        /// Head the results with `#` comment lines recording the version,
+        /// command line and gtrie hash used, or add them as a `provenance`
+        /// object to the JSON format.
+        #[arg(long)]
+        provenance: bool,
+
+        /// Only count the subgraphs starting at the `i`-th of `n` contiguous
+        /// ranges of vertices, given as `i/n` (1-indexed). The counts of the
+        /// `n` shards add up to the full census.
+        #[arg(long, conflicts_with_all = ["require_edge", "timeout", "vertex_filter"])]
+        shard: Option<String>,
+
+        /// Write the canonical graph6 representation of every subgraph found to
+        /// this file, one per line. The file holds one line per occurrence.
+        #[arg(long, conflicts_with_all = ["require_edge", "timeout", "shard"])]
+        dump_subgraphs: Option<String>,
+
+        /// Write every occurrence found to this file, one per line: the repr
+        /// of its motif, its 0-indexed vertices and the orbit of each of their
+        /// positions (comma separated, `-` for a gtrie without orbits), tab
+        /// separated. Vertices sharing an orbit play the same role in the motif.
+        #[arg(
+            long,
+            conflicts_with_all = [
+                "require_edge", "timeout", "vertex_filter", "shard", "dump_subgraphs",
+                "sample", "breakdown_by_color", "threads",
+            ]
+        )]
+        dump_occurrences: Option<String>,
+
+        /// Write the number of occurrences of each motif that each vertex takes
+        /// part in to this file, as a TSV matrix with a row per vertex, 1-indexed
+        /// unless `--zero-indexed`.
+        #[arg(
+            long,
+            conflicts_with_all = [
+                "require_edge", "timeout", "vertex_filter", "shard", "dump_subgraphs",
+                "sample", "breakdown_by_color", "threads", "dump_occurrences",
+            ]
+        )]
+        participation: Option<String>,
+
+        /// Sample the census, keeping each vertex matched at depth `d` with the
+        /// `d`-th of these probabilities (comma separated, one per depth). The
+        /// estimated frequency of each motif is reported with a 95% confidence
+        /// interval.
+        #[arg(
+            long,
+            visible_alias = "sample-probs",
+            value_delimiter = ',',
+            conflicts_with_all = [
+                "require_edge", "timeout", "vertex_filter", "shard", "dump_subgraphs",
+                "embeddings", "by_size", "format", "adjacency_format",
+            ]
+        )]
+        sample: Option<Vec<f64>>,
+
+        /// Seed of the random number generator used by `--sample`.
+        #[arg(long, default_value = "0", requires = "sample")]
+        seed: u64,
+
+        /// Only count the subgraphs whose edges all have a weight of at least
+        /// this, read from an optional third column of the input graph (edges
+        /// without one have a weight of 1).
+        #[arg(
+            long,
+            conflicts_with_all = [
+                "require_edge", "timeout", "shard", "sample", "breakdown_by_color", "threads",
+                "dump_occurrences", "participation",
+            ]
+        )]
+        min_edge_weight: Option<f64>,
+
+        /// Show a progress bar on stderr, ticking once per starting vertex.
+        #[arg(
+            long,
+            conflicts_with_all = [
+                "require_edge", "timeout", "vertex_filter", "shard", "sample", "breakdown_by_color",
+                "threads", "dump_occurrences", "min_edge_weight", "participation",
+            ]
+        )]
+        progress: bool,
+
+        /// Run the census on this many threads.
+        #[arg(
+            long,
+            conflicts_with_all = [
+                "require_edge", "timeout", "vertex_filter", "shard", "sample", "breakdown_by_color",
+            ]
+        )]
+        threads: Option<usize>,
+
+        /// Path to a file of vertex colors (1-indexed vertex and color per
+        /// line), such as the type of each vertex.
+        #[arg(long, requires = "breakdown_by_color")]
+        colors: Option<String>,
+
+        /// Break the count of each motif down by the colors of its vertices,
+        /// ordered as the vertices of its repr and up to its symmetries.
+        #[arg(
+            long,
+            requires = "colors",
+            conflicts_with_all = [
+                "require_edge", "timeout", "vertex_filter", "shard", "dump_subgraphs", "sample",
+                "embeddings", "by_size", "format", "adjacency_format",
+            ]
+        )]
+        breakdown_by_color: bool,
+
+        /// Layout of the results.
+        #[arg(
+            long,
+            visible_alias = "output-format",
+            value_enum,
+            default_value = "plain"
+        )]
+        format: OutputFormat,
+
+        /// Order of the motifs in the plain results.
+        #[arg(
+            long,
+            value_enum,
+            default_value = "none",
+            conflicts_with_all = ["embeddings", "by_size", "format", "adjacency_format"]
+        )]
+        sort: ResultOrder,
+
+        /// Add a column with the adjacency matrix of each motif as a bit string.
+        #[arg(long, value_enum, conflicts_with_all = ["embeddings", "by_size", "format"])]
+        adjacency_format: Option<AdjacencyFormat>,
+    },
+
+    /// Compare the subgraph census of two graphs.
+    Compare {
+        /// Path to the gtrie-formatted file (created with `build`).
+        #[arg(short, long)]
+        gtrie: String,
+
+        /// Path to the first input graph.
+        #[arg(short = 'a', long)]
+        input_a: String,
+
+        /// Path to the second input graph.
+        #[arg(short = 'b', long)]
+        input_b: String,
+    },
+
+    /// Compute the significance of each motif against randomized graphs.
+    Significance {
+        /// Path to the gtrie-formatted file (created with `build`).
+        #[arg(short, long)]
+        gtrie: String,
+
         /// Path to the input graph.
         #[arg(short, long)]
         input: String,
+
+        /// Number of randomized graphs to generate.
+        #[arg(short, long, default_value = "1000")]
+        num_random: usize,
+
+        /// Null model used to generate the randomized graphs.
+        #[arg(long, value_enum, default_value = "swap")]
+        null_model: NullModelKind,
+
+        /// Number of edge swaps per edge made by the `swap` null model.
+        #[arg(long, default_value_t = graphtries::significance::DEFAULT_SWAPS_PER_EDGE)]
+        swaps_per_edge: usize,
+
+        /// Seed of the random number generator. Each randomized graph uses a
+        /// generator derived from it, so results do not depend on `--jobs`.
+        #[arg(long, default_value = "0")]
+        seed: u64,
+
+        /// Number of randomized graphs to generate and census concurrently.
+        #[arg(short, long, default_value = "1")]
+        jobs: usize,
+
+        /// Head the results with `#` comment lines recording the version,
+        /// command line, gtrie hash and seed used.
+        #[arg(long)]
+        provenance: bool,
+
+        /// Layout of the results.
+        #[arg(long, value_enum, default_value = "table")]
+        format: SignificanceFormat,
+    },
+
+    /// Enumerate all subgraphs of many input graphs and report a motif by graph matrix.
+    Batch {
+        /// Path to the gtrie-formatted file (created with `build`).
+        #[arg(short, long)]
+        gtrie: String,
+
+        /// Paths to the input graphs.
+        #[arg(short, long, num_args = 1.., required = true)]
+        input: Vec<String>,
+
+        /// Number of input graphs to process concurrently.
+        #[arg(short, long, default_value = "1")]
+        jobs: usize,
+
+        /// Abort the census of any graph after this many seconds, reporting
+        /// `NA` for its counts.
+        #[arg(long)]
+        timeout: Option<f64>,
     },
 
     /// Build a gtrie from a list of graphs.
     Build {
-        /// Path to the input file containing the graph6 formatted graphs
+        /// Path to the input file containing the graph6 formatted graphs, or `-`
+        /// to read them from stdin.
         #[arg(short, long)]
         input: String,
 
@@ -33,14 +328,323 @@ pub enum Mode {
         #[arg(short, long)]
         size: usize,
 
-        /// Visualize the gtrie.
+        /// Visualize the gtrie, showing the canonical label of each motif.
+        #[arg(short, long)]
+        visualize: bool,
+
+        /// Skip malformed graph6 lines (reported to stderr with their line
+        /// number) instead of aborting.
+        #[arg(long, visible_alias = "skip-errors")]
+        skip_bad: bool,
+
+        /// Only insert motifs with the given structural property.
+        #[arg(long, value_enum)]
+        motif_filter: Option<MotifFilter>,
+
+        /// Report the number of distinct motifs and the motifs given more than
+        /// once in the input after building.
+        #[arg(long)]
+        dedup_summary: bool,
+
+        /// When the canonical labeling excludes articulation points. Modes other
+        /// than `auto` change the canonical labels and so the structure of the
+        /// gtrie; meant for research on canonicalization only.
+        #[arg(
+            long,
+            value_enum,
+            default_value = "auto",
+            conflicts_with = "assume_canonical"
+        )]
+        ap_mode: ApModeKind,
+
+        /// Insert the input graphs as given, skipping canonicalization. The
+        /// input must already be in canonical form: isomorphic graphs in
+        /// different labelings become redundant motifs of the gtrie.
+        #[arg(long)]
+        assume_canonical: bool,
+
+        /// Treat a motif and its reversal (all edges flipped) as the same
+        /// motif: both orientations are inserted under the smaller of their
+        /// graph6 representations, so the census counts them together.
+        #[arg(long, conflicts_with = "assume_canonical")]
+        merge_reversals: bool,
+
+        /// Build a gtrie of undirected motifs. Every input graph must be
+        /// undirected (or have every edge reciprocated).
+        #[arg(long, conflicts_with_all = ["assume_canonical", "merge_reversals"])]
+        undirected: bool,
+
+        /// Serialization format of the output, chosen from its extension by
+        /// default: `.json` is JSON and anything else is MessagePack.
+        #[arg(long, value_enum)]
+        format: Option<GtrieFormatKind>,
+
+        /// Fail on a disconnected input graph instead of skipping it with a
+        /// warning. Disconnected motifs are never matched by the census.
+        #[arg(long)]
+        strict: bool,
+
+        /// Do not cache canonical labels. By default they are read from and
+        /// written to `<output>.canon`, so that rebuilding from overlapping
+        /// inputs skips recomputing them.
+        #[arg(long)]
+        no_cache: bool,
+
+        /// Report the number of nodes, graph nodes and depth of the gtrie and
+        /// the time taken to build it.
+        #[arg(long)]
+        stats: bool,
+    },
+
+    /// Merge gtries of the same size, such as partial gtries built separately.
+    Merge {
+        /// Paths to the gtries to merge.
+        #[arg(short, long, num_args = 1.., required = true)]
+        input: Vec<String>,
+
+        /// Path to the output file where the merged gtrie will be written.
+        #[arg(short, long)]
+        output: String,
+    },
+
+    /// Build a gtrie of all connected non-isomorphic directed motifs of a
+    /// size or range of sizes.
+    Generate {
+        /// Path to the output file where gtrie will be written.
+        #[arg(short, long)]
+        output: String,
+
+        /// Size of the motifs to generate.
+        #[arg(short, long, conflicts_with_all = ["min_size", "max_size"])]
+        size: Option<usize>,
+
+        /// Smallest size of the motifs to generate [default: 3].
+        #[arg(long)]
+        min_size: Option<usize>,
+
+        /// Largest size of the motifs to generate [default: min-size].
+        #[arg(long)]
+        max_size: Option<usize>,
+
+        /// Visualize the gtrie, showing the canonical label of each motif.
         #[arg(short, long)]
         visualize: bool,
+
+        /// Insert motifs as they are generated instead of holding all sizes
+        /// in memory first.
+        #[arg(long)]
+        low_memory: bool,
+    },
+
+    /// Print the canonical form of a single motif as it would be inserted into a gtrie.
+    Canon {
+        /// Path to the motif, either as a numeric edge list or a single graph6 line.
+        #[arg(short, long)]
+        input: String,
+    },
+
+    /// Sample up to `k` occurrences of every motif, optionally weighted by the
+    /// product of their edge weights.
+    SampleOccurrences {
+        /// Path to the gtrie-formatted file (created with `build`).
+        #[arg(short, long)]
+        gtrie: String,
+
+        /// Path to the input graph.
+        #[arg(short, long)]
+        input: String,
+
+        /// Maximum number of occurrences sampled per motif.
+        #[arg(short, long, default_value = "10")]
+        k: usize,
+
+        /// Path to a 1-indexed `u v w` edge list of weights. Edges not listed
+        /// have a weight of 1. Without it occurrences are sampled uniformly.
+        #[arg(long)]
+        edge_weights: Option<String>,
+
+        /// Seed of the random number generator.
+        #[arg(long, default_value = "0")]
+        seed: u64,
+    },
+
+    /// Check the census of a small graph against a brute force enumeration of
+    /// its vertex subsets.
+    Validate {
+        /// Path to the gtrie-formatted file (created with `build`).
+        #[arg(short, long)]
+        gtrie: String,
+
+        /// Path to the input graph.
+        #[arg(short, long)]
+        input: String,
+    },
+
+    /// Check whether two graphs are isomorphic by comparing their canonical
+    /// forms. Exits with 0 if they are, 1 if they are not and 2 if a graph
+    /// can't be read.
+    Iso {
+        /// Path to the first graph, as a single graph6 line or an edge list.
+        a: String,
+
+        /// Path to the second graph, as a single graph6 line or an edge list.
+        b: String,
+    },
+
+    /// Set the missing reprs of a gtrie's motifs and write the repaired gtrie.
+    Repair {
+        /// Path to the gtrie to repair.
+        #[arg(short, long)]
+        input: String,
+
+        /// Path to write the repaired gtrie to.
+        #[arg(short, long)]
+        output: String,
+    },
+
+    /// Write a single motif of a gtrie as a GraphML document.
+    ExportMotif {
+        /// Path to the gtrie-formatted file (created with `build`).
+        #[arg(short, long)]
+        gtrie: String,
+
+        /// The graph6 representation of the motif.
+        #[arg(short, long)]
+        repr: String,
+
+        /// Path to the output GraphML file.
+        #[arg(short, long)]
+        output: String,
     },
 
     /// Visualize a precalculated gtrie.
     Visualize {
         #[arg(short, long)]
         input: String,
+
+        /// Write the trie as a Graphviz DOT digraph instead of a text tree.
+        #[arg(long)]
+        dot: bool,
     },
 }
+
+#[derive(ValueEnum, Clone, Copy, Debug, Default)]
+pub enum InputFormat {
+    /// A 1-indexed edge list, the number of vertices being the largest index.
+    #[default]
+    #[value(alias = "numeric")]
+    Edgelist,
+
+    /// A 1-indexed edge list following an `n m` header line declaring the
+    /// number of vertices and edges.
+    HeaderEdgelist,
+
+    /// A DIMACS edge file of `p edge n m` and `e u v` lines, with `c` comments.
+    Dimacs,
+
+    /// The first graph6 (or directed graph6) line of the file.
+    Graph6,
+
+    /// A 1-indexed adjacency list: a vertex followed by its out-neighbors per line.
+    Adjlist,
+
+    /// A Parquet edge table with integer source and target columns, read
+    /// when built with the `parquet` feature.
+    Parquet,
+}
+
+#[derive(ValueEnum, Clone, Copy, Debug, Default, PartialEq, Eq)]
+pub enum ResultOrder {
+    /// The traversal order of the gtrie.
+    #[default]
+    None,
+
+    /// Decreasing frequency, ties broken by graph6 representation.
+    Freq,
+
+    /// Graph6 representation.
+    Repr,
+}
+
+#[derive(ValueEnum, Clone, Copy, Debug, Default, PartialEq, Eq)]
+pub enum OutputFormat {
+    /// The graph6 representation and frequency of each motif.
+    #[default]
+    #[value(alias = "pretty")]
+    Plain,
+
+    /// A table with a header and the columns `motif_size`, `graph6`,
+    /// `frequency`, `concentration`, `edges` and `acyclic`, sorted by size
+    /// then decreasing frequency.
+    #[value(alias = "tsv")]
+    LongTsv,
+
+    /// A JSON array of `{"repr", "frequency"}` objects for the motifs found,
+    /// with the canonical graph6 representations of the gtrie.
+    Json,
+}
+
+#[derive(ValueEnum, Clone, Copy, Debug)]
+pub enum AdjacencyFormat {
+    /// The full matrix, row by row.
+    Full,
+
+    /// The upper triangle in graph6 order for undirected motifs, and the full
+    /// matrix for directed motifs.
+    Triangle,
+}
+
+#[derive(ValueEnum, Clone, Copy, Debug, Default)]
+pub enum ApModeKind {
+    /// Always exclude articulation points.
+    Always,
+
+    /// Never exclude articulation points. Motifs whose labeling then has a
+    /// disconnected prefix cannot be censused and are rejected.
+    Never,
+
+    /// Exclude articulation points while more than three vertices remain.
+    #[default]
+    Auto,
+}
+
+#[derive(ValueEnum, Clone, Copy, Debug)]
+pub enum GtrieFormatKind {
+    /// Compact binary MessagePack.
+    Msgpack,
+
+    /// Human-readable JSON.
+    Json,
+}
+
+#[derive(ValueEnum, Clone, Copy, Debug, Default, PartialEq, Eq)]
+pub enum SignificanceFormat {
+    /// The count, null model statistics, z-score and p-value of each motif.
+    #[default]
+    Table,
+
+    /// The significance profile: the z-scores normalized to unit length,
+    /// ordered by graph6 representation to compare graphs of different sizes.
+    Sp,
+}
+
+#[derive(ValueEnum, Clone, Copy, Debug)]
+pub enum NullModelKind {
+    /// Degree-preserving edge swaps.
+    Swap,
+
+    /// Erdős–Rényi graphs with the same number of edges.
+    Er,
+
+    /// Configuration model matching the in and out degree sequence.
+    Config,
+}
+
+#[derive(ValueEnum, Clone, Copy, Debug)]
+pub enum MotifFilter {
+    /// Motifs where every vertex reaches every other vertex.
+    StronglyConnected,
+
+    /// Motifs without directed cycles.
+    Acyclic,
+}
@@ -0,0 +1,145 @@
+use anyhow::{bail, Result};
+use hashbrown::HashMap;
+use std::{
+    fs::File,
+    io::{BufRead, BufReader},
+};
+
+/// A categorical color (e.g. a type) for each vertex of a graph.
+///
+/// Colors are identified by the order in which they first appear.
+#[derive(Debug, Clone, Default)]
+pub struct VertexColors {
+    colors: HashMap<usize, usize>,
+    names: Vec<String>,
+}
+impl VertexColors {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Loads colors from a 1-indexed whitespace delimited `v color` list.
+    pub fn from_file(path: &str) -> Result<Self> {
+        let mut reader = File::open(path).map(BufReader::new)?;
+        Self::from_buffer(&mut reader)
+    }
+
+    /// Loads colors from a buffer, see [`VertexColors::from_file`].
+    pub fn from_buffer<B: BufRead>(buffer: &mut B) -> Result<Self> {
+        let mut colors = Self::new();
+        for (idx, line) in buffer.lines().enumerate() {
+            let line = line?;
+            if line.trim().is_empty() {
+                continue;
+            }
+            let fields = line.split_whitespace().collect::<Vec<_>>();
+            let [v, color] = fields[..] else {
+                bail!("Line {}: expected `v color`, found `{}`", idx + 1, line);
+            };
+            let v = v.parse::<usize>()?;
+            if v == 0 {
+                bail!("ERROR: Found a node index: 0; Please use 1-indexed node indices.");
+            }
+            colors.insert(v - 1, color);
+        }
+        Ok(colors)
+    }
+
+    /// Sets the color of vertex `v`.
+    pub fn insert(&mut self, v: usize, name: &str) {
+        let color = match self.names.iter().position(|x| x == name) {
+            Some(color) => color,
+            None => {
+                self.names.push(name.to_string());
+                self.names.len() - 1
+            }
+        };
+        self.colors.insert(v, color);
+    }
+
+    /// The colors of the vertices of a subgraph, vertex `i` of which is vertex
+    /// `vertices[i]` of the colored graph, see `Bitgraph::k_core`.
+    pub fn restricted(&self, vertices: &[usize]) -> Self {
+        let colors = vertices
+            .iter()
+            .enumerate()
+            .filter_map(|(i, v)| self.colors.get(v).map(|color| (i, *color)))
+            .collect();
+        Self {
+            colors,
+            names: self.names.clone(),
+        }
+    }
+
+    /// Returns the color of vertex `v`, if it has one.
+    pub fn get(&self, v: usize) -> Option<usize> {
+        self.colors.get(&v).copied()
+    }
+
+    /// Returns the name of a color.
+    pub fn name(&self, color: usize) -> &str {
+        &self.names[color]
+    }
+
+    /// The colors of the vertices of an occurrence placed by each of the
+    /// `isomorphisms` (see `isomorphism::isomorphisms`), keeping the smallest
+    /// tuple. Without isomorphisms the colors are in position order.
+    pub fn colors_under(&self, used: &[usize], isomorphisms: &[Vec<usize>]) -> Vec<usize> {
+        let colors = used
+            .iter()
+            .map(|v| self.get(*v).unwrap_or(usize::MAX))
+            .collect::<Vec<_>>();
+        isomorphisms
+            .iter()
+            .map(|perm| perm.iter().map(|i| colors[*i]).collect::<Vec<_>>())
+            .min()
+            .unwrap_or(colors)
+    }
+}
+
+#[cfg(test)]
+mod testing {
+    use super::*;
+
+    #[test]
+    fn vertex_colors_from_buffer() {
+        let mut buffer = "1 TF\n2 gene\n\n3 TF\n".as_bytes();
+        let colors = VertexColors::from_buffer(&mut buffer).unwrap();
+        assert_eq!(colors.get(0), Some(0));
+        assert_eq!(colors.get(1), Some(1));
+        assert_eq!(colors.get(2), Some(0));
+        assert_eq!(colors.get(3), None);
+        assert_eq!(colors.name(1), "gene");
+
+        assert!(VertexColors::from_buffer(&mut "1\n".as_bytes()).is_err());
+        assert!(VertexColors::from_buffer(&mut "0 TF\n".as_bytes()).is_err());
+    }
+
+    #[test]
+    fn restricted_colors() {
+        let colors = VertexColors::from_buffer(&mut "1 A\n2 B\n4 A\n".as_bytes()).unwrap();
+        let restricted = colors.restricted(&[3, 1, 2]);
+        assert_eq!(restricted.get(0), Some(0));
+        assert_eq!(restricted.get(1), Some(1));
+        assert_eq!(restricted.get(2), None);
+        assert_eq!(restricted.name(1), "B");
+    }
+
+    #[test]
+    fn colors_under_isomorphisms() {
+        let colors = VertexColors::from_buffer(&mut "1 A\n2 B\n3 A\n".as_bytes()).unwrap();
+
+        // Position 1 of the occurrence is placed first, then positions 0 and
+        // 2 either way.
+        let isomorphisms = [vec![1, 0, 2], vec![1, 2, 0]];
+        assert_eq!(
+            colors.colors_under(&[0, 1, 2], &isomorphisms),
+            vec![1, 0, 0]
+        );
+        assert_eq!(
+            colors.colors_under(&[0, 2, 1], &isomorphisms),
+            vec![0, 0, 1]
+        );
+        assert_eq!(colors.colors_under(&[1, 0, 2], &[]), vec![1, 0, 0]);
+    }
+}
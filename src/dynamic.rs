@@ -0,0 +1,164 @@
+use crate::{bitgraph::Bitgraph, gtrie::Gtrie};
+use hashbrown::HashMap;
+
+/// Maintains the motif counts of a graph as edges are added and removed.
+///
+/// Only subgraphs containing both endpoints of a changed edge can change their
+/// motif, and all of those lie within `max_depth - 1` hops of either endpoint.
+/// Each update therefore re-censuses only that neighborhood, before and after
+/// the change, and applies the difference to the running counts.
+#[allow(dead_code)]
+pub struct DynamicCensus {
+    gtrie: Gtrie,
+    graph: Bitgraph,
+    counts: HashMap<String, usize>,
+    total_subgraphs: usize,
+}
+#[allow(dead_code)]
+impl DynamicCensus {
+    /// Performs the initial census of the graph.
+    pub fn new(gtrie: Gtrie, graph: Bitgraph) -> Self {
+        let mut counts = HashMap::new();
+        let total_subgraphs = gtrie.census_into(&graph, &mut counts);
+        DynamicCensus {
+            gtrie,
+            graph,
+            counts,
+            total_subgraphs,
+        }
+    }
+
+    /// Adds the edge `u -> v` and updates the counts.
+    /// Returns `false` (leaving the counts untouched) if the edge already exists.
+    pub fn add_edge(&mut self, u: usize, v: usize) -> bool {
+        if self.graph.is_connected(u, v) {
+            return false;
+        }
+        self.update(u, v, |graph| graph.insert_edge(u, v));
+        true
+    }
+
+    /// Removes the edge `u -> v` and updates the counts.
+    /// Returns `false` (leaving the counts untouched) if the edge does not exist.
+    pub fn remove_edge(&mut self, u: usize, v: usize) -> bool {
+        if !self.graph.is_connected(u, v) {
+            return false;
+        }
+        self.update(u, v, |graph| graph.remove_edge(u, v));
+        true
+    }
+
+    /// Replaces the counts of subgraphs containing both `u` and `v` with their
+    /// counts after applying `change` to the graph.
+    fn update<F: FnOnce(&mut Bitgraph) -> bool>(&mut self, u: usize, v: usize, change: F) {
+        let before = self.anchored_counts(u, v);
+        change(&mut self.graph);
+        let after = self.anchored_counts(u, v);
+
+        for (repr, count) in before {
+            let entry = self.counts.get_mut(&repr).unwrap();
+            *entry -= count;
+            self.total_subgraphs -= count;
+            if *entry == 0 {
+                self.counts.remove(&repr);
+            }
+        }
+        for (repr, count) in after {
+            *self.counts.entry(repr).or_insert(0) += count;
+            self.total_subgraphs += count;
+        }
+    }
+
+    /// Counts the motifs of the subgraphs containing both `u` and `v`.
+    fn anchored_counts(&self, u: usize, v: usize) -> HashMap<String, usize> {
+        let mut counts = HashMap::new();
+        if u == v {
+            return counts;
+        }
+
+        let hops = self.gtrie.max_depth().saturating_sub(1);
+        let vertices = self.graph.neighborhood(u, hops);
+        let (Ok(local_u), Ok(local_v)) = (vertices.binary_search(&u), vertices.binary_search(&v))
+        else {
+            return counts;
+        };
+
+        let local = self.graph.induced_subgraph(&vertices);
+        self.gtrie.visit_matches(&local, |node, used| {
+            if used.contains(&local_u) && used.contains(&local_v) {
+                if let Some(repr) = node.repr() {
+                    *counts.entry(repr.to_string()).or_insert(0) += 1;
+                }
+            }
+        });
+        counts
+    }
+
+    /// Returns the current frequency of each nonzero motif keyed by its repr.
+    pub fn counts(&self) -> &HashMap<String, usize> {
+        &self.counts
+    }
+
+    pub fn total_subgraphs(&self) -> usize {
+        self.total_subgraphs
+    }
+
+    pub fn graph(&self) -> &Bitgraph {
+        &self.graph
+    }
+}
+
+#[cfg(test)]
+mod testing {
+    use super::*;
+    use crate::io::load_numeric_graph;
+    use petgraph::{Directed, Graph};
+
+    fn census(gtrie: &Gtrie, graph: &Bitgraph) -> (HashMap<String, usize>, usize) {
+        let mut counts = HashMap::new();
+        let total = gtrie.census_into(graph, &mut counts);
+        (counts, total)
+    }
+
+    #[test]
+    fn dynamic_matches_full_census() {
+        let gtrie = Gtrie::read_from_file("example/gtries/dir4.gt").unwrap();
+        let graph = load_numeric_graph("example/graphs/example.txt", true, false).unwrap();
+        let edges = graph
+            .edge_indices()
+            .map(|e| graph.edge_endpoints(e).unwrap())
+            .map(|(u, v)| (u.index(), v.index()))
+            .collect::<Vec<_>>();
+
+        let mut dynamic = DynamicCensus::new(
+            Gtrie::read_from_file("example/gtries/dir4.gt").unwrap(),
+            Bitgraph::with_capacity(graph.node_count(), true),
+        );
+        assert_eq!(dynamic.total_subgraphs(), 0);
+
+        for (u, v) in edges.iter() {
+            dynamic.add_edge(*u, *v);
+        }
+        let (counts, total) = census(&gtrie, &Bitgraph::from_graph(&graph));
+        assert_eq!(dynamic.total_subgraphs(), total);
+        assert_eq!(dynamic.counts(), &counts);
+
+        for (u, v) in edges.iter().step_by(3) {
+            assert!(dynamic.remove_edge(*u, *v));
+        }
+        let (counts, total) = census(&gtrie, dynamic.graph());
+        assert_eq!(dynamic.total_subgraphs(), total);
+        assert_eq!(dynamic.counts(), &counts);
+    }
+
+    #[test]
+    fn dynamic_ignores_noop_updates() {
+        let gtrie = Gtrie::read_from_file("example/gtries/dir3.gt").unwrap();
+        let graph: Graph<(), (), Directed> = Graph::from_edges([(0, 1), (1, 2)]);
+        let mut dynamic = DynamicCensus::new(gtrie, Bitgraph::from_graph(&graph));
+        assert_eq!(dynamic.total_subgraphs(), 1);
+        assert!(!dynamic.add_edge(0, 1));
+        assert!(!dynamic.remove_edge(0, 2));
+        assert_eq!(dynamic.total_subgraphs(), 1);
+    }
+}
@@ -0,0 +1,244 @@
+use crate::{
+    bitgraph::Bitgraph,
+    census::{matches_structure, structure_mismatch, used_respects_conditions, violated_condition},
+    node::GtrieNode,
+    symmetry::Condition,
+};
+
+/// The outcome of attempting to match a specific set of vertices against a gtrie.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum Explanation {
+    /// The vertices were matched to a graph node with the vertices ordered by
+    /// their position in the motif.
+    Matched {
+        repr: Option<String>,
+        used: Vec<usize>,
+    },
+
+    /// The vertices could not be matched. Describes the deepest point that
+    /// matching reached and the vertices placed up to that point.
+    Unmatched { used: Vec<usize>, reason: Rejection },
+}
+
+/// The reason a set of vertices failed to match.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum Rejection {
+    /// No vertices were provided.
+    Empty,
+
+    /// The vertex does not exist in the graph.
+    OutOfRange { vertex: usize },
+
+    /// The vertex was provided more than once.
+    Duplicate { vertex: usize },
+
+    /// The gtrie contains no path of the required depth.
+    NoPath,
+
+    /// Placing `vertex` at the next position fails the structural check
+    /// against `used[position]`: the edge `src -> dst` was expected to be
+    /// present (`expected`) or absent.
+    Structure {
+        vertex: usize,
+        position: usize,
+        src: usize,
+        dst: usize,
+        expected: bool,
+    },
+
+    /// The placed vertices violate a symmetry breaking condition of the node.
+    Condition {
+        condition: Condition,
+        u: usize,
+        v: usize,
+    },
+
+    /// All vertices were placed but the node reached is not a graph.
+    NotAGraph,
+}
+
+/// Explains whether the given vertices are counted by the census rooted at `root`.
+///
+/// All orderings of the vertices along all paths of the trie are attempted
+/// (using the same structural and symmetry checks as the census) and either
+/// the matched graph node or the deepest rejection is returned.
+pub fn explain(root: &GtrieNode, graph: &Bitgraph, vertices: &[usize]) -> Explanation {
+    let unmatched = |reason| Explanation::Unmatched {
+        used: Vec::new(),
+        reason,
+    };
+    if vertices.is_empty() {
+        return unmatched(Rejection::Empty);
+    }
+    for (idx, v) in vertices.iter().enumerate() {
+        if *v >= graph.n_nodes() {
+            return unmatched(Rejection::OutOfRange { vertex: *v });
+        }
+        if vertices[..idx].contains(v) {
+            return unmatched(Rejection::Duplicate { vertex: *v });
+        }
+    }
+
+    let mut used = Vec::with_capacity(vertices.len());
+    let mut deepest = unmatched(Rejection::NoPath);
+    explain_children(root, graph, vertices, &mut used, &mut deepest).unwrap_or(deepest)
+}
+
+/// Attempts to extend `used` along each child of `node`, recording the deepest failure.
+fn explain_children(
+    node: &GtrieNode,
+    graph: &Bitgraph,
+    vertices: &[usize],
+    used: &mut Vec<usize>,
+    deepest: &mut Explanation,
+) -> Option<Explanation> {
+    for child in node.iter_children() {
+        for v in vertices {
+            if used.contains(v) {
+                continue;
+            }
+            if !matches_structure(child, graph, used, *v) {
+                let (position, src, dst, expected) =
+                    structure_mismatch(child, graph, used, *v).unwrap();
+                let reason = Rejection::Structure {
+                    vertex: *v,
+                    position,
+                    src,
+                    dst,
+                    expected,
+                };
+                record(deepest, used, reason);
+                continue;
+            }
+
+            used.push(*v);
+            if !used_respects_conditions(used, child.conditions()) {
+                let condition = violated_condition(used, child.conditions()).unwrap();
+                let reason = Rejection::Condition {
+                    condition,
+                    u: used[condition.min()],
+                    v: used[condition.max()],
+                };
+                record(deepest, used, reason);
+            } else if used.len() == vertices.len() {
+                if child.counts_occurrence(used) {
+                    return Some(Explanation::Matched {
+                        repr: child.repr().map(|r| r.to_string()),
+                        used: used.clone(),
+                    });
+                }
+                match violated_condition(used, child.graph_conditions()) {
+                    Some(condition) if child.is_graph() => {
+                        let reason = Rejection::Condition {
+                            condition,
+                            u: used[condition.min()],
+                            v: used[condition.max()],
+                        };
+                        record(deepest, used, reason);
+                    }
+                    _ => record(deepest, used, Rejection::NotAGraph),
+                }
+            } else if child.iter_children().next().is_none() {
+                record(deepest, used, Rejection::NoPath);
+            } else if let Some(matched) = explain_children(child, graph, vertices, used, deepest) {
+                return Some(matched);
+            }
+            used.pop();
+        }
+    }
+    None
+}
+
+/// Replaces the deepest rejection if the current one reached further.
+fn record(deepest: &mut Explanation, used: &[usize], reason: Rejection) {
+    let is_deeper = match deepest {
+        Explanation::Unmatched {
+            used: deepest_used,
+            reason: deepest_reason,
+        } => {
+            deepest_used.len() < used.len()
+                || (deepest_used.is_empty() && *deepest_reason == Rejection::NoPath)
+        }
+        Explanation::Matched { .. } => false,
+    };
+    if is_deeper {
+        *deepest = Explanation::Unmatched {
+            used: used.to_vec(),
+            reason,
+        };
+    }
+}
+
+#[cfg(test)]
+mod testing {
+    use super::*;
+    use crate::gtrie::Gtrie;
+    use itertools::Itertools;
+    use petgraph::{Directed, Graph};
+
+    fn build_graph() -> Bitgraph {
+        let edges = vec![(0, 1), (1, 2), (2, 0), (2, 3)];
+        let graph: Graph<(), (), Directed> = Graph::from_edges(&edges);
+        Bitgraph::from_graph(&graph)
+    }
+
+    #[test]
+    fn explain_matched() {
+        let gtrie = Gtrie::read_from_file("example/gtries/dir3.gt").unwrap();
+        let graph = build_graph();
+        match gtrie.explain_vertices(&graph, &[1, 2, 3]) {
+            Explanation::Matched { repr, used } => {
+                assert!(repr.is_some());
+                assert_eq!(used.iter().sorted().collect_vec(), vec![&1, &2, &3]);
+            }
+            e => panic!("unexpected explanation: {:?}", e),
+        }
+    }
+
+    #[test]
+    fn explain_disconnected() {
+        let gtrie = Gtrie::read_from_file("example/gtries/dir3.gt").unwrap();
+        let graph = build_graph();
+        match gtrie.explain_vertices(&graph, &[0, 1, 3]) {
+            Explanation::Unmatched { used, reason } => {
+                assert_eq!(used.len(), 2);
+                assert!(matches!(reason, Rejection::Structure { vertex: 3, .. }));
+            }
+            e => panic!("unexpected explanation: {:?}", e),
+        }
+    }
+
+    #[test]
+    fn explain_invalid_vertices() {
+        let gtrie = Gtrie::read_from_file("example/gtries/dir3.gt").unwrap();
+        let graph = build_graph();
+        let reason = |vertices: &[usize]| match gtrie.explain_vertices(&graph, vertices) {
+            Explanation::Unmatched { reason, .. } => reason,
+            e => panic!("unexpected explanation: {:?}", e),
+        };
+        assert_eq!(reason(&[]), Rejection::Empty);
+        assert_eq!(reason(&[0, 1, 10]), Rejection::OutOfRange { vertex: 10 });
+        assert_eq!(reason(&[0, 1, 1]), Rejection::Duplicate { vertex: 1 });
+        assert_eq!(reason(&[0, 1, 2, 3]), Rejection::NoPath);
+    }
+
+    #[test]
+    fn explain_agrees_with_census() {
+        let mut gtrie = Gtrie::read_from_file("example/gtries/dir3.gt").unwrap();
+        let graph =
+            crate::io::load_numeric_graph("example/graphs/example.txt", true, false).unwrap();
+        let graph = Bitgraph::from_graph(&graph);
+        gtrie.census(&graph);
+
+        let matched = (0..graph.n_nodes())
+            .combinations(3)
+            .filter(|vertices| {
+                matches!(
+                    gtrie.explain_vertices(&graph, vertices),
+                    Explanation::Matched { .. }
+                )
+            })
+            .count();
+        assert_eq!(matched, gtrie.total_subgraphs());
+    }
+}
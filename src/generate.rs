@@ -0,0 +1,224 @@
+use graph_canon::CanonLabeling;
+use hashbrown::HashSet;
+use petgraph::{Directed, Graph};
+
+/// Generates one representative of every connected non-isomorphic directed
+/// graph for each size from 1 to `max_size`.
+///
+/// The graphs of size `n` are stored at index `n - 1`, so every graph of every
+/// size is held in memory at once. See `for_each_connected_digraph` to stream
+/// them instead.
+pub fn connected_digraphs(max_size: usize) -> Vec<Vec<Graph<(), (), Directed>>> {
+    let mut by_size: Vec<Vec<Graph<(), (), Directed>>> = Vec::with_capacity(max_size);
+    if max_size == 0 {
+        return by_size;
+    }
+    by_size.push(vec![single_node()]);
+
+    for n in 1..max_size {
+        let mut extended = Vec::new();
+        extend_digraphs(&by_size[n - 1], n, |graph| extended.push(graph));
+        by_size.push(extended);
+    }
+    by_size
+}
+
+/// Calls `on_graph` with one representative of every connected non-isomorphic
+/// directed graph from `min_size` to `max_size` nodes, in increasing size.
+///
+/// Only the graphs of the previous size are kept to extend the next one, and
+/// graphs of `max_size` are passed on as soon as they are found without being
+/// stored. Peak memory is then that of the graphs of size `max_size - 1` plus
+/// the canonical adjacency of each graph of size `max_size` (used to discard
+/// isomorphic duplicates), instead of every graph of every size.
+pub fn for_each_connected_digraph<F>(min_size: usize, max_size: usize, mut on_graph: F)
+where
+    F: FnMut(&Graph<(), (), Directed>),
+{
+    if max_size == 0 {
+        return;
+    }
+    let mut previous = vec![single_node()];
+    if min_size <= 1 {
+        on_graph(&previous[0]);
+    }
+
+    for n in 1..max_size {
+        let is_last = n + 1 == max_size;
+        let mut extended = Vec::new();
+        extend_digraphs(&previous, n, |graph| {
+            if n + 1 >= min_size {
+                on_graph(&graph);
+            }
+            if !is_last {
+                extended.push(graph);
+            }
+        });
+        previous = extended;
+    }
+}
+
+fn single_node() -> Graph<(), (), Directed> {
+    let mut graph = Graph::new();
+    graph.add_node(());
+    graph
+}
+
+/// Attaches a new vertex to each graph of size `n` in every possible way that
+/// keeps it connected and passes each non-isomorphic result to `on_new`.
+///
+/// Every connected graph has a vertex whose removal leaves it connected, so
+/// this finds all connected graphs of size `n + 1`.
+fn extend_digraphs<F>(graphs: &[Graph<(), (), Directed>], n: usize, mut on_new: F)
+where
+    F: FnMut(Graph<(), (), Directed>),
+{
+    // Only the canonical adjacency is kept: all candidates have the same number
+    // of nodes so it identifies the isomorphism class on its own.
+    let mut seen = HashSet::new();
+
+    // Each existing vertex is either unconnected to the new vertex or has an
+    // edge to it, from it, or both: two bits per vertex.
+    let n_states = 1_usize << (2 * n);
+    for graph in graphs {
+        for state in 1..n_states {
+            let mut candidate = graph.clone();
+            let new = candidate.add_node(());
+            for u in candidate.node_indices().take(n) {
+                let bits = (state >> (2 * u.index())) & 0b11;
+                if bits & 0b01 != 0 {
+                    candidate.add_edge(u, new, ());
+                }
+                if bits & 0b10 != 0 {
+                    candidate.add_edge(new, u, ());
+                }
+            }
+            if seen.insert(CanonLabeling::new(&candidate).g) {
+                on_new(candidate);
+            }
+        }
+    }
+}
+
+#[cfg(test)]
+mod testing {
+    use super::*;
+    use crate::gtrie::Gtrie;
+    use std::{
+        alloc::{GlobalAlloc, Layout, System},
+        cell::Cell,
+    };
+
+    /// Counts the heap bytes allocated by a thread while it is tracking them,
+    /// see `heap_usage`.
+    struct TrackingAllocator;
+
+    thread_local! {
+        static TRACKING: Cell<bool> = const { Cell::new(false) };
+        static LIVE: Cell<isize> = const { Cell::new(0) };
+        static PEAK: Cell<isize> = const { Cell::new(0) };
+    }
+
+    fn record(delta: isize) {
+        let _ = TRACKING.try_with(|tracking| {
+            if tracking.get() {
+                let live = LIVE.get() + delta;
+                LIVE.set(live);
+                PEAK.set(PEAK.get().max(live));
+            }
+        });
+    }
+
+    unsafe impl GlobalAlloc for TrackingAllocator {
+        unsafe fn alloc(&self, layout: Layout) -> *mut u8 {
+            let ptr = System.alloc(layout);
+            if !ptr.is_null() {
+                record(layout.size() as isize);
+            }
+            ptr
+        }
+
+        unsafe fn dealloc(&self, ptr: *mut u8, layout: Layout) {
+            record(-(layout.size() as isize));
+            System.dealloc(ptr, layout);
+        }
+
+        unsafe fn realloc(&self, ptr: *mut u8, layout: Layout, new_size: usize) -> *mut u8 {
+            let new_ptr = System.realloc(ptr, layout, new_size);
+            if !new_ptr.is_null() {
+                record(new_size as isize - layout.size() as isize);
+            }
+            new_ptr
+        }
+    }
+
+    #[global_allocator]
+    static ALLOCATOR: TrackingAllocator = TrackingAllocator;
+
+    /// Runs `f` on this thread and returns its result along with the peak and
+    /// the final number of heap bytes it allocated and had not yet freed.
+    fn heap_usage<T>(f: impl FnOnce() -> T) -> (T, usize, usize) {
+        LIVE.set(0);
+        PEAK.set(0);
+        TRACKING.set(true);
+        let result = f();
+        TRACKING.set(false);
+        (result, PEAK.get() as usize, LIVE.get().max(0) as usize)
+    }
+
+    #[test]
+    fn connected_digraph_classes() {
+        let counts = connected_digraphs(4)
+            .iter()
+            .map(|graphs| graphs.len())
+            .collect::<Vec<_>>();
+        assert_eq!(counts, vec![1, 2, 13, 199]);
+    }
+
+    #[test]
+    fn streaming_matches_materialized() {
+        let expected = connected_digraphs(4)
+            .iter()
+            .map(|graphs| graphs.len())
+            .collect::<Vec<_>>();
+        let mut counts = vec![0; 4];
+        for_each_connected_digraph(1, 4, |graph| counts[graph.node_count() - 1] += 1);
+        assert_eq!(counts, expected);
+
+        let mut counts = vec![0; 4];
+        for_each_connected_digraph(3, 4, |graph| counts[graph.node_count() - 1] += 1);
+        assert_eq!(counts, vec![0, 0, 13, 199]);
+
+        let mut n_graphs = 0;
+        for_each_connected_digraph(1, 1, |_| n_graphs += 1);
+        assert_eq!(n_graphs, 1);
+    }
+
+    /// The streaming build of `generate --low-memory` under a heap budget.
+    #[test]
+    fn streaming_build_bounded_heap() {
+        let size = 5;
+        let (gtrie, peak, retained) = heap_usage(|| {
+            let mut gtrie = Gtrie::new(size);
+            for_each_connected_digraph(1, size, |graph| {
+                gtrie.insert_canonical(graph);
+            });
+            gtrie
+        });
+        let (graphs, materialized, _) = heap_usage(|| connected_digraphs(size));
+        assert_eq!(
+            gtrie.n_leaves(),
+            graphs.iter().map(|graphs| graphs.len()).sum::<usize>()
+        );
+
+        // Beyond the trie it returns, streaming only holds the graphs of size 4
+        // and the canonical forms of size 5, well below every graph at once.
+        assert!(
+            peak - retained < materialized / 2,
+            "peak {} of which {} retained, against {} materialized",
+            peak,
+            retained,
+            materialized
+        );
+    }
+}
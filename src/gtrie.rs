@@ -1,47 +1,269 @@
-use anyhow::Result;
+use anyhow::{anyhow, bail, Result};
 use fixedbitset::FixedBitSet;
-use hashbrown::HashMap;
+use graph_canon::CanonLabeling;
+use hashbrown::{HashMap, HashSet};
+use itertools::Itertools;
+use petgraph::{Directed, Graph};
+use rayon::prelude::*;
 use serde::{Deserialize, Serialize};
-use std::io::Write;
+use std::{
+    cmp::{Ordering, Reverse},
+    collections::BinaryHeap,
+    io::{BufRead, Write},
+    ops::Range,
+    time::Instant,
+};
 
 use crate::{
-    bitgraph::Bitgraph,
-    census::{match_child_conditionally, Candidates},
+    bitgraph::{Bitgraph, GraphQuery, Neighborhood},
+    census::{
+        extend_shared, match_child_reporting, match_child_sampled, match_child_shared, Candidates,
+        CensusScratch,
+    },
+    color::VertexColors,
+    explain::{explain, Explanation},
+    io,
+    isomorphism::{
+        assume_canonical, automorphism_group_size, canonicalize, canonicalize_with, isomorphisms,
+        ApMode, CanonicalBasedNauty,
+    },
+    motif::MotifRef,
     node::GtrieNode,
+    probability::EdgeProbs,
+    random::XorShift,
+    results::{
+        CensusReport, CensusResults, CensusSummary, ParticipationTable, SampledEstimate,
+        SharedCensusResults,
+    },
+    sampling::Sampler,
     symmetry::Conditions,
+    weight::{EdgeWeights, HeavyEdges},
 };
 
+/// Number of connected non-isomorphic directed graphs by node count
+/// (OEIS A003085), starting at a single node.
+const CONNECTED_DIGRAPH_CLASSES: [usize; 7] = [1, 2, 13, 199, 9364, 1530843, 880471142];
+
+/// Maximum number of vertex subsets enumerated by `Gtrie::validate_against_brute_force`.
+const MAX_BRUTE_FORCE_SUBSETS: usize = 1_000_000;
+
+/// Serialization format of a gtrie file.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum GtrieFormat {
+    /// Compact binary MessagePack, the default.
+    MessagePack,
+
+    /// Human-readable JSON, larger and slower to read.
+    Json,
+}
+impl GtrieFormat {
+    /// Picks the format of a path from its extension: `.json` (or
+    /// `.json.gz`) is JSON and anything else is MessagePack.
+    pub fn from_path(path: &str) -> Self {
+        if path.trim_end_matches(".gz").ends_with(".json") {
+            Self::Json
+        } else {
+            Self::MessagePack
+        }
+    }
+}
+
 #[derive(Debug, Serialize, Deserialize)]
 #[allow(dead_code)]
 pub struct Gtrie {
     root: GtrieNode,
     max_depth: usize,
     total_subgraphs: usize,
+
+    /// Whether the motifs are directed, set by the first motif inserted.
+    /// Tries written before the flag existed are directed.
+    #[serde(default = "directed")]
+    is_dir: bool,
+
+    /// Number of graph nodes, each numbered by its `GtrieNode::leaf`.
+    #[serde(default)]
+    n_leaves: usize,
 }
+
+fn directed() -> bool {
+    true
+}
+
 impl Gtrie {
     pub fn new(max_depth: usize) -> Self {
         Gtrie {
             root: GtrieNode::new(0),
             max_depth,
             total_subgraphs: 0,
+            is_dir: true,
+            n_leaves: 0,
         }
     }
 
+    /// Reads a gtrie in either format, recognizing JSON by its opening brace:
+    /// a MessagePack gtrie starts with an array marker. A path ending in
+    /// `.gz` is decompressed.
     pub fn read_from_file(path: &str) -> Result<Self> {
-        let file = std::fs::File::open(path)?;
-        let reader = std::io::BufReader::new(file);
-        let gtrie = rmp_serde::from_read(reader)?;
+        let mut reader = io::open_file(path)?;
+        let is_json = reader
+            .fill_buf()?
+            .iter()
+            .find(|b| !b.is_ascii_whitespace())
+            .is_some_and(|b| *b == b'{');
+        let mut gtrie: Self = if is_json {
+            serde_json::from_reader(reader)?
+        } else {
+            rmp_serde::from_read(reader)?
+        };
+        gtrie
+            .validate()
+            .map_err(|e| anyhow!("Invalid gtrie file {}: {}", path, e))?;
+        // Tries written before graph nodes were numbered have no leaf indices.
+        gtrie.index_leaves();
         Ok(gtrie)
     }
 
+    /// Checks that the structure of the trie is consistent with its depth: the
+    /// root is at depth 0, every node is one level below its parent and at
+    /// most `max_depth`, and every motif has as many nodes as its depth.
+    ///
+    /// A trie failing these checks would miscount or panic during a census.
+    pub fn validate(&self) -> Result<()> {
+        if self.max_depth == 0 {
+            bail!("the maximum depth of the gtrie is 0");
+        }
+        if self.root.depth() != 0 {
+            bail!("the root of the gtrie is at depth {}", self.root.depth());
+        }
+        Self::validate_node(&self.root, self.max_depth)
+    }
+
+    fn validate_node(node: &GtrieNode, max_depth: usize) -> Result<()> {
+        for child in node.iter_children() {
+            if child.depth() != node.depth() + 1 || child.depth() > max_depth {
+                bail!(
+                    "a node at depth {} has a child at depth {} (maximum depth {})",
+                    node.depth(),
+                    child.depth(),
+                    max_depth
+                );
+            }
+            if let Some(repr) = child.repr() {
+                let size = io::load_repr(repr)?.node_count();
+                if size != child.depth() {
+                    bail!(
+                        "motif {} of {} nodes is at depth {}",
+                        repr,
+                        size,
+                        child.depth()
+                    );
+                }
+            }
+            Self::validate_node(child, max_depth)?;
+        }
+        Ok(())
+    }
+
+    /// Merges another gtrie of the same depth and directedness into this one,
+    /// e.g. partial tries built separately.
+    ///
+    /// Nodes standing for the same trie position are matched by their edges,
+    /// as insertion does, and their frequencies summed. Subtrees found in only
+    /// one of the tries are kept as they are.
+    pub fn merge(&mut self, other: Gtrie) -> Result<()> {
+        if self.max_depth != other.max_depth {
+            bail!(
+                "Cannot merge gtries of depths {} and {}",
+                self.max_depth,
+                other.max_depth
+            );
+        }
+        if self.is_dir != other.is_dir {
+            bail!("Cannot merge a directed gtrie with an undirected gtrie");
+        }
+        self.root.merge(other.root);
+        self.total_subgraphs += other.total_subgraphs;
+        self.index_leaves();
+        Ok(())
+    }
+
+    /// Inserts a graph already labeled in the canonical order of the trie.
+    ///
+    /// `orbits` gives the orbit of each vertex of the graph under its
+    /// automorphisms, in the same labeling.
+    ///
+    /// Returns `false` if the graph was already in the trie.
     pub fn insert(
         &mut self,
         graph: &Bitgraph,
         conditions: Option<&Conditions>,
+        orbits: Option<&[usize]>,
         repr: Option<String>,
-    ) {
+    ) -> bool {
         assert!(graph.n_nodes() <= self.max_depth);
-        Self::insert_recursively_conditional(graph, &mut self.root, 0, conditions, repr);
+        if self.root.iter_children().next().is_none() {
+            self.is_dir = graph.is_dir();
+        }
+        Self::insert_recursively_conditional(
+            graph,
+            &mut self.root,
+            0,
+            conditions,
+            orbits,
+            repr,
+            &mut self.n_leaves,
+        )
+    }
+
+    /// Numbers the graph nodes of the trie in traversal order.
+    fn index_leaves(&mut self) {
+        self.n_leaves = 0;
+        self.root.index_leaves(&mut self.n_leaves);
+    }
+
+    /// Inserts the canonical form of an arbitrarily labeled graph.
+    ///
+    /// Returns the repr of the canonical form and `false` if an isomorphic
+    /// graph was already in the trie.
+    pub fn insert_canonical(&mut self, graph: &Graph<(), (), Directed>) -> (String, bool) {
+        self.insert_canonical_with(graph, ApMode::Auto)
+    }
+
+    /// Equivalent to `insert_canonical` with the given handling of articulation
+    /// points by the canonical labeling, see `ApMode`.
+    pub fn insert_canonical_with(
+        &mut self,
+        graph: &Graph<(), (), Directed>,
+        ap_mode: ApMode,
+    ) -> (String, bool) {
+        let (bgraph, canon) = canonicalize_with(graph, ap_mode);
+        let repr = canon.repr();
+        let is_new = self.insert_canonical_form(&bgraph, &canon, repr.clone());
+        (repr, is_new)
+    }
+
+    /// Inserts a graph in the canonical form computed by `canonicalize_with`
+    /// under `repr`, with the conditions and orbits of the canonical form.
+    ///
+    /// Returns `false` if the graph was already in the trie.
+    pub fn insert_canonical_form(
+        &mut self,
+        bgraph: &Bitgraph,
+        canon: &CanonicalBasedNauty,
+        repr: String,
+    ) -> bool {
+        self.insert(bgraph, canon.conditions(), Some(&canon.orbits), Some(repr))
+    }
+
+    /// Inserts a graph already in canonical form without relabeling it, see
+    /// `isomorphism::assume_canonical`.
+    ///
+    /// Returns the repr of the graph and `false` if it was already in the trie.
+    pub fn insert_assume_canonical(&mut self, graph: &Graph<(), (), Directed>) -> (String, bool) {
+        let (bgraph, canon) = assume_canonical(graph);
+        let repr = canon.repr();
+        let is_new = self.insert_canonical_form(&bgraph, &canon, repr.clone());
+        (repr, is_new)
     }
 
     fn insert_recursively_conditional(
@@ -49,16 +271,36 @@ impl Gtrie {
         node: &mut GtrieNode,
         k: usize,
         conditions: Option<&Conditions>,
+        orbits: Option<&[usize]>,
         repr: Option<String>,
-    ) {
+        n_leaves: &mut usize,
+    ) -> bool {
         if k == graph.n_nodes() {
+            let is_new = !node.is_graph();
+            if is_new {
+                node.set_leaf(*n_leaves);
+                *n_leaves += 1;
+            }
+
+            // The node may already be a prefix of larger graphs.
+            node.intersect_conditions(conditions);
             node.set_graph(true);
+            node.set_graph_conditions(conditions);
+            node.set_orbits(orbits);
             node.set_repr(repr);
+            is_new
         } else {
             for c in node.iter_children_mut() {
                 if Self::depth_eq(c, graph, k) {
-                    Self::insert_recursively_conditional(graph, c, k + 1, conditions, repr);
-                    return;
+                    return Self::insert_recursively_conditional(
+                        graph,
+                        c,
+                        k + 1,
+                        conditions,
+                        orbits,
+                        repr,
+                        n_leaves,
+                    );
                 }
             }
             node.intersect_conditions(conditions);
@@ -68,8 +310,17 @@ impl Gtrie {
                 GtrieNode::new(k + 1)
             };
             child.update_adjacency(graph, k + 1);
-            Self::insert_recursively_conditional(graph, &mut child, k + 1, conditions, repr);
+            Self::insert_recursively_conditional(
+                graph,
+                &mut child,
+                k + 1,
+                conditions,
+                orbits,
+                repr,
+                n_leaves,
+            );
             node.insert_child(child);
+            true
         }
     }
 
@@ -86,19 +337,170 @@ impl Gtrie {
     }
 
     /// A depth first search that prints out all nodes in the trie.
+    ///
+    /// If `frequency` is set the census count of each graph node is shown, and
+    /// if `repr` is set the canonical graph6 label of each graph node is shown.
     #[allow(dead_code)]
-    pub fn pprint(&self, frequency: bool) {
-        self.root.pprint(frequency);
+    pub fn pprint(&self, frequency: bool, repr: bool) {
+        self.root.pprint(frequency, repr);
+    }
+
+    /// The structure of the trie as a Graphviz DOT digraph, with an edge from
+    /// each node to its children. Nodes are labeled by their edges and graph
+    /// nodes, drawn as double circles, by their repr and frequency.
+    pub fn to_dot(&self) -> String {
+        let mut dot = String::from("digraph gtrie {\n    node [shape=circle];\n");
+        let mut n_nodes = 0;
+        Self::write_dot_node(&self.root, &mut n_nodes, &mut dot);
+        dot.push_str("}\n");
+        dot
+    }
+
+    /// Writes a node and its subtree to `dot`, returning the id of the node.
+    fn write_dot_node(node: &GtrieNode, n_nodes: &mut usize, dot: &mut String) -> usize {
+        let id = *n_nodes;
+        *n_nodes += 1;
+        if node.depth() == 0 {
+            dot.push_str(&format!("    n{} [label=\"root\", shape=box];\n", id));
+        } else if node.is_graph() {
+            // Graph6 uses the characters 63 to 126, of which only `\` needs escaping.
+            let repr = node.repr().unwrap_or_default().replace('\\', "\\\\");
+            dot.push_str(&format!(
+                "    n{} [label=\"{}\\n{} -> {}\", shape=doublecircle];\n",
+                id,
+                node.edge_string(),
+                repr,
+                node.frequency()
+            ));
+        } else {
+            dot.push_str(&format!(
+                "    n{} [label=\"{}\"];\n",
+                id,
+                node.edge_string()
+            ));
+        }
+        for child in node.iter_children() {
+            let child_id = Self::write_dot_node(child, n_nodes, dot);
+            dot.push_str(&format!("    n{} -> n{};\n", id, child_id));
+        }
+        id
     }
 
     pub fn pprint_results(&self) {
-        self.root.pprint_results();
+        for (repr, freq) in self.results() {
+            println!("{}\t{}", repr, freq);
+        }
+    }
+
+    /// Returns the undirected graph6 representation and frequency of every
+    /// symmetric motif in traversal order.
+    ///
+    /// When the census is performed on a symmetric graph only symmetric motifs can
+    /// be matched, and these are in one-to-one correspondence with the undirected motifs.
+    pub fn undirected_results(&self) -> Vec<(String, usize)> {
+        let results = self.results();
+        results
+            .into_iter()
+            .filter_map(|(repr, freq)| io::undirected_repr(repr).map(|repr| (repr, freq)))
+            .collect()
+    }
+
+    /// Returns the repr, frequency and number of embeddings of every motif in
+    /// traversal order.
+    ///
+    /// The census counts each induced vertex set once, while tools counting
+    /// embeddings count every mapping of the motif onto it: the frequency times
+    /// the size of the motif's automorphism group.
+    pub fn embedding_counts(&self) -> Result<Vec<(&str, usize, usize)>> {
+        let results = self.results();
+        results
+            .into_iter()
+            .map(|(repr, freq)| {
+                let embeddings = if freq > 0 {
+                    freq * automorphism_group_size(&io::load_repr(repr)?)
+                } else {
+                    0
+                };
+                Ok((repr, freq, embeddings))
+            })
+            .collect()
+    }
+
+    /// Sets the repr of every graph node missing one from the adjacency along
+    /// its path from the root. Returns the number of reprs set.
+    pub fn recompute_reprs(&mut self) -> usize {
+        let mut edges = Vec::new();
+        self.root
+            .iter_children_mut()
+            .map(|child| Self::recompute_reprs_recursively(child, &mut edges))
+            .sum()
+    }
+
+    fn recompute_reprs_recursively(node: &mut GtrieNode, edges: &mut Vec<(usize, usize)>) -> usize {
+        let k = node.depth();
+        let n_edges = edges.len();
+        for u in 0..k - 1 {
+            if node.out_contains(u) {
+                edges.push((u, k - 1));
+            }
+            if node.in_contains(u) {
+                edges.push((k - 1, u));
+            }
+        }
+
+        let mut n_set = 0;
+        if node.is_graph() && node.repr().is_none() {
+            let mut bitvec = vec![0; k * k];
+            edges.iter().for_each(|(u, v)| bitvec[u * k + v] = 1);
+            node.set_repr(Some(graph6_rs::write_graph6(bitvec, k, true)));
+            n_set += 1;
+        }
+        for child in node.iter_children_mut() {
+            n_set += Self::recompute_reprs_recursively(child, edges);
+        }
+        edges.truncate(n_edges);
+        n_set
+    }
+
+    /// Groups the repr and frequency of every motif by its size, in increasing
+    /// size and traversal order within each size.
+    pub fn results_by_size(&self) -> Vec<(usize, Vec<(&str, usize)>)> {
+        let mut results = Vec::new();
+        self.root.collect_sized_results(&mut results);
+        let mut results = merge_by_repr(results, |(_, repr, _)| repr, |(_, _, freq)| freq);
+        let mut by_size: Vec<(usize, Vec<(&str, usize)>)> = Vec::new();
+        results.sort_by_key(|(size, _, _)| *size);
+        for (size, repr, freq) in results {
+            match by_size.last_mut() {
+                Some((last, motifs)) if *last == size => motifs.push((repr, freq)),
+                _ => by_size.push((size, vec![(repr, freq)])),
+            }
+        }
+        by_size
+    }
+
+    pub fn pprint_results_undirected(&self) {
+        for (repr, freq) in self.undirected_results() {
+            println!("{}\t{}", repr, freq);
+        }
     }
 
+    /// Writes the gtrie in the format given by the extension of the path, see
+    /// `GtrieFormat::from_path`.
     pub fn write_to_file(&self, path: &str) -> Result<()> {
-        let mut file = std::fs::File::create(path)?;
-        self.write_to_buffer(&mut file)?;
-        Ok(())
+        self.write_to_file_as(path, GtrieFormat::from_path(path))
+    }
+
+    /// Writes the gtrie in the given format regardless of the extension of the
+    /// path, compressed if the path ends in `.gz`.
+    pub fn write_to_file_as(&self, path: &str, format: GtrieFormat) -> Result<()> {
+        io::write_file(path, |writer| {
+            match format {
+                GtrieFormat::MessagePack => self.write_to_buffer(&mut &mut *writer)?,
+                GtrieFormat::Json => serde_json::to_writer(writer, self)?,
+            }
+            Ok(())
+        })
     }
 
     pub fn write_to_buffer<W: Write>(&self, writer: &mut W) -> Result<()> {
@@ -106,31 +508,2445 @@ impl Gtrie {
         Ok(())
     }
 
-    pub fn census(&mut self, graph: &Bitgraph) {
+    /// Writes the trie in a line-based text format meant for review and diffs.
+    ///
+    /// The first line is `gtrie <max_depth>`, followed by `undirected` for a
+    /// trie of undirected motifs, and then one line per node in
+    /// depth-first order, indented by two spaces per level below the first.
+    /// See `GtrieNode::text_line` for the content of each line. Frequencies are
+    /// not written.
+    pub fn write_text_format<W: Write>(&self, writer: &mut W) -> Result<()> {
+        if self.is_dir {
+            writeln!(writer, "gtrie {}", self.max_depth)?;
+        } else {
+            writeln!(writer, "gtrie {} undirected", self.max_depth)?;
+        }
+        self.root.write_text(writer)?;
+        Ok(())
+    }
+
+    /// Hashes the structure of the trie: its nodes, conditions, orbits and
+    /// reprs, but not its frequencies.
+    ///
+    /// This is the 64-bit FNV-1a hash of `write_text_format`, so it is stable
+    /// across runs, platforms and compiler versions.
+    pub fn structural_hash(&self) -> u64 {
+        let mut text = Vec::new();
+        self.write_text_format(&mut text)
+            .expect("writing to a vector cannot fail");
+        text.iter().fold(0xcbf2_9ce4_8422_2325, |hash, byte| {
+            (hash ^ *byte as u64).wrapping_mul(0x0000_0100_0000_01b3)
+        })
+    }
+
+    /// Reads a trie written by `write_text_format`.
+    pub fn read_text_format<R: BufRead>(reader: R) -> Result<Self> {
+        let mut lines = reader.lines();
+        let header = lines.next().ok_or_else(|| anyhow!("Empty gtrie text"))??;
+        let (max_depth, is_dir) = match header.split_whitespace().collect::<Vec<_>>()[..] {
+            ["gtrie", depth] => (depth.parse::<usize>().ok(), true),
+            ["gtrie", depth, "undirected"] => (depth.parse::<usize>().ok(), false),
+            _ => (None, true),
+        };
+        let max_depth =
+            max_depth.ok_or_else(|| anyhow!("Invalid gtrie text header `{}`", header))?;
+
+        // The nodes on the path from the root to the last node read.
+        let mut path = vec![GtrieNode::new(0)];
+        for (idx, line) in lines.enumerate() {
+            let line = line?;
+            if line.trim().is_empty() {
+                continue;
+            }
+            let indent = line.len() - line.trim_start_matches(' ').len();
+            let depth = indent / 2 + 1;
+            if indent % 2 != 0 || depth > path.len() || depth > max_depth {
+                bail!("Line {}: unexpected indentation", idx + 2);
+            }
+            while path.len() > depth {
+                let child = path.pop().unwrap();
+                path.last_mut().unwrap().insert_child(child);
+            }
+            let node = GtrieNode::from_text_line(depth, line.trim_start())
+                .map_err(|e| anyhow!("Line {}: {}", idx + 2, e))?;
+            path.push(node);
+        }
+        while path.len() > 1 {
+            let child = path.pop().unwrap();
+            path.last_mut().unwrap().insert_child(child);
+        }
+
+        let mut gtrie = Gtrie {
+            root: path.pop().unwrap(),
+            max_depth,
+            total_subgraphs: 0,
+            is_dir,
+            n_leaves: 0,
+        };
+        gtrie.validate()?;
+        gtrie.index_leaves();
+        Ok(gtrie)
+    }
+
+    /// Checks that the graph can be matched against the motifs of the trie.
+    ///
+    /// Undirected graphs are symmetric, so they are censused by directed tries
+    /// as well. A directed graph censused by an undirected trie would have its
+    /// asymmetric edges silently ignored, and is an error.
+    pub fn check_directedness(&self, graph: &impl GraphQuery) -> Result<()> {
+        if graph.is_dir() && !self.is_dir {
+            bail!("Cannot census a directed graph with a gtrie of undirected motifs");
+        }
+        Ok(())
+    }
+
+    /// Performs the census after `check_directedness`.
+    pub fn census_checked(&mut self, graph: &impl GraphQuery) -> Result<()> {
+        self.check_directedness(graph)?;
+        self.census(graph);
+        Ok(())
+    }
+
+    /// Performs the census, adding the occurrences found to the counts.
+    ///
+    /// Unchecked: a `Neighborhood` does not know whether it is directed, so a
+    /// directed graph is not rejected by an undirected trie. Call
+    /// `check_directedness` first or use `census_checked`.
+    pub fn census(&mut self, graph: &impl Neighborhood) {
+        self.census_excluding(graph, &FixedBitSet::with_capacity(graph.n_nodes()));
+    }
+
+    /// Performs the census ignoring all subgraphs that contain an `excluded` vertex.
+    pub fn census_excluding(&mut self, graph: &impl Neighborhood, excluded: &FixedBitSet) {
+        let mut counts = vec![0; self.n_leaves];
+        self.visit_matches_with_scratch(
+            graph,
+            &mut CensusScratch::excluding(graph.n_nodes(), excluded),
+            |node, _| counts[node.leaf()] += 1,
+        );
+        self.add_leaf_counts(&counts);
+    }
+
+    /// Performs a sampled census keeping each vertex matched at depth `d` with
+    /// probability `probs[d - 1]`, and estimates the frequency of every motif
+    /// with a confidence interval, in increasing size.
+    ///
+    /// The draws are made by a generator seeded from `seed`, see `sampling::Sampler`.
+    ///
+    /// Probabilities of 1 at every depth give the exact census with intervals
+    /// of zero width.
+    pub fn census_sampled(
+        &mut self,
+        graph: &Bitgraph,
+        probs: &[f64],
+        seed: u64,
+    ) -> Result<Vec<SampledEstimate>> {
+        if probs.len() != self.max_depth {
+            bail!(
+                "ERROR: Expected {} sampling probabilities (one per depth) but found {}",
+                self.max_depth,
+                probs.len()
+            );
+        }
+        let mut sampler = Sampler::new(probs, XorShift::new(seed))?;
+
         let mut used = Vec::with_capacity(self.max_depth);
         let mut candidates = Candidates::new(graph.n_nodes());
         let mut blacklist = FixedBitSet::with_capacity(graph.n_nodes());
-
         for c in self.root.iter_children_mut() {
-            match_child_conditionally(
+            match_child_sampled(
                 c,
                 &mut used,
                 &mut candidates,
                 &mut blacklist,
                 graph,
+                &mut sampler,
                 &mut self.total_subgraphs,
+            );
+        }
+
+        Ok(self
+            .results_by_size()
+            .into_iter()
+            .flat_map(|(size, motifs)| {
+                let sampler = &sampler;
+                motifs
+                    .into_iter()
+                    .map(move |(repr, freq)| sampler.estimate(repr, size, freq))
+            })
+            .collect())
+    }
+
+    /// Resets the counts of the trie, performs the census then passes each
+    /// nonzero motif to `sink` as `(graph_id, repr, frequency)`, in traversal
+    /// order.
+    ///
+    /// Lets the results be pushed to an external store as they are read from
+    /// the trie instead of being collected into a map first. Any counts left
+    /// by earlier censuses are discarded, so the frequencies are those of
+    /// `graph` alone when one trie is sunk across several graphs: use `census`
+    /// to accumulate instead.
+    pub fn census_to_sink<F: FnMut(&str, &str, u64)>(
+        &mut self,
+        graph: &Bitgraph,
+        graph_id: &str,
+        sink: &mut F,
+    ) {
+        self.reset_counts();
+        self.census(graph);
+        let results = self.results();
+        for (repr, freq) in results {
+            if freq > 0 {
+                sink(graph_id, repr, freq as u64);
+            }
+        }
+    }
+
+    /// Performs the part of the census whose occurrences start at a vertex of
+    /// `vertex_range`, i.e. place one of these vertices first in their motif.
+    ///
+    /// Each occurrence is counted from a single ordering of its vertices, so
+    /// the censuses of disjoint ranges covering the graph add up to the full
+    /// census.
+    pub fn census_shard(&mut self, graph: &Bitgraph, vertex_range: Range<usize>) {
+        let mut scratch = CensusScratch::new(graph.n_nodes());
+        let mut counts = vec![0; self.n_leaves];
+        let mut on_match = |node: &GtrieNode, _: &[usize]| counts[node.leaf()] += 1;
+        for v in vertex_range.start..vertex_range.end.min(graph.n_nodes()) {
+            self.visit_matches_from(graph, v, &mut scratch, &mut on_match);
+        }
+        self.add_leaf_counts(&counts);
+    }
+
+    /// Performs the census reusing the buffers of `scratch` rather than
+    /// allocating new ones, for loops censusing many graphs.
+    pub fn census_with_scratch(&mut self, graph: &Bitgraph, scratch: &mut CensusScratch) {
+        scratch.prepare(graph.n_nodes());
+        let mut counts = vec![0; self.n_leaves];
+        self.visit_matches_with_scratch(graph, scratch, |node, _| counts[node.leaf()] += 1);
+        self.add_leaf_counts(&counts);
+    }
+
+    /// Performs the census as `census` does, also calling `on_occurrence` with
+    /// the repr of the motif and the vertices of every occurrence found, in
+    /// the order of their positions in the trie.
+    pub fn census_with<F: FnMut(&str, &[usize])>(
+        &mut self,
+        graph: &Bitgraph,
+        mut on_occurrence: F,
+    ) {
+        self.census_with_nodes(graph, |node, used| {
+            if let Some(repr) = node.repr() {
+                on_occurrence(repr, used);
+            }
+        });
+    }
+
+    /// Performs the census as `census_with` does, also passing the orbit of
+    /// each position of the motif (see `GtrieNode::orbits`), if the trie has
+    /// them, so that the vertices of an occurrence can be labeled by role.
+    pub fn census_with_orbits<F: FnMut(&str, &[usize], Option<&[usize]>)>(
+        &mut self,
+        graph: &Bitgraph,
+        mut on_occurrence: F,
+    ) {
+        self.census_with_nodes(graph, |node, used| {
+            if let Some(repr) = node.repr() {
+                on_occurrence(repr, used, node.orbits());
+            }
+        });
+    }
+
+    /// Performs the census as `census` does, also calling `on_occurrence` with
+    /// the graph node and the vertices of every occurrence found.
+    fn census_with_nodes<F: FnMut(&GtrieNode, &[usize])>(
+        &mut self,
+        graph: &Bitgraph,
+        mut on_occurrence: F,
+    ) {
+        let mut counts = vec![0; self.n_leaves];
+        self.visit_matches(graph, |node, used| {
+            counts[node.leaf()] += 1;
+            on_occurrence(node, used);
+        });
+        self.add_leaf_counts(&counts);
+    }
+
+    /// Performs the census as `census` does, also counting for each vertex the
+    /// occurrences of every motif it takes part in.
+    pub fn census_participation(&mut self, graph: &Bitgraph) -> ParticipationTable {
+        let reprs = self.reprs().into_iter().map(String::from).collect();
+        let mut table = ParticipationTable::new(graph.n_nodes(), reprs);
+        let motifs = self
+            .leaf_reprs()
+            .into_iter()
+            .map(|repr| table.motif_index(repr))
+            .collect::<Vec<_>>();
+        self.census_with_nodes(graph, |node, used| {
+            if let Some(idx) = motifs[node.leaf()] {
+                table.add_occurrence(idx, used);
+            }
+        });
+        table
+    }
+
+    /// Performs the census ignoring all subgraphs that contain an `excluded`
+    /// vertex or an edge with a weight below `min_weight`, see
+    /// `Bitgraph::edge_weight`.
+    ///
+    /// Occurrences are kept or rejected whole, so their motifs are those of
+    /// the unweighted graph.
+    pub fn census_min_edge_weight(
+        &mut self,
+        graph: &Bitgraph,
+        excluded: &FixedBitSet,
+        min_weight: f64,
+    ) {
+        self.census_excluding(&HeavyEdges::new(graph, min_weight), excluded);
+    }
+
+    /// Performs the census on the current rayon thread pool.
+    ///
+    /// The starting vertices are dealt round robin into a few batches per
+    /// thread, each censused with its own buffers into its own counts. The
+    /// counts are then merged into the trie, giving the same frequencies as
+    /// `census`.
+    pub fn census_parallel(&mut self, graph: &Bitgraph) {
+        let n_nodes = graph.n_nodes();
+        let n_batches = (rayon::current_num_threads() * 4).clamp(1, n_nodes.max(1));
+        let this = &*self;
+        let batches = (0..n_batches)
+            .into_par_iter()
+            .map(|batch| {
+                let mut scratch = CensusScratch::new(n_nodes);
+                let mut counts = vec![0; this.n_leaves];
+                let mut on_match = |node: &GtrieNode, _: &[usize]| counts[node.leaf()] += 1;
+                for v in (batch..n_nodes).step_by(n_batches) {
+                    this.visit_matches_from(graph, v, &mut scratch, &mut on_match);
+                }
+                counts
+            })
+            .collect::<Vec<_>>();
+
+        let mut counts = vec![0; self.n_leaves];
+        for batch_counts in batches {
+            for (count, batch_count) in counts.iter_mut().zip(batch_counts) {
+                *count += batch_count;
+            }
+        }
+        self.add_leaf_counts(&counts);
+    }
+
+    /// Performs the census as `census` does and reports its duration and the
+    /// effort of the search.
+    pub fn census_reporting(&mut self, graph: &Bitgraph) -> CensusReport {
+        let now = Instant::now();
+        let mut report = CensusReport::default();
+        let mut used = Vec::with_capacity(self.max_depth);
+        let mut candidates = Candidates::new(graph.n_nodes());
+        let mut blacklist = FixedBitSet::with_capacity(graph.n_nodes());
+
+        for c in self.root.iter_children_mut() {
+            match_child_reporting(
+                c,
+                &mut used,
+                &mut candidates,
+                &mut blacklist,
+                graph,
+                &mut report,
             )
         }
+        self.total_subgraphs += report.total_subgraphs;
+        report.elapsed = now.elapsed();
+        report
     }
 
-    pub fn total_subgraphs(&self) -> usize {
-        self.total_subgraphs
+    /// Performs the census without modifying the trie so that a single trie
+    /// can be shared across threads.
+    ///
+    /// The frequency of each motif is accumulated into `counts` (keyed by its repr)
+    /// and the number of subgraphs found is returned.
+    pub fn census_into(&self, graph: &Bitgraph, counts: &mut HashMap<String, usize>) -> usize {
+        let mut total_subgraphs = 0;
+        self.visit_matches(graph, |node, _used| {
+            total_subgraphs += 1;
+            Self::count_match(counts, node);
+        });
+        total_subgraphs
     }
 
-    #[allow(dead_code)]
-    pub fn get_nonzero(&self) -> HashMap<String, usize> {
-        let mut map = HashMap::new();
-        self.root.get_nonzero(&mut map);
-        map
+    /// Performs the census without modifying the trie, atomically adding every
+    /// occurrence to `results` so that many threads can share one accumulator.
+    ///
+    /// Returns the number of subgraphs found in this graph.
+    pub fn census_shared(&self, graph: &Bitgraph, results: &SharedCensusResults) -> usize {
+        let mut total_subgraphs = 0;
+        self.visit_matches(graph, |node, _used| {
+            total_subgraphs += 1;
+            results.increment(node.leaf());
+        });
+        total_subgraphs
+    }
+
+    /// Performs the census, aborting once `deadline` has passed.
+    ///
+    /// The deadline is only checked between the starting vertices of the census
+    /// so it costs nothing in the inner loops, though a single starting vertex
+    /// may run past it. Returns `false` if the census was aborted, in which case
+    /// the counts are partial.
+    pub fn census_until(&mut self, graph: &Bitgraph, deadline: Instant) -> bool {
+        let mut scratch = CensusScratch::new(graph.n_nodes());
+        let mut counts = vec![0; self.n_leaves];
+        let mut on_match = |node: &GtrieNode, _: &[usize]| counts[node.leaf()] += 1;
+        let mut completed = true;
+        for v in 0..graph.n_nodes() {
+            if Instant::now() >= deadline {
+                completed = false;
+                break;
+            }
+            self.visit_matches_from(graph, v, &mut scratch, &mut on_match);
+        }
+        self.add_leaf_counts(&counts);
+        completed
+    }
+
+    /// The motifs found by the last census as a JSON array of
+    /// `{"repr": ..., "frequency": ...}` objects, sorted by repr.
+    ///
+    /// Unless `is_dir`, the motifs are given by their undirected reprs as in
+    /// `undirected_results`.
+    pub fn results_as_json(&self, is_dir: bool) -> serde_json::Value {
+        let mut results = self
+            .get_nonzero()
+            .into_iter()
+            .filter_map(|(repr, freq)| io::output_repr(&repr, is_dir).map(|repr| (repr, freq)))
+            .collect::<Vec<_>>();
+        results.sort_unstable();
+        results
+            .into_iter()
+            .map(|(repr, frequency)| serde_json::json!({ "repr": repr, "frequency": frequency }))
+            .collect()
+    }
+
+    /// Performs the census as `census` does, calling `on_vertex` with the
+    /// number of starting vertices processed so far and the running number of
+    /// subgraphs found after each starting vertex, e.g. to report progress.
+    pub fn census_with_progress<F: FnMut(usize, usize)>(
+        &mut self,
+        graph: &Bitgraph,
+        mut on_vertex: F,
+    ) {
+        let mut scratch = CensusScratch::new(graph.n_nodes());
+        let mut counts = vec![0; self.n_leaves];
+        let mut total_subgraphs = self.total_subgraphs;
+        for v in 0..graph.n_nodes() {
+            self.visit_matches_from(graph, v, &mut scratch, &mut |node, _| {
+                counts[node.leaf()] += 1;
+                total_subgraphs += 1;
+            });
+            on_vertex(v + 1, total_subgraphs);
+        }
+        self.add_leaf_counts(&counts);
+    }
+
+    /// Equivalent to `census_into` but aborts once `deadline` has passed, see
+    /// `census_until`. Returns `None` if the census was aborted.
+    pub fn census_into_until(
+        &self,
+        graph: &Bitgraph,
+        counts: &mut HashMap<String, usize>,
+        deadline: Instant,
+    ) -> Option<usize> {
+        let mut scratch = CensusScratch::new(graph.n_nodes());
+        let mut total_subgraphs = 0;
+        let mut on_match = |node: &GtrieNode, _used: &[usize]| {
+            total_subgraphs += 1;
+            Self::count_match(counts, node);
+        };
+        for v in 0..graph.n_nodes() {
+            if Instant::now() >= deadline {
+                return None;
+            }
+            self.visit_matches_from(graph, v, &mut scratch, &mut on_match);
+        }
+        Some(total_subgraphs)
+    }
+
+    /// Performs the census counting only the subgraphs that contain the edge `a -> b`.
+    ///
+    /// The census is anchored on the neighborhood of `a` within reach of a
+    /// motif, and the occurrences missing either endpoint are discarded. Nothing
+    /// is counted if the graph has no edge `a -> b`.
+    pub fn census_with_edge(&mut self, graph: &Bitgraph, a: usize, b: usize) {
+        if a == b || !graph.is_connected(a, b) {
+            return;
+        }
+        let hops = self.max_depth.saturating_sub(1);
+        let vertices = graph.neighborhood(a, hops);
+        let (Ok(local_a), Ok(local_b)) = (vertices.binary_search(&a), vertices.binary_search(&b))
+        else {
+            return;
+        };
+
+        let local = graph.induced_subgraph(&vertices);
+        let mut counts = vec![0; self.n_leaves];
+        self.visit_matches(&local, |node, used| {
+            if used.contains(&local_a) && used.contains(&local_b) {
+                counts[node.leaf()] += 1;
+            }
+        });
+        self.add_leaf_counts(&counts);
+    }
+
+    /// Computes the expected frequency of each motif when every edge of the graph
+    /// exists independently with the probability given by `edge_probs`.
+    ///
+    /// Each occurrence found in the graph contributes the probability that its
+    /// vertices induce exactly the matched motif, i.e. the product of the
+    /// probabilities of its edges and of the complements for the pairs it
+    /// requires absent. Realizations of a vertex set that drop some of its
+    /// edges are not attributed to the motif they form. With probabilities of 0
+    /// or 1 this is the census itself.
+    pub fn census_expected(
+        &self,
+        graph: &Bitgraph,
+        edge_probs: &EdgeProbs,
+    ) -> HashMap<String, f64> {
+        let mut expected = HashMap::new();
+        self.visit_matches(graph, |node, used| {
+            if let Some(repr) = node.repr() {
+                *expected.entry_ref(repr).or_insert(0.0) +=
+                    edge_probs.realization_probability(graph, used);
+            }
+        });
+        expected
+    }
+
+    /// Adds the occurrences counted per leaf by a traversal to the
+    /// frequencies of the trie and to its total.
+    fn add_leaf_counts(&mut self, counts: &[usize]) {
+        self.root.add_leaf_frequencies(counts);
+        self.total_subgraphs += counts.iter().sum::<usize>();
+    }
+
+    /// Increments the count of the motif of a matched graph node.
+    fn count_match(counts: &mut HashMap<String, usize>, node: &GtrieNode) {
+        if let Some(repr) = node.repr() {
+            match counts.get_mut(repr) {
+                Some(count) => *count += 1,
+                None => {
+                    counts.insert(repr.to_string(), 1);
+                }
+            }
+        }
+    }
+
+    /// Censuses two graphs independently and joins the counts on the repr of
+    /// each motif of the trie as `(repr, count_a, count_b)`.
+    ///
+    /// Motifs missing from either census are reported with a count of zero.
+    pub fn compare(&self, graph_a: &Bitgraph, graph_b: &Bitgraph) -> Vec<(&str, usize, usize)> {
+        let mut counts_a = HashMap::new();
+        let mut counts_b = HashMap::new();
+        self.census_into(graph_a, &mut counts_a);
+        self.census_into(graph_b, &mut counts_b);
+        self.reprs()
+            .into_iter()
+            .map(|repr| {
+                let count_a = counts_a.get(repr).copied().unwrap_or(0);
+                let count_b = counts_b.get(repr).copied().unwrap_or(0);
+                (repr, count_a, count_b)
+            })
+            .collect()
+    }
+
+    /// Performs the census without modifying the trie, calling `on_match` with
+    /// the matched graph node and the vertices of every occurrence found.
+    ///
+    /// Occurrences are reported once per induced vertex set, not once per
+    /// automorphism: the symmetry breaking conditions admit a single ordering
+    /// of each set, given by the positions of the vertices in the motif.
+    pub fn visit_matches<F: FnMut(&GtrieNode, &[usize])>(&self, graph: &Bitgraph, on_match: F) {
+        let excluded = FixedBitSet::with_capacity(graph.n_nodes());
+        self.visit_matches_excluding(graph, &excluded, on_match);
+    }
+
+    /// Collects the vertices of every occurrence found, grouped by the repr of
+    /// its motif. See `visit_matches` for the order and uniqueness of occurrences.
+    pub fn occurrences(&self, graph: &Bitgraph) -> HashMap<String, Vec<Vec<usize>>> {
+        let mut occurrences: HashMap<String, Vec<Vec<usize>>> = HashMap::new();
+        self.visit_matches(graph, |node, used| {
+            if let Some(repr) = node.repr() {
+                occurrences.entry_ref(repr).or_default().push(used.to_vec());
+            }
+        });
+        occurrences
+    }
+
+    /// Samples up to `k` occurrences of every motif with weighted reservoir
+    /// sampling (A-Res), grouped by the repr of the motif.
+    ///
+    /// The weight of an occurrence is the product of the weights of its edges,
+    /// so heavier occurrences are more likely to be kept and occurrences of
+    /// weight zero never are. Without weights every occurrence is equally
+    /// likely. Only the reservoirs are held in memory.
+    pub fn sample_occurrences(
+        &self,
+        graph: &Bitgraph,
+        k: usize,
+        weights: Option<&EdgeWeights>,
+        seed: u64,
+    ) -> HashMap<String, Vec<Vec<usize>>> {
+        let mut rng = XorShift::new(seed);
+        let mut reservoirs: HashMap<String, Reservoir> = HashMap::new();
+        self.visit_matches(graph, |node, used| {
+            let Some(repr) = node.repr() else {
+                return;
+            };
+            let weight = weights.map_or(1.0, |w| w.occurrence_weight(graph, used));
+            if k == 0 || weight <= 0.0 {
+                return;
+            }
+
+            // Each occurrence draws the key `u^(1 / weight)` and the `k`
+            // largest keys are kept, compared as logarithms.
+            let u = 1.0 - (rng.next_u64() >> 11) as f64 / (1_u64 << 53) as f64;
+            let key = SampleKey(u.ln() / weight);
+            let reservoir = reservoirs.entry_ref(repr).or_default();
+            if reservoir.len() < k {
+                reservoir.push(Reverse((key, used.to_vec())));
+            } else if let Some(mut min) = reservoir.peek_mut() {
+                let Reverse((min_key, _)) = &*min;
+                if *min_key < key {
+                    *min = Reverse((key, used.to_vec()));
+                }
+            }
+        });
+        reservoirs
+            .into_iter()
+            .map(|(repr, reservoir)| {
+                let used = reservoir.into_iter().map(|Reverse((_, used))| used);
+                (repr, used.collect())
+            })
+            .collect()
+    }
+
+    /// Counts the occurrences of every motif broken down by the colors of their
+    /// vertices, keyed by the repr of the motif and the colors at the vertices
+    /// of the graph of its repr.
+    ///
+    /// The colors of an occurrence are placed by every isomorphism from its
+    /// leaf onto the repr (see `VertexColors::colors_under`), through the
+    /// reversal of the leaf for motifs merged with their reversal. The key is
+    /// then the same for every leaf and up to the automorphisms of the motif,
+    /// so the counts of a motif over all of its color combinations add up to
+    /// its frequency. Errors if a vertex of the graph has no color.
+    pub fn census_by_color(
+        &self,
+        graph: &Bitgraph,
+        colors: &VertexColors,
+    ) -> Result<HashMap<(String, Vec<usize>), u64>> {
+        if let Some(v) = (0..graph.n_nodes()).find(|v| colors.get(*v).is_none()) {
+            bail!("ERROR: Vertex {} has no color", v + 1);
+        }
+        let mut placements: Vec<Option<Vec<Vec<usize>>>> = vec![None; self.n_leaves];
+        let mut counts = HashMap::new();
+        self.visit_matches(graph, |node, used| {
+            let Some(repr) = node.repr() else {
+                return;
+            };
+            // The induced subgraph is the motif labeled by trie position.
+            let placements = placements[node.leaf()]
+                .get_or_insert_with(|| repr_isomorphisms(&graph.induced_subgraph(used), repr));
+            let key = (repr.to_string(), colors.colors_under(used, placements));
+            *counts.entry(key).or_insert(0) += 1;
+        });
+        Ok(counts)
+    }
+
+    /// Checks the census of a small graph against a brute force enumeration of
+    /// every vertex subset of each motif size, identifying the motif of each
+    /// induced subgraph by canonical labeling.
+    ///
+    /// Errors describing the motifs whose counts differ, or if the graph has
+    /// too many subsets to enumerate.
+    pub fn validate_against_brute_force(&self, graph: &Bitgraph) -> Result<()> {
+        let motifs = self.motifs();
+        let mut sizes = motifs.iter().map(|m| m.size()).collect::<Vec<_>>();
+        sizes.sort_unstable();
+        sizes.dedup();
+        let n_subsets = sizes
+            .iter()
+            .map(|k| {
+                (0..*k).fold(1_usize, |acc, i| {
+                    acc.saturating_mul(graph.n_nodes().saturating_sub(i)) / (i + 1)
+                })
+            })
+            .fold(0_usize, |acc, n| acc.saturating_add(n));
+        if n_subsets > MAX_BRUTE_FORCE_SUBSETS {
+            bail!(
+                "ERROR: Graph of {} nodes has {} vertex subsets to check; brute force is limited to {}",
+                graph.n_nodes(),
+                n_subsets,
+                MAX_BRUTE_FORCE_SUBSETS
+            );
+        }
+
+        // The orientations of a motif merged with its reversal share a repr,
+        // so both labelings map to it.
+        let labels = motifs
+            .iter()
+            .map(|m| (CanonLabeling::new(&m.to_graph()), m.repr()))
+            .collect::<HashMap<_, _>>();
+        let mut expected: HashMap<String, usize> = HashMap::new();
+        for k in sizes {
+            for subset in (0..graph.n_nodes()).combinations(k) {
+                let induced = graph.induced_subgraph(&subset).to_graph();
+                if let Some(repr) = labels.get(&CanonLabeling::new(&induced)) {
+                    *expected.entry_ref(*repr).or_default() += 1;
+                }
+            }
+        }
+
+        let mut counts = HashMap::new();
+        self.census_into(graph, &mut counts);
+        let mismatches = self
+            .reprs()
+            .into_iter()
+            .filter_map(|repr| {
+                let count = counts.get(repr).copied().unwrap_or(0);
+                let brute_force = expected.get(repr).copied().unwrap_or(0);
+                (count != brute_force)
+                    .then(|| format!("{}: census {}, brute force {}", repr, count, brute_force))
+            })
+            .collect::<Vec<_>>();
+        if !mismatches.is_empty() {
+            bail!(
+                "ERROR: Census disagrees with brute force for {} motifs: {}",
+                mismatches.len(),
+                mismatches.join("; ")
+            );
+        }
+        Ok(())
+    }
+
+    /// Performs the census and counts the pairs of occurrences of `motif_a` and
+    /// `motif_b` that share at least one vertex.
+    ///
+    /// If both reprs are the same motif each unordered pair of distinct
+    /// occurrences is counted once. Every occurrence of both motifs is held in
+    /// memory, so this is an analysis tool for small graphs.
+    pub fn census_cooccurrence(&mut self, graph: &Bitgraph, motif_a: &str, motif_b: &str) -> usize {
+        let mut occurrences_a = Vec::new();
+        let mut occurrences_b = Vec::new();
+        self.census_with(graph, |repr, used| {
+            if repr == motif_a {
+                occurrences_a.push(used.to_vec());
+            }
+            if repr == motif_b {
+                occurrences_b.push(used.to_vec());
+            }
+        });
+
+        let mut by_vertex: HashMap<usize, Vec<usize>> = HashMap::new();
+        for (idx, occurrence) in occurrences_b.iter().enumerate() {
+            for v in occurrence {
+                by_vertex.entry(*v).or_default().push(idx);
+            }
+        }
+
+        let mut n_pairs = 0;
+        let mut partners = FixedBitSet::with_capacity(occurrences_b.len());
+        for (idx, occurrence) in occurrences_a.iter().enumerate() {
+            partners.clear();
+            for v in occurrence {
+                by_vertex
+                    .get(v)
+                    .into_iter()
+                    .flatten()
+                    .for_each(|partner| partners.insert(*partner));
+            }
+            if motif_a == motif_b {
+                partners.set(idx, false);
+            }
+            n_pairs += partners.count_ones(..);
+        }
+        if motif_a == motif_b {
+            n_pairs /= 2;
+        }
+        n_pairs
+    }
+
+    /// Writes the canonical graph6 representation of every occurrence found,
+    /// one per line, ignoring the subgraphs that contain an `excluded` vertex.
+    ///
+    /// Each occurrence is induced from the graph and canonicalized on its own,
+    /// independently of the trie. For a trie built with `insert_canonical` the
+    /// number of lines of each repr then checks the frequency of its motif.
+    /// Returns the number of lines written.
+    pub fn write_subgraphs<W: Write>(
+        &self,
+        graph: &Bitgraph,
+        excluded: &FixedBitSet,
+        writer: &mut W,
+    ) -> Result<usize> {
+        let mut n_written = 0;
+        let mut result = Ok(());
+        self.visit_matches_excluding(graph, excluded, |_, used| {
+            if result.is_ok() {
+                let induced = graph.induced_subgraph(used).to_graph();
+                let (_, canon) = canonicalize(&induced);
+                result = writeln!(writer, "{}", canon.repr());
+                n_written += 1;
+            }
+        });
+        result?;
+        Ok(n_written)
+    }
+
+    /// Performs the census without modifying the trie, ignoring all subgraphs that
+    /// contain an `excluded` vertex, and calls `on_match` for every occurrence found.
+    pub fn visit_matches_excluding<F: FnMut(&GtrieNode, &[usize])>(
+        &self,
+        graph: &Bitgraph,
+        excluded: &FixedBitSet,
+        on_match: F,
+    ) {
+        let mut scratch = CensusScratch::excluding(graph.n_nodes(), excluded);
+        self.visit_matches_with_scratch(graph, &mut scratch, on_match);
+    }
+
+    /// The traversal behind every census: calls `on_match` for each occurrence
+    /// avoiding the vertices of the blacklist of `scratch`.
+    fn visit_matches_with_scratch<G: Neighborhood, F: FnMut(&GtrieNode, &[usize])>(
+        &self,
+        graph: &G,
+        scratch: &mut CensusScratch,
+        mut on_match: F,
+    ) {
+        for c in self.root.iter_children() {
+            match_child_shared(
+                c,
+                &mut scratch.used,
+                &mut scratch.candidates,
+                &mut scratch.blacklist,
+                graph,
+                &mut on_match,
+            )
+        }
+    }
+
+    /// Calls `on_match` for each occurrence whose first vertex is `v`, so that
+    /// a census can be split or interrupted between starting vertices.
+    fn visit_matches_from<G: Neighborhood, F: FnMut(&GtrieNode, &[usize])>(
+        &self,
+        graph: &G,
+        v: usize,
+        scratch: &mut CensusScratch,
+        on_match: &mut F,
+    ) {
+        for c in self.root.iter_children() {
+            extend_shared(
+                c,
+                v,
+                &mut scratch.used,
+                &mut scratch.candidates,
+                &mut scratch.blacklist,
+                graph,
+                on_match,
+            );
+        }
+    }
+
+    /// Performs the census of the graph and then, for each vertex, the census of
+    /// the graph with that vertex removed.
+    ///
+    /// Returns the total number of subgraphs found with each vertex removed as
+    /// `(vertex, total_subgraphs)`. The vertex removals reuse the exclusion
+    /// mechanism of the census and do not affect the frequencies of the trie,
+    /// which reflect the full graph.
+    #[allow(dead_code)]
+    pub fn census_leave_one_out(&mut self, graph: &Bitgraph) -> Vec<(usize, usize)> {
+        self.census(graph);
+        let mut excluded = FixedBitSet::with_capacity(graph.n_nodes());
+        (0..graph.n_nodes())
+            .map(|v| {
+                let mut total_subgraphs = 0;
+                excluded.insert(v);
+                self.visit_matches_excluding(graph, &excluded, |_, _| total_subgraphs += 1);
+                excluded.set(v, false);
+                (v, total_subgraphs)
+            })
+            .collect()
+    }
+
+    /// Explains whether a specific set of vertices is counted by the census,
+    /// reporting either the motif it matches or where matching fails.
+    pub fn explain_vertices(&self, graph: &Bitgraph, vertices: &[usize]) -> Explanation {
+        explain(&self.root, graph, vertices)
+    }
+
+    #[allow(dead_code)]
+    pub fn max_depth(&self) -> usize {
+        self.max_depth
+    }
+
+    /// The number of nodes of the trie, excluding its root.
+    pub fn node_count(&self) -> usize {
+        self.root.count_nodes() - 1
+    }
+
+    /// The depth of the deepest node of the trie, at most `max_depth`.
+    pub fn depth(&self) -> usize {
+        self.root.deepest()
+    }
+
+    /// The number of graph nodes of the trie, numbered from 0 by
+    /// `GtrieNode::leaf`.
+    pub fn n_leaves(&self) -> usize {
+        self.n_leaves
+    }
+
+    /// The repr of each graph node, indexed by its `GtrieNode::leaf`.
+    pub fn leaf_reprs(&self) -> Vec<&str> {
+        let mut reprs = vec![""; self.n_leaves];
+        let mut stack = vec![&self.root];
+        while let Some(node) = stack.pop() {
+            if node.is_graph() {
+                reprs[node.leaf()] = node.repr().unwrap_or_default();
+            }
+            stack.extend(node.iter_children());
+        }
+        reprs
+    }
+
+    /// The root of the trie, for driving custom traversals with the census primitives.
+    pub fn root(&self) -> &GtrieNode {
+        &self.root
+    }
+
+    pub fn total_subgraphs(&self) -> usize {
+        self.total_subgraphs
+    }
+
+    /// Zeroes the counts of the last censuses, so that a loaded gtrie can be
+    /// reused for the census of another graph.
+    pub fn reset_counts(&mut self) {
+        self.root.reset_frequency();
+        self.total_subgraphs = 0;
+    }
+
+    #[allow(dead_code)]
+    pub fn get_nonzero(&self) -> HashMap<String, usize> {
+        let mut map = HashMap::new();
+        self.root.get_nonzero(&mut map);
+        map
+    }
+
+    /// Copies the frequency of every motif and the total number of subgraphs
+    /// into a standalone result set.
+    pub fn extract_results(&self) -> CensusResults {
+        let results = self.results();
+        CensusResults {
+            max_depth: self.max_depth,
+            total_subgraphs: self.total_subgraphs,
+            counts: results
+                .into_iter()
+                .map(|(repr, freq)| (repr.to_string(), freq))
+                .collect(),
+        }
+    }
+
+    /// Returns the distinct reprs of the graph nodes in traversal order.
+    pub fn reprs(&self) -> Vec<&str> {
+        let mut reprs = Vec::new();
+        self.root.collect_reprs(&mut reprs);
+        let mut seen = HashSet::with_capacity(reprs.len());
+        reprs.retain(|repr| seen.insert(*repr));
+        reprs
+    }
+
+    /// Returns the repr and frequency of every motif in traversal order.
+    ///
+    /// Graph nodes sharing a repr, such as the two orientations of a motif in
+    /// a trie merging reversals, are reported once with their frequencies summed.
+    pub fn results(&self) -> Vec<(&str, usize)> {
+        let mut results = Vec::new();
+        self.root.collect_results(&mut results);
+        merge_by_repr(results, |(repr, _)| repr, |(_, freq)| freq)
+    }
+
+    /// Summarizes the motifs found by the last census.
+    pub fn summary(&self) -> CensusSummary {
+        let nonzero = self.get_nonzero();
+        let most_frequent = nonzero
+            .iter()
+            .max_by(|a, b| a.1.cmp(b.1).then_with(|| b.0.cmp(a.0)));
+        CensusSummary {
+            n_distinct: nonzero.len(),
+            total: self.total_subgraphs,
+            most_frequent_repr: most_frequent.map(|(repr, _)| repr.clone()),
+            most_frequent_count: most_frequent.map_or(0, |(_, count)| *count),
+        }
+    }
+
+    /// Returns the repr and frequency of every motif by decreasing frequency,
+    /// ties broken by repr.
+    pub fn ranked_results(&self) -> Vec<(String, usize)> {
+        let mut results = self
+            .results()
+            .into_iter()
+            .map(|(repr, freq)| (repr.to_string(), freq))
+            .collect::<Vec<_>>();
+        results.sort_unstable_by(|a, b| b.1.cmp(&a.1).then_with(|| a.0.cmp(&b.0)));
+        results
+    }
+
+    /// Returns the motif with the given repr, also matching the undirected
+    /// graph6 representation of a symmetric motif.
+    pub fn motif(&self, repr: &str) -> Option<MotifRef<'_>> {
+        self.motifs().into_iter().find(|motif| {
+            motif.repr() == repr || io::undirected_repr(motif.repr()).is_some_and(|r| r == repr)
+        })
+    }
+
+    /// Returns every motif of the trie in traversal order.
+    pub fn motifs(&self) -> Vec<MotifRef<'_>> {
+        let mut motifs = Vec::new();
+        let mut path = Vec::with_capacity(self.max_depth);
+        for child in self.root.iter_children() {
+            Self::collect_motifs(child, self.is_dir, &mut path, &mut motifs);
+        }
+        motifs
+    }
+
+    fn collect_motifs<'a>(
+        node: &'a GtrieNode,
+        is_dir: bool,
+        path: &mut Vec<&'a GtrieNode>,
+        motifs: &mut Vec<MotifRef<'a>>,
+    ) {
+        path.push(node);
+        if node.repr().is_some() {
+            motifs.push(MotifRef::new(path.clone(), is_dir));
+        }
+        for child in node.iter_children() {
+            Self::collect_motifs(child, is_dir, path, motifs);
+        }
+        path.pop();
+    }
+
+    /// Returns the frequency of every motif ordered by sorted repr, so that the
+    /// fingerprints of graphs censused with the same trie are aligned.
+    ///
+    /// With `normalized` each frequency is divided by the total number of
+    /// subgraphs found, giving the concentrations of the motifs.
+    pub fn fingerprint(&self, normalized: bool) -> Vec<f64> {
+        let mut results = self.results();
+        results.sort_unstable_by(|a, b| a.0.cmp(b.0));
+        let total = if normalized {
+            self.total_subgraphs.max(1) as f64
+        } else {
+            1.0
+        };
+        results
+            .into_iter()
+            .map(|(_, freq)| freq as f64 / total)
+            .collect()
+    }
+
+    /// Checks if the trie contains all connected non-isomorphic directed graphs
+    /// of the given size by comparing its number of graphs of that size to the
+    /// known number of classes. Sizes without a known class count are never complete.
+    #[allow(dead_code)]
+    pub fn is_complete(&self, size: usize) -> bool {
+        match size
+            .checked_sub(1)
+            .and_then(|i| CONNECTED_DIGRAPH_CLASSES.get(i))
+        {
+            Some(n_classes) => self.root.count_graphs_at_depth(size) == *n_classes,
+            None => false,
+        }
+    }
+
+    /// Calculates the concentration of each nonzero motif, i.e. its frequency
+    /// relative to the total number of subgraphs found.
+    ///
+    /// The total is only the number of *all* connected subgraphs of the size
+    /// if the trie is complete, so a warning is written to stderr otherwise.
+    #[allow(dead_code)]
+    pub fn concentrations(&self) -> HashMap<String, f64> {
+        if !self.is_complete(self.max_depth) {
+            eprintln!(
+                "WARNING: gtrie does not contain all motifs of size {}; concentrations are relative to the motifs present only.",
+                self.max_depth
+            );
+        }
+        self.get_nonzero()
+            .into_iter()
+            .map(|(repr, freq)| (repr, concentration(freq, self.total_subgraphs)))
+            .collect()
+    }
+}
+
+/// The share of `total` subgraphs that are occurrences of a motif of the
+/// given frequency, zero for an empty census.
+pub fn concentration(frequency: usize, total: usize) -> f64 {
+    frequency as f64 / total.max(1) as f64
+}
+
+/// The isomorphisms from a motif labeled by trie position onto the graph of
+/// its repr, or from its reversal if it was merged with its reversal.
+fn repr_isomorphisms(motif: &Bitgraph, repr: &str) -> Vec<Vec<usize>> {
+    let Ok(target) = io::load_repr(repr) else {
+        return Vec::new();
+    };
+    let target = Bitgraph::from_graph(&target);
+    let found = isomorphisms(motif, &target);
+    if !found.is_empty() {
+        return found;
+    }
+    let mut reversed = motif.to_graph();
+    reversed.reverse();
+    isomorphisms(&Bitgraph::from_graph(&reversed), &target)
+}
+
+/// A sampling reservoir, the min-heap of the keys of its occurrences.
+type Reservoir = BinaryHeap<Reverse<(SampleKey, Vec<usize>)>>;
+
+/// The key of an occurrence in a sampling reservoir, totally ordered so that
+/// reservoirs can be heaps.
+#[derive(Debug, Clone, Copy)]
+struct SampleKey(f64);
+impl PartialEq for SampleKey {
+    fn eq(&self, other: &Self) -> bool {
+        self.cmp(other) == Ordering::Equal
+    }
+}
+impl Eq for SampleKey {}
+impl PartialOrd for SampleKey {
+    fn partial_cmp(&self, other: &Self) -> Option<Ordering> {
+        Some(self.cmp(other))
+    }
+}
+impl Ord for SampleKey {
+    fn cmp(&self, other: &Self) -> Ordering {
+        self.0.total_cmp(&other.0)
+    }
+}
+
+/// Merges the entries sharing a repr into the first of them, summing their
+/// frequencies and keeping the order of first appearance.
+fn merge_by_repr<'a, T>(
+    entries: Vec<T>,
+    repr: impl Fn(&T) -> &'a str,
+    freq: impl Fn(&mut T) -> &mut usize,
+) -> Vec<T> {
+    let mut index: HashMap<&str, usize> = HashMap::with_capacity(entries.len());
+    let mut merged: Vec<T> = Vec::with_capacity(entries.len());
+    for mut entry in entries {
+        match index.get(repr(&entry)) {
+            Some(i) => {
+                let count = *freq(&mut entry);
+                *freq(&mut merged[*i]) += count;
+            }
+            None => {
+                index.insert(repr(&entry), merged.len());
+                merged.push(entry);
+            }
+        }
+    }
+    merged
+}
+
+#[cfg(test)]
+mod testing {
+    use super::*;
+    use crate::{random::XorShift, reference::random_graph};
+    use hashbrown::HashSet;
+    use petgraph::{Directed, Graph};
+
+    #[test]
+    fn complete_gtrie() {
+        let gtrie = Gtrie::read_from_file("example/gtries/dir3.gt").unwrap();
+        assert!(gtrie.is_complete(3));
+        assert!(!gtrie.is_complete(4));
+    }
+
+    #[test]
+    fn leave_one_out() {
+        let graph =
+            crate::io::load_numeric_graph("example/graphs/example.txt", true, false).unwrap();
+        let graph = Bitgraph::from_graph(&graph);
+        let mut gtrie = Gtrie::read_from_file("example/gtries/dir4.gt").unwrap();
+        let results = gtrie.census_leave_one_out(&graph);
+        assert_eq!(results.len(), graph.n_nodes());
+        assert_eq!(gtrie.total_subgraphs(), 24);
+
+        for (v, total) in results {
+            let remaining = (0..graph.n_nodes()).filter(|u| *u != v).collect::<Vec<_>>();
+            let pruned = graph.induced_subgraph(&remaining);
+            let mut counts = HashMap::new();
+            assert_eq!(gtrie.census_into(&pruned, &mut counts), total);
+        }
+    }
+
+    #[test]
+    fn undirected_results() {
+        let edges = vec![
+            (0, 1),
+            (1, 0),
+            (1, 2),
+            (2, 1),
+            (2, 0),
+            (0, 2),
+            (2, 3),
+            (3, 2),
+        ];
+        let graph: Graph<(), (), Directed> = Graph::from_edges(&edges);
+        let graph = Bitgraph::from_graph(&graph).into_undirected();
+        let mut gtrie = Gtrie::read_from_file("example/gtries/dir3.gt").unwrap();
+        gtrie.census(&graph);
+
+        let results = gtrie.undirected_results();
+        assert_eq!(results.len(), 2);
+        assert!(results.iter().all(|(repr, _)| !repr.starts_with('&')));
+        let mut counts = results.iter().map(|(_, freq)| *freq).collect::<Vec<_>>();
+        counts.sort();
+        assert_eq!(counts, vec![1, 2]);
+    }
+
+    #[test]
+    fn incomplete_gtrie() {
+        let mut gtrie = Gtrie::new(3);
+        let graph: Graph<(), (), Directed> = Graph::from_edges([(0, 1), (1, 2)]);
+        gtrie.insert(&Bitgraph::from_graph(&graph), None, None, None);
+        assert!(!gtrie.is_complete(3));
+        assert!(!gtrie.is_complete(0));
+    }
+
+    #[test]
+    fn loops_do_not_affect_census() {
+        let edges = "1 2\n2 3\n3 1\n3 4\n1 1\n3 3\n4 4\n";
+        let mut totals = Vec::new();
+        for include_loops in [true, false] {
+            let mut buffer = std::io::Cursor::new(edges);
+            let graph =
+                io::load_numeric_graph_from_buffer(&mut buffer, include_loops, false).unwrap();
+            assert_eq!(graph.edge_count(), if include_loops { 7 } else { 4 });
+
+            let graph = Bitgraph::from_graph(&graph);
+            let mut gtrie = Gtrie::read_from_file("example/gtries/dir3.gt").unwrap();
+            gtrie.census(&graph);
+            totals.push((gtrie.total_subgraphs(), gtrie.get_nonzero()));
+        }
+        assert_eq!(totals[0].0, 3);
+        assert_eq!(totals[0], totals[1]);
+    }
+
+    #[test]
+    fn occurrences_are_unique_vertex_sets() {
+        let gtrie = Gtrie::read_from_file("example/gtries/dir4.gt").unwrap();
+        for seed in 0..20 {
+            let mut rng = XorShift::new(seed);
+            let graph = random_graph(&mut rng, 9, 0.3);
+            let occurrences = gtrie.occurrences(&graph);
+
+            let mut counts = HashMap::new();
+            gtrie.census_into(&graph, &mut counts);
+
+            let mut seen = HashSet::new();
+            for (repr, tuples) in occurrences {
+                assert_eq!(tuples.len(), counts[&repr]);
+                for tuple in tuples {
+                    let mut set = tuple.clone();
+                    set.sort_unstable();
+                    assert!(seen.insert(set), "{:?} reported twice", tuple);
+                }
+            }
+        }
+    }
+
+    #[test]
+    fn compare_graphs() {
+        let graph =
+            crate::io::load_numeric_graph("example/graphs/example.txt", true, false).unwrap();
+        let graph = Bitgraph::from_graph(&graph);
+        let empty = Bitgraph::with_capacity(graph.n_nodes(), true);
+        let gtrie = Gtrie::read_from_file("example/gtries/dir3.gt").unwrap();
+
+        let rows = gtrie.compare(&graph, &empty);
+        assert_eq!(rows.len(), gtrie.reprs().len());
+        assert_eq!(rows.iter().map(|(_, a, _)| a).sum::<usize>(), 16);
+        assert!(rows.iter().all(|(_, _, b)| *b == 0));
+
+        let rows = gtrie.compare(&graph, &graph);
+        assert!(rows.iter().all(|(_, a, b)| a == b));
+    }
+
+    #[test]
+    fn orbits_align_with_occurrences() {
+        let mut gtrie = Gtrie::new(3);
+        // An out-star, where both leaves share a role.
+        gtrie.insert_canonical(&Graph::from_edges([(0, 1), (0, 2)]));
+        // A feed-forward loop, where every vertex has a distinct role.
+        gtrie.insert_canonical(&Graph::from_edges([(0, 1), (0, 2), (1, 2)]));
+
+        let edges = [(0, 1), (0, 2), (1, 2), (5, 6), (5, 7)];
+        let graph: Graph<(), (), Directed> = Graph::from_edges(edges);
+        let graph = Bitgraph::from_graph(&graph);
+
+        let mut n_matches = 0;
+        gtrie.visit_matches(&graph, |node, used| {
+            n_matches += 1;
+            let orbits = node.orbits().unwrap();
+            if let Some(center) = used.iter().position(|v| *v == 5) {
+                let leaves = (0..3).filter(|i| *i != center).collect::<Vec<_>>();
+                assert_eq!(orbits[leaves[0]], orbits[leaves[1]]);
+                assert_ne!(orbits[center], orbits[leaves[0]]);
+            } else {
+                let roles = orbits.iter().collect::<HashSet<_>>();
+                assert_eq!(roles.len(), 3);
+            }
+        });
+        assert_eq!(n_matches, 2);
+
+        let mut roles = Vec::new();
+        gtrie.census_with_orbits(&graph, |_, used, orbits| {
+            let orbits = orbits.unwrap();
+            roles.push(
+                used.iter()
+                    .zip(orbits)
+                    .map(|(u, o)| (*u, *o))
+                    .collect::<Vec<_>>(),
+            );
+        });
+        let star = roles
+            .iter()
+            .find(|r| r.iter().any(|(u, _)| *u == 5))
+            .unwrap();
+        let orbit = |v| star.iter().find(|(u, _)| *u == v).unwrap().1;
+        assert_eq!(orbit(6), orbit(7));
+        assert_ne!(orbit(5), orbit(6));
+    }
+
+    #[test]
+    fn census_until_deadline() {
+        let graph = crate::io::load_numeric_graph("example/graphs/yeast.txt", true, false).unwrap();
+        let graph = Bitgraph::from_graph(&graph);
+        let mut reference = Gtrie::read_from_file("example/gtries/dir3.gt").unwrap();
+        reference.census(&graph);
+
+        let far = Instant::now() + std::time::Duration::from_secs(3600);
+        let mut gtrie = Gtrie::read_from_file("example/gtries/dir3.gt").unwrap();
+        assert!(gtrie.census_until(&graph, far));
+        assert_eq!(gtrie.get_nonzero(), reference.get_nonzero());
+
+        let mut counts = HashMap::new();
+        let total = gtrie.census_into_until(&graph, &mut counts, far);
+        assert_eq!(total, Some(reference.total_subgraphs()));
+
+        let mut gtrie = Gtrie::read_from_file("example/gtries/dir3.gt").unwrap();
+        assert!(!gtrie.census_until(&graph, Instant::now()));
+        assert_eq!(gtrie.total_subgraphs(), 0);
+        assert_eq!(
+            gtrie.census_into_until(&graph, &mut counts, Instant::now()),
+            None
+        );
+    }
+
+    #[test]
+    fn census_with_edge_matches_filtered_occurrences() {
+        let graph = crate::io::load_numeric_graph("example/graphs/yeast.txt", true, false).unwrap();
+        let graph = Bitgraph::from_graph(&graph);
+        let reference = Gtrie::read_from_file("example/gtries/dir4.gt").unwrap();
+        let occurrences = reference.occurrences(&graph);
+
+        for (a, b) in graph.edges().into_iter().step_by(97) {
+            let mut gtrie = Gtrie::read_from_file("example/gtries/dir4.gt").unwrap();
+            gtrie.census_with_edge(&graph, a, b);
+
+            let expected = occurrences
+                .iter()
+                .map(|(repr, sets)| {
+                    let count = sets
+                        .iter()
+                        .filter(|used| used.contains(&a) && used.contains(&b))
+                        .count();
+                    (repr.clone(), count)
+                })
+                .filter(|(_, count)| *count > 0)
+                .collect::<HashMap<_, _>>();
+            assert_eq!(gtrie.get_nonzero(), expected);
+            assert_eq!(gtrie.total_subgraphs(), expected.values().sum::<usize>());
+        }
+
+        // Without the edge nothing is counted.
+        let (a, b) = graph.edges()[0];
+        let mut gtrie = Gtrie::read_from_file("example/gtries/dir4.gt").unwrap();
+        if !graph.is_connected(b, a) {
+            gtrie.census_with_edge(&graph, b, a);
+            assert_eq!(gtrie.total_subgraphs(), 0);
+        }
+    }
+
+    #[test]
+    fn gzipped_files_roundtrip() {
+        let original = Gtrie::read_from_file("example/gtries/dir4.gt").unwrap();
+        let dir = std::env::temp_dir();
+        let msgpack = dir.join("graphtries_format.gt.gz");
+        let json = dir.join("graphtries_format.json.gz");
+        for path in [&msgpack, &json].map(|path| path.to_str().unwrap()) {
+            original.write_to_file(path).unwrap();
+            let bytes = std::fs::read(path).unwrap();
+            assert_eq!(bytes[..2], [0x1f, 0x8b]);
+            let gtrie = Gtrie::read_from_file(path).unwrap();
+            std::fs::remove_file(path).unwrap();
+            assert_eq!(gtrie.structural_hash(), original.structural_hash());
+        }
+        assert_eq!(
+            GtrieFormat::from_path("graphtries_format.json.gz"),
+            GtrieFormat::Json
+        );
+    }
+
+    #[test]
+    fn text_format_roundtrip() {
+        for path in ["example/gtries/dir3.gt", "example/gtries/dir4.gt"] {
+            let gtrie = Gtrie::read_from_file(path).unwrap();
+            let mut text = Vec::new();
+            gtrie.write_text_format(&mut text).unwrap();
+
+            let decoded = Gtrie::read_text_format(text.as_slice()).unwrap();
+            let mut rewritten = Vec::new();
+            decoded.write_text_format(&mut rewritten).unwrap();
+            assert_eq!(
+                String::from_utf8(rewritten).unwrap(),
+                String::from_utf8(text).unwrap()
+            );
+
+            let graph =
+                crate::io::load_numeric_graph("example/graphs/yeast.txt", true, false).unwrap();
+            let graph = Bitgraph::from_graph(&graph);
+            let mut expected = HashMap::new();
+            let mut counts = HashMap::new();
+            gtrie.census_into(&graph, &mut expected);
+            decoded.census_into(&graph, &mut counts);
+            assert_eq!(counts, expected);
+        }
+
+        assert!(Gtrie::read_text_format("gtrie 3\n    [0][0]\n".as_bytes()).is_err());
+        assert!(Gtrie::read_text_format("gtrie 3\n[0][0] bogus\n".as_bytes()).is_err());
+    }
+
+    #[test]
+    fn results_by_size_match_single_sizes() {
+        let graph = crate::io::load_numeric_graph("example/graphs/yeast.txt", true, false).unwrap();
+        let graph = Bitgraph::from_graph(&graph);
+        let by_size = crate::generate::connected_digraphs(4);
+
+        let mut mixed = Gtrie::new(4);
+        let mut totals = Vec::new();
+        for size in 3..=4 {
+            let mut gtrie = Gtrie::new(size);
+            for motif in by_size[size - 1].iter() {
+                gtrie.insert_canonical(motif);
+                mixed.insert_canonical(motif);
+            }
+            gtrie.census(&graph);
+            totals.push((size, gtrie.total_subgraphs()));
+        }
+        mixed.census(&graph);
+
+        let results = mixed.results_by_size();
+        assert_eq!(results.len(), 2);
+        for ((size, motifs), (expected_size, total)) in results.iter().zip(totals) {
+            assert_eq!(*size, expected_size);
+            assert_eq!(motifs.len(), by_size[size - 1].len());
+            assert_eq!(motifs.iter().map(|(_, f)| f).sum::<usize>(), total);
+        }
+    }
+
+    #[test]
+    fn insert_reports_duplicates() {
+        let mut gtrie = Gtrie::new(3);
+        let path: Graph<(), (), Directed> = Graph::from_edges([(0, 1), (1, 2)]);
+        let relabeled: Graph<(), (), Directed> = Graph::from_edges([(2, 0), (0, 1)]);
+        let (repr, is_new) = gtrie.insert_canonical(&path);
+        assert!(is_new);
+        assert_eq!(gtrie.insert_canonical(&relabeled), (repr, false));
+
+        // A graph ending at an existing prefix is still new.
+        let edge: Graph<(), (), Directed> = Graph::from_edges([(0, 1)]);
+        assert!(gtrie.insert_canonical(&edge).1);
+        assert!(!gtrie.insert_canonical(&edge).1);
+    }
+
+    #[test]
+    fn embeddings_scale_by_automorphisms() {
+        let graphs: [(&[(u32, u32)], usize); 3] = [
+            (&[(0, 1), (1, 2)], 1),
+            (&[(0, 1), (1, 2), (2, 0)], 3),
+            (&[(0, 1), (1, 0), (1, 2), (2, 1), (2, 0), (0, 2)], 6),
+        ];
+        for (edges, aut_size) in graphs {
+            let graph: Graph<(), (), Directed> = Graph::from_edges(edges.iter().copied());
+            let mut gtrie = Gtrie::read_from_file("example/gtries/dir3.gt").unwrap();
+            gtrie.census(&Bitgraph::from_graph(&graph));
+            let counts = gtrie
+                .embedding_counts()
+                .unwrap()
+                .into_iter()
+                .filter(|(_, freq, _)| *freq > 0)
+                .collect::<Vec<_>>();
+            assert_eq!(counts.len(), 1);
+            assert_eq!(counts[0].1, 1);
+            assert_eq!(counts[0].2, aut_size);
+        }
+    }
+
+    #[test]
+    fn recompute_missing_reprs() {
+        let mut expected = Gtrie::new(4);
+        let mut gtrie = Gtrie::new(4);
+        for graphs in crate::generate::connected_digraphs(4).iter().skip(2) {
+            for graph in graphs {
+                expected.insert_canonical(graph);
+                let (bgraph, canon) = canonicalize(graph);
+                gtrie.insert(&bgraph, canon.conditions(), Some(&canon.orbits), None);
+            }
+        }
+        assert!(gtrie.reprs().is_empty());
+
+        assert_eq!(gtrie.recompute_reprs(), 13 + 199);
+        assert_eq!(gtrie.reprs(), expected.reprs());
+        assert_eq!(gtrie.recompute_reprs(), 0);
+    }
+
+    #[test]
+    fn cooccurrence_counts_overlapping_pairs() {
+        let mut gtrie = Gtrie::read_from_file("example/gtries/dir3.gt").unwrap();
+        for seed in 0..10 {
+            let mut rng = XorShift::new(seed);
+            let graph = random_graph(&mut rng, 8, 0.3);
+            let occurrences = gtrie.occurrences(&graph);
+            let reprs = gtrie
+                .reprs()
+                .into_iter()
+                .map(String::from)
+                .collect::<Vec<_>>();
+            let empty = Vec::new();
+            for a in &reprs {
+                for b in &reprs {
+                    let occurrences_a = occurrences.get(a).unwrap_or(&empty);
+                    let occurrences_b = occurrences.get(b).unwrap_or(&empty);
+                    let mut expected = occurrences_a
+                        .iter()
+                        .flat_map(|x| occurrences_b.iter().map(move |y| (x, y)))
+                        .filter(|(x, y)| x != y && x.iter().any(|v| y.contains(v)))
+                        .count();
+                    if a == b {
+                        expected /= 2;
+                    }
+                    assert_eq!(gtrie.census_cooccurrence(&graph, a, b), expected);
+                }
+            }
+        }
+
+        // The two paths of a directed path on four vertices share an edge.
+        let mut path = Bitgraph::with_capacity(4, true);
+        for u in 0..3 {
+            path.insert_edge(u, u + 1);
+        }
+        let mut gtrie = Gtrie::read_from_file("example/gtries/dir3.gt").unwrap();
+        let repr = gtrie.occurrences(&path).into_keys().next().unwrap();
+        assert_eq!(gtrie.census_cooccurrence(&path, &repr, &repr), 1);
+        assert_eq!(gtrie.get_nonzero()[&repr], 2);
+    }
+
+    #[test]
+    fn structural_hash_ignores_frequencies() {
+        let graph =
+            crate::io::load_numeric_graph("example/graphs/example.txt", true, false).unwrap();
+        let graph = Bitgraph::from_graph(&graph);
+        let mut gtrie = Gtrie::read_from_file("example/gtries/dir3.gt").unwrap();
+        let hash = gtrie.structural_hash();
+        gtrie.census(&graph);
+        assert_eq!(gtrie.structural_hash(), hash);
+
+        let other = Gtrie::read_from_file("example/gtries/dir4.gt").unwrap();
+        assert_ne!(other.structural_hash(), hash);
+    }
+
+    #[test]
+    fn insert_assume_canonical_matches_canonical() {
+        let mut canonical = Gtrie::new(4);
+        let mut as_is = Gtrie::new(4);
+        for graphs in crate::generate::connected_digraphs(4).iter().skip(2) {
+            for graph in graphs {
+                let (repr, _) = canonical.insert_canonical(graph);
+                let graph = io::load_repr(&repr).unwrap();
+                assert_eq!(as_is.insert_assume_canonical(&graph), (repr, true));
+            }
+        }
+
+        let text = |gtrie: &Gtrie| {
+            let mut text = Vec::new();
+            gtrie.write_text_format(&mut text).unwrap();
+            String::from_utf8(text).unwrap()
+        };
+        assert_eq!(text(&as_is), text(&canonical));
+    }
+
+    #[test]
+    fn census_reporting_matches_census() {
+        let graph = crate::io::load_numeric_graph("example/graphs/yeast.txt", true, false).unwrap();
+        let graph = Bitgraph::from_graph(&graph);
+        let mut expected = Gtrie::read_from_file("example/gtries/dir4.gt").unwrap();
+        expected.census(&graph);
+
+        let mut gtrie = Gtrie::read_from_file("example/gtries/dir4.gt").unwrap();
+        let report = gtrie.census_reporting(&graph);
+        assert_eq!(report.total_subgraphs, expected.total_subgraphs());
+        assert_eq!(gtrie.total_subgraphs(), expected.total_subgraphs());
+        assert_eq!(gtrie.get_nonzero(), expected.get_nonzero());
+        assert!(report.candidates_tested >= report.total_subgraphs);
+        assert!(report.nodes_visited > gtrie.reprs().len());
+    }
+
+    #[test]
+    fn census_with_scratch_matches_census() {
+        let mut scratch = CensusScratch::new(0);
+        let mut reused = Gtrie::read_from_file("example/gtries/dir3.gt").unwrap();
+        let mut fresh = Gtrie::read_from_file("example/gtries/dir3.gt").unwrap();
+        let mut rng = XorShift::new(3);
+        for _ in 0..100 {
+            let n = 3 + rng.below(3);
+            let graph = random_graph(&mut rng, n, 0.4);
+            reused.census_with_scratch(&graph, &mut scratch);
+            fresh.census(&graph);
+        }
+        assert_eq!(reused.total_subgraphs(), fresh.total_subgraphs());
+        assert_eq!(reused.get_nonzero(), fresh.get_nonzero());
+    }
+
+    #[test]
+    fn census_checked_directedness() {
+        let mut triangle = Bitgraph::with_capacity(3, true);
+        for (u, v) in [(0, 1), (1, 0), (1, 2), (2, 1), (0, 2), (2, 0)] {
+            triangle.insert_edge(u, v);
+        }
+        let mut undirected = Gtrie::new(3);
+        let conditions = Conditions::from_pairs(&[(0, 1), (0, 2), (1, 2)]);
+        undirected.insert(
+            &triangle.clone().into_undirected(),
+            Some(&conditions),
+            None,
+            None,
+        );
+        assert!(!undirected.is_dir);
+
+        let graph =
+            crate::io::load_numeric_graph("example/graphs/example.txt", true, false).unwrap();
+        let graph = Bitgraph::from_graph(&graph);
+        let err = undirected.census_checked(&graph).unwrap_err();
+        assert!(err.to_string().contains("directed graph"));
+        assert!(undirected
+            .census_checked(&triangle.into_undirected())
+            .is_ok());
+        assert_eq!(undirected.total_subgraphs(), 1);
+
+        // Undirected graphs are censused by directed tries.
+        let mut directed = Gtrie::read_from_file("example/gtries/dir3.gt").unwrap();
+        assert!(directed.is_dir);
+        let symmetric = crate::results::census_edges(&directed, &[(0, 1), (1, 2)], false);
+        assert_eq!(symmetric.total_subgraphs, 1);
+        assert!(directed.census_checked(&graph).is_ok());
+
+        // The flag survives both formats.
+        let mut buffer = Vec::new();
+        undirected.write_to_buffer(&mut buffer).unwrap();
+        let decoded: Gtrie = rmp_serde::from_slice(&buffer).unwrap();
+        assert!(!decoded.is_dir);
+        let mut text = Vec::new();
+        undirected.write_text_format(&mut text).unwrap();
+        assert!(text.starts_with(b"gtrie 3 undirected\n"));
+        assert!(!Gtrie::read_text_format(text.as_slice()).unwrap().is_dir);
+    }
+
+    #[test]
+    fn validate_structure() {
+        for path in ["example/gtries/dir3.gt", "example/gtries/dir4.gt"] {
+            assert!(Gtrie::read_from_file(path).unwrap().validate().is_ok());
+        }
+
+        let mut gtrie = Gtrie::read_from_file("example/gtries/dir3.gt").unwrap();
+        gtrie.max_depth = 2;
+        let err = gtrie.validate().unwrap_err();
+        assert!(err.to_string().contains("maximum depth 2"));
+        gtrie.max_depth = 0;
+        assert!(gtrie.validate().is_err());
+
+        // A motif placed at the wrong depth is rejected on load.
+        let mut gtrie = Gtrie::new(3);
+        gtrie.insert_canonical(&Graph::from_edges([(0, 1), (1, 2)]));
+        gtrie
+            .root
+            .iter_children_mut()
+            .next()
+            .unwrap()
+            .set_repr(Some("&BH?".to_string()));
+        let path = std::env::temp_dir().join("graphtries_invalid.json");
+        let path = path.to_str().unwrap();
+        gtrie.write_to_file(path).unwrap();
+        let err = Gtrie::read_from_file(path).unwrap_err();
+        std::fs::remove_file(path).unwrap();
+        assert!(err.to_string().contains("is at depth 1"), "{}", err);
+    }
+
+    #[test]
+    fn file_formats_roundtrip() {
+        let graph = io::load_numeric_graph("example/graphs/yeast.txt", true, false).unwrap();
+        let graph = Bitgraph::from_graph(&graph);
+        let mut expected = Gtrie::read_from_file("example/gtries/dir4.gt").unwrap();
+        expected.census(&graph);
+
+        let dir = std::env::temp_dir();
+        let json = dir.join("graphtries_format.json");
+        let forced_json = dir.join("graphtries_format_json.gt");
+        let msgpack = dir.join("graphtries_format.gt");
+        let paths = [&json, &forced_json, &msgpack].map(|path| path.to_str().unwrap());
+        let original = Gtrie::read_from_file("example/gtries/dir4.gt").unwrap();
+        original.write_to_file(paths[0]).unwrap();
+        original
+            .write_to_file_as(paths[1], GtrieFormat::Json)
+            .unwrap();
+        original.write_to_file(paths[2]).unwrap();
+
+        assert_eq!(std::fs::read(paths[0]).unwrap().first(), Some(&b'{'));
+        assert_eq!(std::fs::read(paths[1]).unwrap().first(), Some(&b'{'));
+        let mut buffer = Vec::new();
+        original.write_to_buffer(&mut buffer).unwrap();
+        assert_eq!(std::fs::read(paths[2]).unwrap(), buffer);
+        for path in paths {
+            let mut gtrie = Gtrie::read_from_file(path).unwrap();
+            std::fs::remove_file(path).unwrap();
+            assert_eq!(gtrie.structural_hash(), original.structural_hash());
+            gtrie.census(&graph);
+            assert_eq!(gtrie.total_subgraphs(), expected.total_subgraphs());
+            assert_eq!(gtrie.get_nonzero(), expected.get_nonzero());
+        }
+    }
+
+    #[test]
+    fn written_subgraphs_match_frequencies() {
+        let graph = crate::io::load_numeric_graph("example/graphs/yeast.txt", true, false).unwrap();
+        let graph = Bitgraph::from_graph(&graph);
+        let mut gtrie = Gtrie::new(3);
+        for motif in crate::generate::connected_digraphs(3)[2].iter() {
+            gtrie.insert_canonical(motif);
+        }
+
+        let mut buffer = Vec::new();
+        let excluded = FixedBitSet::with_capacity(graph.n_nodes());
+        let n_written = gtrie
+            .write_subgraphs(&graph, &excluded, &mut buffer)
+            .unwrap();
+
+        let mut counts = HashMap::new();
+        for line in String::from_utf8(buffer).unwrap().lines() {
+            *counts.entry(line.to_string()).or_insert(0) += 1;
+        }
+        gtrie.census(&graph);
+        assert_eq!(n_written, gtrie.total_subgraphs());
+        assert_eq!(counts, gtrie.get_nonzero());
+    }
+
+    #[test]
+    fn ap_modes_build_valid_tries() {
+        let graph = crate::io::load_numeric_graph("example/graphs/yeast.txt", true, false).unwrap();
+        let graph = Bitgraph::from_graph(&graph);
+        let motifs = crate::generate::connected_digraphs(5);
+
+        // Counts keyed by the default canonical repr, comparable across labelings.
+        let canonical_counts = |gtrie: &mut Gtrie| {
+            gtrie.census(&graph);
+            gtrie
+                .get_nonzero()
+                .into_iter()
+                .map(|(repr, count)| {
+                    let (_, canon) = canonicalize(&io::load_repr(&repr).unwrap());
+                    (canon.repr(), count)
+                })
+                .collect::<HashMap<_, _>>()
+        };
+
+        // Modes keeping prefixes connected census each motif as the shipped tries do.
+        for (size, path) in [(3, "example/gtries/dir3.gt"), (4, "example/gtries/dir4.gt")] {
+            let mut shipped = Gtrie::read_from_file(path).unwrap();
+            let expected = canonical_counts(&mut shipped);
+            assert!(!expected.is_empty());
+            for ap_mode in [ApMode::Auto, ApMode::Always] {
+                let mut gtrie = Gtrie::new(size);
+                for motif in motifs[size - 1].iter() {
+                    gtrie.insert_canonical_with(motif, ap_mode);
+                }
+                assert_eq!(canonical_counts(&mut gtrie), expected, "{:?}", ap_mode);
+
+                // Motif by motif, under the reprs the mode labels them with.
+                let relabeled = shipped
+                    .get_nonzero()
+                    .into_iter()
+                    .map(|(repr, count)| {
+                        let (_, canon) = canonicalize_with(&io::load_repr(&repr).unwrap(), ap_mode);
+                        (canon.repr(), count)
+                    })
+                    .collect::<HashMap<_, _>>();
+                assert_eq!(gtrie.get_nonzero(), relabeled, "{:?}", ap_mode);
+            }
+        }
+
+        // Without excluding articulation points some prefixes are disconnected.
+        let disconnected = motifs[4]
+            .iter()
+            .filter(|motif| {
+                let (bgraph, _) = canonicalize_with(motif, ApMode::Never);
+                !crate::isomorphism::prefixes_connected(bgraph.adjacency(), bgraph.n_nodes())
+            })
+            .count();
+        assert!(disconnected > 0);
+    }
+
+    #[test]
+    fn fingerprints_are_aligned() {
+        let yeast = crate::io::load_numeric_graph("example/graphs/yeast.txt", true, false).unwrap();
+        let example =
+            crate::io::load_numeric_graph("example/graphs/example.txt", true, false).unwrap();
+        let fingerprint = |graph, normalized| {
+            let mut gtrie = Gtrie::read_from_file("example/gtries/dir3.gt").unwrap();
+            gtrie.census(&Bitgraph::from_graph(graph));
+            (gtrie.fingerprint(normalized), gtrie.get_nonzero())
+        };
+
+        let (a, counts_a) = fingerprint(&yeast, false);
+        let (b, counts_b) = fingerprint(&example, false);
+        assert_eq!(a.len(), 13);
+        assert_eq!(a.len(), b.len());
+
+        let gtrie = Gtrie::read_from_file("example/gtries/dir3.gt").unwrap();
+        let mut reprs = gtrie.reprs();
+        reprs.sort_unstable();
+        for (idx, repr) in reprs.into_iter().enumerate() {
+            assert_eq!(a[idx], counts_a.get(repr).copied().unwrap_or(0) as f64);
+            assert_eq!(b[idx], counts_b.get(repr).copied().unwrap_or(0) as f64);
+        }
+
+        let (normalized, _) = fingerprint(&example, true);
+        assert!((normalized.iter().sum::<f64>() - 1.0).abs() < 1e-12);
+        assert_eq!(normalized.iter().map(|x| x * 16.0).collect::<Vec<_>>(), b);
+    }
+
+    #[test]
+    fn shards_add_up_to_census() {
+        let graph = crate::io::load_numeric_graph("example/graphs/yeast.txt", true, false).unwrap();
+        let graph = Bitgraph::from_graph(&graph);
+        let mut expected = Gtrie::read_from_file("example/gtries/dir4.gt").unwrap();
+        expected.census(&graph);
+
+        let n = graph.n_nodes();
+        let mut totals: HashMap<String, usize> = HashMap::new();
+        let mut total_subgraphs = 0;
+        for range in [0..n / 3, n / 3..2 * n / 3, 2 * n / 3..n] {
+            let mut shard = Gtrie::read_from_file("example/gtries/dir4.gt").unwrap();
+            shard.census_shard(&graph, range);
+            assert!(shard.total_subgraphs() < expected.total_subgraphs());
+            total_subgraphs += shard.total_subgraphs();
+            for (repr, count) in shard.get_nonzero() {
+                *totals.entry(repr).or_default() += count;
+            }
+        }
+        assert_eq!(total_subgraphs, expected.total_subgraphs());
+        assert_eq!(totals, expected.get_nonzero());
+    }
+
+    #[test]
+    fn census_to_sink_matches_census() {
+        let graph = crate::io::load_numeric_graph("example/graphs/yeast.txt", true, false).unwrap();
+        let graph = Bitgraph::from_graph(&graph);
+        let mut expected = Gtrie::read_from_file("example/gtries/dir3.gt").unwrap();
+        expected.census(&graph);
+
+        let mut gtrie = Gtrie::read_from_file("example/gtries/dir3.gt").unwrap();
+        let mut calls = Vec::new();
+        gtrie.census_to_sink(&graph, "yeast", &mut |id, repr, freq| {
+            calls.push((id.to_string(), repr.to_string(), freq))
+        });
+        assert!(calls.iter().all(|(id, _, _)| id == "yeast"));
+        let sunk = calls
+            .into_iter()
+            .map(|(_, repr, freq)| (repr, freq as usize))
+            .collect::<HashMap<_, _>>();
+        assert_eq!(sunk, expected.get_nonzero());
+    }
+
+    #[test]
+    fn census_to_sink_across_graphs() {
+        let load = |path: &str| {
+            let graph = crate::io::load_numeric_graph(path, true, false).unwrap();
+            Bitgraph::from_graph(&graph)
+        };
+        let graphs = [
+            ("yeast", load("example/graphs/yeast.txt")),
+            ("example", load("example/graphs/example.txt")),
+        ];
+
+        let mut gtrie = Gtrie::read_from_file("example/gtries/dir3.gt").unwrap();
+        let mut sunk: HashMap<String, HashMap<String, usize>> = HashMap::new();
+        for (id, graph) in &graphs {
+            gtrie.census_to_sink(graph, id, &mut |id, repr, freq| {
+                sunk.entry(id.to_string())
+                    .or_default()
+                    .insert(repr.to_string(), freq as usize);
+            });
+        }
+
+        // Each graph has the counts of its own census, not the running totals.
+        for (id, graph) in &graphs {
+            let mut expected = Gtrie::read_from_file("example/gtries/dir3.gt").unwrap();
+            expected.census(graph);
+            assert_eq!(sunk[*id], expected.get_nonzero());
+        }
+    }
+
+    #[test]
+    fn sampled_interval_narrows_with_probability() {
+        let graph = crate::io::load_numeric_graph("example/graphs/yeast.txt", true, false).unwrap();
+        let graph = Bitgraph::from_graph(&graph);
+        let mut expected = Gtrie::read_from_file("example/gtries/dir3.gt").unwrap();
+        expected.census(&graph);
+        let expected = expected.get_nonzero();
+
+        let sampled = |probs: &[f64]| {
+            let mut gtrie = Gtrie::read_from_file("example/gtries/dir3.gt").unwrap();
+            gtrie.census_sampled(&graph, probs, 7).unwrap()
+        };
+        let width = |estimates: &[SampledEstimate]| {
+            estimates.iter().map(|e| e.ci_high - e.ci_low).sum::<f64>()
+        };
+
+        let exact = sampled(&[1.0, 1.0, 1.0]);
+        assert_eq!(width(&exact), 0.0);
+        for estimate in &exact {
+            let count = expected.get(&estimate.repr).copied().unwrap_or(0);
+            assert_eq!(estimate.estimate, count as f64);
+        }
+
+        let coarse = sampled(&[1.0, 0.3, 0.3]);
+        let fine = sampled(&[1.0, 0.9, 0.9]);
+        assert!(width(&fine) < width(&coarse));
+        assert!(width(&fine) > 0.0);
+
+        // The most frequent motif is estimated within its interval.
+        let (top, count) = expected.iter().max_by_key(|(_, count)| **count).unwrap();
+        let estimate = fine.iter().find(|e| &e.repr == top).unwrap();
+        assert!(estimate.ci_low <= *count as f64 && *count as f64 <= estimate.ci_high);
+
+        let mut gtrie = Gtrie::read_from_file("example/gtries/dir3.gt").unwrap();
+        assert!(gtrie.census_sampled(&graph, &[1.0, 0.5], 7).is_err());
+        assert!(gtrie.census_sampled(&graph, &[1.0, 0.0, 1.0], 7).is_err());
+    }
+
+    #[test]
+    fn weighted_sampling_favors_heavy_occurrences() {
+        // The out-star motif occurs for each of the 190 pairs of leaves of a
+        // star with 20 leaves, and 19 of these contain the leaf 1.
+        let star: Graph<(), (), Directed> = Graph::from_edges((1..=20).map(|v| (0, v)));
+        let graph = Bitgraph::from_graph(&star);
+        let mut gtrie = Gtrie::new(3);
+        let (repr, _) = gtrie.insert_canonical(&Graph::from_edges([(0, 1), (0, 2)]));
+        let mut weights = EdgeWeights::new();
+        weights.insert(0, 1, 10.0).unwrap();
+
+        let n_trials = 200;
+        let heavy_picks = |weights: Option<&EdgeWeights>| {
+            (0..n_trials)
+                .filter(|seed| {
+                    let sample = gtrie.sample_occurrences(&graph, 1, weights, *seed);
+                    assert_eq!(sample[&repr].len(), 1);
+                    sample[&repr][0].contains(&1)
+                })
+                .count()
+        };
+
+        // Uniformly 10% of the picks contain the leaf 1, and with weights
+        // 190 / (190 + 171) = 53% of them.
+        let uniform = heavy_picks(None);
+        let weighted = heavy_picks(Some(&weights));
+        assert!(
+            uniform < n_trials as usize / 5,
+            "uniform picks: {}",
+            uniform
+        );
+        assert!(
+            weighted > n_trials as usize / 3,
+            "weighted picks: {}",
+            weighted
+        );
+
+        // Reservoirs hold every occurrence when they are large enough.
+        let sample = gtrie.sample_occurrences(&graph, 500, Some(&weights), 0);
+        assert_eq!(sample[&repr].len(), 190);
+    }
+
+    #[test]
+    fn census_min_edge_weight_rejects_light_occurrences() {
+        // A feed-forward loop with one light edge and a heavy chain.
+        let graph: Graph<(), (), Directed> =
+            Graph::from_edges([(0, 1), (0, 2), (1, 2), (3, 4), (4, 5)]);
+        let mut weights = EdgeWeights::new();
+        weights.insert(1, 2, 0.1).unwrap();
+        weights.insert(3, 4, 5.0).unwrap();
+        weights.insert(4, 5, 5.0).unwrap();
+        let graph = Bitgraph::from_graph(&graph).with_weights(weights);
+        let none = FixedBitSet::with_capacity(graph.n_nodes());
+
+        let build = || {
+            let mut gtrie = Gtrie::new(3);
+            let (ffl, _) = gtrie.insert_canonical(&Graph::from_edges([(0, 1), (0, 2), (1, 2)]));
+            let (chain, _) = gtrie.insert_canonical(&Graph::from_edges([(0, 1), (1, 2)]));
+            (gtrie, ffl, chain)
+        };
+        let (mut gtrie, ffl, chain) = build();
+        gtrie.census_min_edge_weight(&graph, &none, 1.0);
+        assert_eq!(gtrie.total_subgraphs(), 1);
+        assert_eq!(gtrie.get_nonzero().get(&chain), Some(&1));
+        assert_eq!(gtrie.get_nonzero().get(&ffl), None);
+
+        // Excluded vertices still remove the occurrences they take part in.
+        let (mut gtrie, _, _) = build();
+        let mut excluded = none.clone();
+        excluded.insert(4);
+        gtrie.census_min_edge_weight(&graph, &excluded, 1.0);
+        assert_eq!(gtrie.total_subgraphs(), 0);
+
+        // Without a threshold every occurrence is counted, as by `census`.
+        let (mut gtrie, _, _) = build();
+        gtrie.census_min_edge_weight(&graph, &none, f64::NEG_INFINITY);
+        let (mut expected, _, _) = build();
+        expected.census(&graph);
+        assert_eq!(gtrie.get_nonzero(), expected.get_nonzero());
+    }
+
+    #[test]
+    fn leaves_are_numbered_densely() {
+        let is_dense = |gtrie: &Gtrie| {
+            let reprs = gtrie.leaf_reprs();
+            let n_graphs = (1..=gtrie.max_depth)
+                .map(|depth| gtrie.root.count_graphs_at_depth(depth))
+                .sum::<usize>();
+            reprs.len() == n_graphs && reprs.iter().all(|repr| !repr.is_empty())
+        };
+        let motifs = &crate::generate::connected_digraphs(3)[2];
+        let mut a = Gtrie::new(3);
+        for motif in &motifs[..7] {
+            a.insert_canonical(motif);
+            a.insert_canonical(motif);
+        }
+        assert_eq!(a.n_leaves(), 7);
+        assert!(is_dense(&a));
+
+        let mut b = Gtrie::new(3);
+        for motif in &motifs[5..] {
+            b.insert_canonical(motif);
+        }
+        a.merge(b).unwrap();
+        assert_eq!(a.n_leaves(), motifs.len());
+        assert!(is_dense(&a));
+
+        let loaded = Gtrie::read_from_file("example/gtries/dir4.gt").unwrap();
+        assert!(is_dense(&loaded));
+        let mut text = Vec::new();
+        loaded.write_text_format(&mut text).unwrap();
+        let parsed = Gtrie::read_text_format(text.as_slice()).unwrap();
+        assert_eq!(parsed.leaf_reprs(), loaded.leaf_reprs());
+    }
+
+    #[test]
+    fn merge_partial_tries() {
+        let graph = io::load_numeric_graph("example/graphs/yeast.txt", true, false).unwrap();
+        let graph = Bitgraph::from_graph(&graph);
+        let motifs = &crate::generate::connected_digraphs(3)[2];
+        let build = |motifs: &[Graph<(), (), Directed>]| {
+            let mut gtrie = Gtrie::new(3);
+            for motif in motifs {
+                gtrie.insert_canonical(motif);
+            }
+            gtrie
+        };
+        let mut full = build(motifs);
+        full.census(&graph);
+
+        // Merging censused tries sums their frequencies.
+        let (mut a, mut b) = (build(&motifs[..7]), build(&motifs[7..]));
+        a.census(&graph);
+        b.census(&graph);
+        a.merge(b).unwrap();
+        assert_eq!(a.total_subgraphs(), full.total_subgraphs());
+        assert_eq!(a.get_nonzero(), full.get_nonzero());
+
+        // The merged trie censuses like the full trie.
+        let mut merged = build(&motifs[..7]);
+        merged.merge(build(&motifs[7..])).unwrap();
+        assert_eq!(merged.reprs().len(), motifs.len());
+        merged.census(&graph);
+        assert_eq!(merged.get_nonzero(), full.get_nonzero());
+
+        assert!(merged.merge(Gtrie::new(4)).is_err());
+        let mut undirected = Gtrie::new(3);
+        undirected.is_dir = false;
+        assert!(merged.merge(undirected).is_err());
+    }
+
+    #[test]
+    fn results_json() {
+        let graph = io::load_numeric_graph("example/graphs/example.txt", true, false).unwrap();
+        let mut gtrie = Gtrie::read_from_file("example/gtries/dir3.gt").unwrap();
+        gtrie.census(&Bitgraph::from_graph(&graph));
+
+        let json = gtrie.results_as_json(true);
+        let results = json.as_array().unwrap();
+        let nonzero = gtrie.get_nonzero();
+        assert_eq!(results.len(), nonzero.len());
+        for result in results {
+            let repr = result["repr"].as_str().unwrap();
+            assert_eq!(result["frequency"].as_u64(), Some(nonzero[repr] as u64));
+        }
+        let text = serde_json::to_string(&json).unwrap();
+        assert_eq!(
+            serde_json::from_str::<serde_json::Value>(&text).unwrap(),
+            json
+        );
+
+        // Undirected reprs agree with the plain undirected output.
+        let undirected = Bitgraph::from_graph(&graph).symmetrize();
+        gtrie.reset_counts();
+        gtrie.census(&undirected);
+        let json = gtrie.results_as_json(false);
+        let mut expected = gtrie.undirected_results();
+        expected.sort_unstable();
+        let results = json
+            .as_array()
+            .unwrap()
+            .iter()
+            .map(|result| {
+                (
+                    result["repr"].as_str().unwrap().to_string(),
+                    result["frequency"].as_u64().unwrap() as usize,
+                )
+            })
+            .collect::<Vec<_>>();
+        assert!(!results.is_empty());
+        assert_eq!(results, expected);
+    }
+
+    #[test]
+    fn census_with_progress_ticks_per_vertex() {
+        let graph = io::load_numeric_graph("example/graphs/yeast.txt", true, false).unwrap();
+        let graph = Bitgraph::from_graph(&graph);
+        let mut expected = Gtrie::read_from_file("example/gtries/dir3.gt").unwrap();
+        expected.census(&graph);
+
+        let mut gtrie = Gtrie::read_from_file("example/gtries/dir3.gt").unwrap();
+        let mut ticks = Vec::new();
+        gtrie.census_with_progress(&graph, |done, total| ticks.push((done, total)));
+        assert_eq!(ticks.len(), graph.n_nodes());
+        assert!(ticks
+            .iter()
+            .enumerate()
+            .all(|(idx, (done, _))| *done == idx + 1));
+        assert!(ticks.windows(2).all(|w| w[0].1 <= w[1].1));
+        assert_eq!(ticks.last().unwrap().1, expected.total_subgraphs());
+        assert_eq!(gtrie.get_nonzero(), expected.get_nonzero());
+    }
+
+    #[test]
+    fn census_with_reports_occurrences() {
+        let graph = io::load_numeric_graph("example/graphs/yeast.txt", true, false).unwrap();
+        let graph = Bitgraph::from_graph(&graph);
+        let mut expected = Gtrie::read_from_file("example/gtries/dir3.gt").unwrap();
+        expected.census(&graph);
+
+        let mut gtrie = Gtrie::read_from_file("example/gtries/dir3.gt").unwrap();
+        let mut occurrences: HashMap<String, HashSet<Vec<usize>>> = HashMap::new();
+        gtrie.census_with(&graph, |repr, used| {
+            assert_eq!(used.len(), 3);
+            let mut vertices = used.to_vec();
+            vertices.sort_unstable();
+            assert!(occurrences.entry_ref(repr).or_default().insert(vertices));
+        });
+        assert_eq!(gtrie.total_subgraphs(), expected.total_subgraphs());
+        assert_eq!(gtrie.get_nonzero(), expected.get_nonzero());
+        for (repr, vertex_sets) in &occurrences {
+            assert_eq!(vertex_sets.len(), expected.get_nonzero()[repr]);
+        }
+    }
+
+    #[test]
+    fn parallel_census_matches_census() {
+        let graph = io::load_numeric_graph("example/graphs/yeast.txt", true, false).unwrap();
+        let graph = Bitgraph::from_graph(&graph);
+        for path in ["example/gtries/dir3.gt", "example/gtries/dir4.gt"] {
+            let mut expected = Gtrie::read_from_file(path).unwrap();
+            expected.census(&graph);
+
+            for n_threads in [1, 4] {
+                let pool = rayon::ThreadPoolBuilder::new()
+                    .num_threads(n_threads)
+                    .build()
+                    .unwrap();
+                let mut gtrie = Gtrie::read_from_file(path).unwrap();
+                pool.install(|| gtrie.census_parallel(&graph));
+                assert_eq!(gtrie.total_subgraphs(), expected.total_subgraphs());
+                assert_eq!(gtrie.get_nonzero(), expected.get_nonzero());
+            }
+        }
+
+        // A graph with no vertices has no subgraphs.
+        let mut gtrie = Gtrie::read_from_file("example/gtries/dir3.gt").unwrap();
+        gtrie.census_parallel(&Bitgraph::with_capacity(0, true));
+        assert_eq!(gtrie.total_subgraphs(), 0);
+    }
+
+    #[test]
+    fn reset_counts_between_censuses() {
+        let graph = io::load_numeric_graph("example/graphs/yeast.txt", true, false).unwrap();
+        let graph = Bitgraph::from_graph(&graph);
+        let mut gtrie = Gtrie::read_from_file("example/gtries/dir3.gt").unwrap();
+        gtrie.census(&graph);
+        let total = gtrie.total_subgraphs();
+        let counts = gtrie.get_nonzero();
+
+        gtrie.reset_counts();
+        assert_eq!(gtrie.total_subgraphs(), 0);
+        assert!(gtrie.get_nonzero().is_empty());
+
+        gtrie.census(&graph);
+        assert_eq!(gtrie.total_subgraphs(), total);
+        assert_eq!(gtrie.get_nonzero(), counts);
+    }
+
+    #[test]
+    fn concentrations_of_empty_census() {
+        let mut gtrie = Gtrie::read_from_file("example/gtries/dir3.gt").unwrap();
+        assert!(gtrie.concentrations().is_empty());
+
+        // A graph without connected triads.
+        let graph: Graph<(), (), Directed> = Graph::from_edges([(0, 1), (2, 3)]);
+        gtrie.census(&Bitgraph::from_graph(&graph));
+        assert_eq!(gtrie.total_subgraphs(), 0);
+        assert!(gtrie.concentrations().values().all(|c| c.is_finite()));
+
+        let graph = io::load_numeric_graph("example/graphs/yeast.txt", true, false).unwrap();
+        gtrie.census(&Bitgraph::from_graph(&graph));
+        let total = gtrie.concentrations().values().sum::<f64>();
+        assert!((total - 1.0).abs() < 1e-9);
+    }
+
+    #[test]
+    fn ranked_results_by_decreasing_frequency() {
+        let graph = io::load_numeric_graph("example/graphs/yeast.txt", true, false).unwrap();
+        let graph = Bitgraph::from_graph(&graph);
+        let mut gtrie = Gtrie::read_from_file("example/gtries/dir3.gt").unwrap();
+        gtrie.census(&graph);
+
+        let ranked = gtrie.ranked_results();
+        assert_eq!(ranked.len(), gtrie.results().len());
+        for pair in ranked.windows(2) {
+            assert!(pair[0].1 > pair[1].1 || (pair[0].1 == pair[1].1 && pair[0].0 < pair[1].0));
+        }
+        let nonzero = gtrie.get_nonzero();
+        for (repr, freq) in ranked.into_iter().filter(|(_, freq)| *freq > 0) {
+            assert_eq!(nonzero[&repr], freq);
+        }
+    }
+
+    #[test]
+    fn dot_has_a_node_per_trie_node() {
+        let graph = io::load_numeric_graph("example/graphs/example.txt", true, false).unwrap();
+        let graph = Bitgraph::from_graph(&graph);
+        let mut gtrie = Gtrie::read_from_file("example/gtries/dir3.gt").unwrap();
+        gtrie.census(&graph);
+        let dot = gtrie.to_dot();
+        assert!(dot.starts_with("digraph gtrie {"));
+        assert!(dot.ends_with("}\n"));
+
+        // Every node but the root has a parent.
+        let n_nodes = dot.matches(" [label=").count();
+        assert_eq!(dot.matches(" -> n").count(), n_nodes - 1);
+        assert_eq!(dot.matches("doublecircle").count(), gtrie.motifs().len());
+        for (repr, freq) in gtrie.get_nonzero() {
+            assert!(dot.contains(&format!("{} -> {}\"", repr.replace('\\', "\\\\"), freq)));
+        }
+    }
+
+    #[test]
+    fn node_and_graph_counts() {
+        let gtrie = Gtrie::read_from_file("example/gtries/dir3.gt").unwrap();
+        let n_labels = gtrie.to_dot().matches(" [label=").count();
+        assert_eq!(gtrie.node_count(), n_labels - 1);
+        assert_eq!(gtrie.n_leaves(), 13);
+        assert_eq!(gtrie.depth(), 3);
+
+        let empty = Gtrie::new(3);
+        assert_eq!(empty.node_count(), 0);
+        assert_eq!(empty.n_leaves(), 0);
+        assert_eq!(empty.depth(), 0);
+    }
+
+    #[test]
+    fn summary_of_census() {
+        let mut gtrie = Gtrie::read_from_file("example/gtries/dir3.gt").unwrap();
+        assert_eq!(gtrie.summary(), CensusSummary::default());
+
+        let graph = io::load_numeric_graph("example/graphs/yeast.txt", true, false).unwrap();
+        gtrie.census(&Bitgraph::from_graph(&graph));
+        let summary = gtrie.summary();
+        assert_eq!(summary.n_distinct, gtrie.get_nonzero().len());
+        assert_eq!(summary.total, gtrie.total_subgraphs());
+        let (repr, count) = gtrie.ranked_results().remove(0);
+        assert_eq!(summary.most_frequent_repr, Some(repr));
+        assert_eq!(summary.most_frequent_count, count);
+    }
+
+    #[test]
+    fn participation_sums_to_census() {
+        let graph = io::load_numeric_graph("example/graphs/yeast.txt", true, false).unwrap();
+        let graph = Bitgraph::from_graph(&graph);
+        let mut expected = Gtrie::read_from_file("example/gtries/dir3.gt").unwrap();
+        expected.census(&graph);
+
+        let mut gtrie = Gtrie::read_from_file("example/gtries/dir3.gt").unwrap();
+        let table = gtrie.census_participation(&graph);
+        assert_eq!(gtrie.get_nonzero(), expected.get_nonzero());
+
+        // Each occurrence of a motif of size 3 counts for its 3 vertices.
+        assert_eq!(table.reprs().len(), gtrie.reprs().len());
+        let nonzero = gtrie.get_nonzero();
+        for repr in table.reprs() {
+            let total = (0..graph.n_nodes())
+                .map(|v| table.get(repr, v))
+                .sum::<usize>();
+            assert_eq!(total, 3 * nonzero.get(repr).copied().unwrap_or(0));
+        }
+
+        let mut buffer = Vec::new();
+        table.write_tsv(&mut buffer, true, |v| v + 1).unwrap();
+        let tsv = String::from_utf8(buffer).unwrap();
+        assert_eq!(tsv.lines().count(), graph.n_nodes() + 1);
+        assert!(tsv.starts_with("vertex\t"));
+    }
+
+    #[test]
+    fn color_breakdown_sums_to_census() {
+        // Two out-stars whose leaves are colored `A B` and `B A`, and a chain.
+        let graph: Graph<(), (), Directed> =
+            Graph::from_edges([(0, 1), (0, 2), (3, 4), (3, 5), (6, 7), (7, 8)]);
+        let graph = Bitgraph::from_graph(&graph);
+        let buffer = "1 TF\n2 TF\n3 gene\n4 TF\n5 gene\n6 TF\n7 TF\n8 gene\n9 gene\n";
+        let colors = VertexColors::from_buffer(&mut buffer.as_bytes()).unwrap();
+        let mut gtrie = Gtrie::new(3);
+        let (star, _) = gtrie.insert_canonical(&Graph::from_edges([(0, 1), (0, 2)]));
+        gtrie.insert_canonical(&Graph::from_edges([(0, 1), (1, 2)]));
+
+        let breakdown = gtrie.census_by_color(&graph, &colors).unwrap();
+        let mut totals = HashMap::new();
+        for ((repr, tuple), count) in &breakdown {
+            assert_eq!(tuple.len(), 3);
+            *totals.entry(repr.clone()).or_insert(0) += *count as usize;
+        }
+        let mut counts = HashMap::new();
+        gtrie.census_into(&graph, &mut counts);
+        counts.retain(|_, count| *count > 0);
+        assert_eq!(totals, counts);
+
+        // The leaves of the out-star are interchangeable, so both stars share
+        // a color combination.
+        assert_eq!(
+            breakdown.keys().filter(|(repr, _)| *repr == star).count(),
+            1
+        );
+
+        let partial = VertexColors::from_buffer(&mut "1 TF\n".as_bytes()).unwrap();
+        assert!(gtrie.census_by_color(&graph, &partial).is_err());
+    }
+
+    #[test]
+    fn color_breakdown_of_merged_reversals() {
+        // A feed-forward loop with a second edge into its sink, and the
+        // reversal of the motif. The sink of the first and the source of the
+        // second are the TFs.
+        let motif: Graph<(), (), Directed> = Graph::from_edges([(1, 0), (2, 0), (2, 1), (3, 0)]);
+        let graph: Graph<(), (), Directed> = Graph::from_edges([
+            (1, 0),
+            (2, 0),
+            (2, 1),
+            (3, 0),
+            (4, 5),
+            (4, 6),
+            (5, 6),
+            (4, 7),
+        ]);
+        let graph = Bitgraph::from_graph(&graph);
+        let buffer = "1 TF\n2 gene\n3 gene\n4 gene\n5 TF\n6 gene\n7 gene\n8 gene\n";
+        let colors = VertexColors::from_buffer(&mut buffer.as_bytes()).unwrap();
+
+        // Both orientations under one repr, as `build --merge-reversals` does.
+        // The reversal places the vertex of the sink at another position.
+        let mut reversed = motif.clone();
+        reversed.reverse();
+        let mut gtrie = Gtrie::new(4);
+        let (repr, _) = gtrie.insert_canonical(&motif);
+        let (bgraph, canon) = canonicalize(&reversed);
+        gtrie.insert_canonical_form(&bgraph, &canon, repr.clone());
+        assert_eq!(gtrie.n_leaves(), 2);
+
+        // Both occurrences are keyed by the labeling of the repr, where the
+        // TF is the sink.
+        let breakdown = gtrie.census_by_color(&graph, &colors).unwrap();
+        assert_eq!(breakdown.len(), 1);
+        let ((key_repr, tuple), count) = breakdown.iter().next().unwrap();
+        assert_eq!((key_repr, *count), (&repr, 2));
+        let labeled = Bitgraph::from_graph(&io::load_repr(&repr).unwrap());
+        let sink = (0..4)
+            .find(|v| (0..4).filter(|u| labeled.is_connected(*u, *v)).count() == 3)
+            .unwrap();
+        let tf = colors.get(0).unwrap();
+        assert_eq!(tuple[sink], tf);
+        assert_eq!(tuple.iter().filter(|c| **c == tf).count(), 1);
+    }
+
+    #[test]
+    fn validate_against_brute_force() {
+        let graph =
+            crate::io::load_numeric_graph("example/graphs/example.txt", true, false).unwrap();
+        let graph = Bitgraph::from_graph(&graph);
+        for path in ["example/gtries/dir3.gt", "example/gtries/dir4.gt"] {
+            let gtrie = Gtrie::read_from_file(path).unwrap();
+            gtrie.validate_against_brute_force(&graph).unwrap();
+        }
+
+        // Without symmetry breaking conditions the out-star is counted once per
+        // automorphism, twice per occurrence.
+        let mut gtrie = Gtrie::new(3);
+        let (bgraph, canon) = canonicalize(&Graph::from_edges([(0, 1), (0, 2)]));
+        gtrie.insert(&bgraph, None, None, Some(canon.repr()));
+        let err = gtrie.validate_against_brute_force(&graph).unwrap_err();
+        assert!(err.to_string().contains(&canon.repr()));
+
+        let large = crate::io::load_numeric_graph("example/graphs/yeast.txt", true, false).unwrap();
+        let gtrie = Gtrie::read_from_file("example/gtries/dir4.gt").unwrap();
+        assert!(gtrie
+            .validate_against_brute_force(&Bitgraph::from_graph(&large))
+            .is_err());
     }
 }
@@ -1,19 +1,94 @@
-use anyhow::{bail, Result};
-use graph6_rs::DiGraph;
+use anyhow::{anyhow, bail, Result};
+use flate2::{read::GzDecoder, write::GzEncoder, Compression};
+use graph6_rs::{DiGraph, Graph as UndirectedGraph};
+use hashbrown::HashMap;
 use petgraph::{Directed, Graph};
 use std::{
     fs::File,
-    io::{BufRead, BufReader},
+    io::{BufRead, BufReader, BufWriter, Write},
 };
 
-pub fn iter_graphs_from_file(path: &str) -> impl Iterator<Item = Graph<(), (), Directed>> {
-    let file = File::open(path).unwrap();
-    let reader = BufReader::new(file);
-    reader.lines().map(|line| load_repr(&line.unwrap()))
+use crate::weight::EdgeWeights;
+
+/// Opens an input for reading, where a path of `-` reads from stdin, see
+/// `open_file`.
+pub fn open_input(path: &str) -> Result<Box<dyn BufRead>> {
+    if path == "-" {
+        Ok(Box::new(std::io::stdin().lock()))
+    } else {
+        open_file(path)
+    }
+}
+
+/// Opens a file for reading, decompressing it if the path ends in `.gz`.
+pub fn open_file(path: &str) -> Result<Box<dyn BufRead>> {
+    let file = File::open(path)?;
+    if is_gzipped(path) {
+        Ok(Box::new(BufReader::new(GzDecoder::new(file))))
+    } else {
+        Ok(Box::new(BufReader::new(file)))
+    }
+}
+
+/// Creates a file and writes it with `write`, compressing it if the path
+/// ends in `.gz`.
+pub fn write_file<F>(path: &str, write: F) -> Result<()>
+where
+    F: FnOnce(&mut dyn Write) -> Result<()>,
+{
+    let file = File::create(path)?;
+    if is_gzipped(path) {
+        let mut writer = BufWriter::new(GzEncoder::new(file, Compression::default()));
+        write(&mut writer)?;
+        writer.into_inner().map_err(|e| e.into_error())?.finish()?;
+    } else {
+        let mut writer = BufWriter::new(file);
+        write(&mut writer)?;
+        writer.flush()?;
+    }
+    Ok(())
+}
+
+/// Checks if a path is gzip compressed by its `.gz` extension.
+pub fn is_gzipped(path: &str) -> bool {
+    path.ends_with(".gz")
+}
+
+/// Iterate over the graph6 formatted graphs of a file (one per line), or of
+/// stdin if the path is `-`.
+///
+/// Each item is a `Result` so that a single malformed line can be reported
+/// (or skipped) by the caller without aborting the whole iteration. Errors
+/// identify the 1-indexed line number and its content.
+pub fn iter_graphs_from_file(
+    path: &str,
+) -> Result<impl Iterator<Item = Result<Graph<(), (), Directed>>>> {
+    let iter = iter_reprs_from_file(path)?.map(|result| result.map(|(_, graph)| graph));
+    Ok(iter)
+}
+
+/// A graph6 line and the graph parsed from it.
+pub type ReprGraph = (String, Graph<(), (), Directed>);
+
+/// Equivalent to `iter_graphs_from_file`, also yielding the graph6 line each
+/// graph was parsed from.
+pub fn iter_reprs_from_file(path: &str) -> Result<impl Iterator<Item = Result<ReprGraph>>> {
+    let reader = open_input(path)?;
+    let iter = reader.lines().enumerate().map(|(idx, line)| {
+        let line = line.map_err(|e| anyhow!("Line {}: unable to read line: {}", idx + 1, e))?;
+        let graph = load_repr(&line).map_err(|e| anyhow!("Line {}: `{}`: {}", idx + 1, line, e))?;
+        Ok((line, graph))
+    });
+    Ok(iter)
 }
 
-pub fn load_repr(repr: &str) -> Graph<(), (), Directed> {
-    let graph = DiGraph::from_d6(repr).unwrap();
+/// Load a directed graph from its graph6 representation.
+///
+/// Directed graph6 (prefixed by `&`) is loaded as is. Undirected graph6, as
+/// written by `geng`, is loaded with every edge reciprocated, which is how
+/// undirected graphs are censused.
+pub fn load_repr(repr: &str) -> Result<Graph<(), (), Directed>> {
+    let graph = parse_repr(repr)?;
     let mut g = Graph::with_capacity(graph.n, graph.bit_vec.iter().sum());
     for _ in 0..graph.n {
         g.add_node(());
@@ -25,38 +100,1075 @@ pub fn load_repr(repr: &str) -> Graph<(), (), Directed> {
             }
         }
     }
-    g
+    Ok(g)
+}
+
+/// Parses a directed or undirected graph6 representation into its row-major
+/// adjacency matrix, undirected graphs having every edge reciprocated.
+fn parse_repr(repr: &str) -> Result<DiGraph> {
+    validate_repr(repr)?;
+    if repr.starts_with('&') {
+        DiGraph::from_d6(repr).map_err(|e| anyhow!("Invalid graph6: {:?}", e))
+    } else {
+        let graph =
+            UndirectedGraph::from_g6(repr).map_err(|e| anyhow!("Invalid graph6: {:?}", e))?;
+        Ok(DiGraph {
+            bit_vec: graph.bit_vec,
+            n: graph.n,
+        })
+    }
 }
 
-/// Load a graph from a file
+/// Writes the adjacency matrix of a motif as a string of `0` and `1`, row by row.
+pub fn adjacency_bits(repr: &str) -> Result<String> {
+    let graph = parse_repr(repr)?;
+    Ok(graph
+        .bit_vec
+        .iter()
+        .map(|b| if *b == 1 { '1' } else { '0' })
+        .collect())
+}
+
+/// Writes the upper triangle of the adjacency matrix of a symmetric motif as a
+/// string of `0` and `1`, in the order of graph6: `(0, 1), (0, 2), (1, 2), (0, 3), ...`.
 ///
-/// Expects a 1-Indexed numeric white-space delimited edgelist.
-pub fn load_numeric_graph(filepath: &str, include_loops: bool) -> Result<Graph<(), (), Directed>> {
-    let mut reader = File::open(filepath).map(BufReader::new)?;
-    load_numeric_graph_from_buffer(&mut reader, include_loops)
+/// Returns `None` if the motif is not symmetric, as the triangle would lose edges.
+pub fn adjacency_triangle(repr: &str) -> Result<Option<String>> {
+    let graph = parse_repr(repr)?;
+    let n = graph.n;
+    let bit = |u: usize, v: usize| graph.bit_vec[u * n + v] == 1;
+    if (0..n).any(|u| (0..n).any(|v| bit(u, v) != bit(v, u))) {
+        return Ok(None);
+    }
+    let triangle = (1..n)
+        .flat_map(|v| (0..v).map(move |u| (u, v)))
+        .map(|(u, v)| if bit(u, v) { '1' } else { '0' })
+        .collect();
+    Ok(Some(triangle))
+}
+
+/// Expands an upper triangle written by `adjacency_triangle` back into the
+/// full adjacency matrix written by `adjacency_bits`.
+pub fn adjacency_from_triangle(triangle: &str) -> Result<String> {
+    let mut n = 1;
+    while n * (n - 1) / 2 < triangle.len() {
+        n += 1;
+    }
+    if n * (n - 1) / 2 != triangle.len() {
+        bail!(
+            "A triangle of {} bits is not the upper triangle of a square matrix",
+            triangle.len()
+        );
+    }
+
+    let mut matrix = vec!['0'; n * n];
+    let pairs = (1..n).flat_map(|v| (0..v).map(move |u| (u, v)));
+    for ((u, v), bit) in pairs.zip(triangle.chars()) {
+        match bit {
+            '0' => {}
+            '1' => {
+                matrix[u * n + v] = '1';
+                matrix[v * n + u] = '1';
+            }
+            _ => bail!("Invalid character `{}` in adjacency triangle", bit),
+        }
+    }
+    Ok(matrix.into_iter().collect())
 }
 
-/// Load a graph from a buffer
+/// Converts the directed graph6 representation of a symmetric graph to its
+/// undirected graph6 representation. Returns `None` if the graph is not symmetric.
+pub fn undirected_repr(repr: &str) -> Option<String> {
+    let graph = DiGraph::from_d6(repr).ok()?;
+    let n = graph.n;
+    let is_symmetric =
+        (0..n).all(|u| (0..n).all(|v| graph.bit_vec[u * n + v] == graph.bit_vec[v * n + u]));
+    if is_symmetric {
+        Some(graph6_rs::write_graph6(graph.bit_vec, n, false))
+    } else {
+        None
+    }
+}
+
+/// The repr a motif is reported under: as is if `is_dir`, and otherwise as
+/// its undirected repr, `None` for a motif an undirected graph can't contain.
+pub fn output_repr(repr: &str, is_dir: bool) -> Option<String> {
+    if is_dir {
+        Some(repr.to_string())
+    } else {
+        undirected_repr(repr)
+    }
+}
+
+/// Checks that a directed or undirected graph6 representation is well formed
+/// before it is handed to the parser (which panics on truncated or out of
+/// range input).
+fn validate_repr(repr: &str) -> Result<()> {
+    let (bytes, is_dir) = match repr.strip_prefix('&') {
+        Some(rest) => (rest.as_bytes(), true),
+        None => (repr.as_bytes(), false),
+    };
+    if bytes.is_empty() {
+        bail!("Invalid graph6: missing size character");
+    }
+    if let Some(c) = bytes.iter().find(|c| !(63..=126).contains(*c)) {
+        bail!("Invalid graph6: unexpected character '{}'", *c as char);
+    }
+    let n = (bytes[0] - 63) as usize;
+    if n == 63 {
+        bail!("Invalid graph6: graphs larger than 62 nodes are not supported");
+    }
+    let (kind, n_bits) = if is_dir {
+        ("directed", n * n)
+    } else {
+        ("undirected", n * n.saturating_sub(1) / 2)
+    };
+    let expected = n_bits.div_ceil(6);
+    if bytes.len() - 1 != expected {
+        bail!(
+            "Invalid graph6: expected {} adjacency characters for {} nodes ({} graph6), found {}{}",
+            expected,
+            n,
+            kind,
+            bytes.len() - 1,
+            if is_dir {
+                ""
+            } else {
+                "; directed graph6 must start with '&'"
+            }
+        );
+    }
+    Ok(())
+}
+
+/// A problem of an input graph that does not prevent loading it, returned by
+/// the loaders for the caller to report.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum LoadWarning {
+    /// Self-loops kept in the graph, which the census never matches.
+    SelfLoops(usize),
+
+    /// A header declaring another number of edges than were read.
+    EdgeCount { declared: usize, read: usize },
+}
+impl LoadWarning {
+    /// The warning for the self-loops of a graph loaded with `include_loops`,
+    /// if it has any.
+    pub fn self_loops(graph: &Graph<(), (), Directed>) -> Option<Self> {
+        let n_loops = graph
+            .raw_edges()
+            .iter()
+            .filter(|e| e.source() == e.target())
+            .count();
+        (n_loops > 0).then_some(Self::SelfLoops(n_loops))
+    }
+
+    fn edge_count(declared: usize, read: usize) -> Option<Self> {
+        (declared != read).then_some(Self::EdgeCount { declared, read })
+    }
+}
+impl std::fmt::Display for LoadWarning {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self {
+            Self::SelfLoops(n_loops) => write!(
+                f,
+                "{} self-loops included in the graph; they are never matched by the census",
+                n_loops
+            ),
+            Self::EdgeCount { declared, read } => write!(
+                f,
+                "the header declares {} edges but {} were read",
+                declared, read
+            ),
+        }
+    }
+}
+
+/// Load a graph from a file, or from stdin if the path is `-`
+///
+/// Expects a 1-Indexed numeric white-space delimited edgelist. Blank lines and
+/// comment lines starting with `#` are skipped.
+///
+/// Self-loops are kept if `include_loops` is set, but they only contribute to
+/// the edge count of the graph: the census never matches a vertex to itself so
+/// counts are identical either way. See [`LoadWarning::self_loops`].
 ///
-/// Expects a 1-Indexed numeric white-space delimited edgelist.
+/// With `zero_indexed` the indices are taken as is, vertex 0 being the first.
+/// A 1-indexed file read this way gains an isolated vertex 0 and has all of
+/// its vertices shifted by one.
+pub fn load_numeric_graph(
+    filepath: &str,
+    include_loops: bool,
+    zero_indexed: bool,
+) -> Result<Graph<(), (), Directed>> {
+    load_numeric_graph_skipping(filepath, include_loops, zero_indexed, 0)
+}
+
+/// Load a graph from a file as [`load_numeric_graph`] does, ignoring its first
+/// `skip_header` data lines, e.g. a header row naming the columns.
+pub fn load_numeric_graph_skipping(
+    filepath: &str,
+    include_loops: bool,
+    zero_indexed: bool,
+    skip_header: usize,
+) -> Result<Graph<(), (), Directed>> {
+    let mut reader = open_input(filepath)?;
+    load_numeric_graph_skipping_from_buffer(&mut reader, include_loops, zero_indexed, skip_header)
+}
+
+/// Load a graph from a buffer, see [`load_numeric_graph`].
 pub fn load_numeric_graph_from_buffer<B: BufRead>(
     buffer: &mut B,
     include_loops: bool,
+    zero_indexed: bool,
+) -> Result<Graph<(), (), Directed>> {
+    load_numeric_graph_skipping_from_buffer(buffer, include_loops, zero_indexed, 0)
+}
+
+/// Load a graph from a buffer, see [`load_numeric_graph_skipping`].
+pub fn load_numeric_graph_skipping_from_buffer<B: BufRead>(
+    buffer: &mut B,
+    include_loops: bool,
+    zero_indexed: bool,
+    skip_header: usize,
 ) -> Result<Graph<(), (), Directed>> {
+    read_numeric_edges(buffer, include_loops, zero_indexed, skip_header, None)
+}
+
+/// Load a graph as [`load_numeric_graph_skipping`] does, along with the
+/// weights of its edges read from an optional third column. Edges without one
+/// have a weight of 1.
+pub fn load_weighted_graph(
+    filepath: &str,
+    include_loops: bool,
+    zero_indexed: bool,
+    skip_header: usize,
+) -> Result<(Graph<(), (), Directed>, EdgeWeights)> {
+    let mut reader = open_input(filepath)?;
+    load_weighted_graph_from_buffer(&mut reader, include_loops, zero_indexed, skip_header)
+}
+
+/// Load a weighted graph from a buffer, see [`load_weighted_graph`].
+pub fn load_weighted_graph_from_buffer<B: BufRead>(
+    buffer: &mut B,
+    include_loops: bool,
+    zero_indexed: bool,
+    skip_header: usize,
+) -> Result<(Graph<(), (), Directed>, EdgeWeights)> {
+    let mut weights = EdgeWeights::new();
+    let graph = read_numeric_edges(
+        buffer,
+        include_loops,
+        zero_indexed,
+        skip_header,
+        Some(&mut weights),
+    )?;
+    Ok((graph, weights))
+}
+
+/// Reads a numeric edgelist, also reading the third column of each line into
+/// `weights` if given.
+fn read_numeric_edges<B: BufRead>(
+    buffer: &mut B,
+    include_loops: bool,
+    zero_indexed: bool,
+    skip_header: usize,
+    mut weights: Option<&mut EdgeWeights>,
+) -> Result<Graph<(), (), Directed>> {
+    let offset = if zero_indexed { 0 } else { 1 };
     let mut edges = Vec::new();
-    for line in buffer.lines() {
-        let line = line.unwrap();
+    let lines = buffer
+        .lines()
+        .enumerate()
+        .filter(|(_, line)| {
+            line.as_ref().map_or(true, |l| {
+                let l = l.trim_start();
+                !l.is_empty() && !l.starts_with('#')
+            })
+        })
+        .skip(skip_header);
+    for (idx, line) in lines {
+        let line = line?;
         let mut split = line.split_whitespace();
-        let u = split.next().unwrap().parse::<u32>()?;
-        let v = split.next().unwrap().parse::<u32>()?;
-        if u == 0 || v == 0 {
+        let (Some(u), Some(v)) = (split.next(), split.next()) else {
+            bail!("Line {}: expected two vertices, found `{}`", idx + 1, line);
+        };
+        let (Ok(u), Ok(v)) = (u.parse::<u32>(), v.parse::<u32>()) else {
+            bail!(
+                "Line {}: expected numeric vertices, found `{}`",
+                idx + 1,
+                line
+            );
+        };
+        if !zero_indexed && (u == 0 || v == 0) {
             bail!("ERROR: Found a node index: 0; Please use 1-indexed node indices.");
         }
         if !include_loops && u == v {
             continue;
-        } else {
+        }
+        let (u, v) = (u - offset, v - offset);
+        if let (Some(weights), Some(w)) = (weights.as_deref_mut(), split.next()) {
+            let Ok(w) = w.parse::<f64>() else {
+                bail!(
+                    "Line {}: expected a numeric weight, found `{}`",
+                    idx + 1,
+                    line
+                );
+            };
+            weights.insert(u as usize, v as usize, w)?;
+        }
+        edges.push((u, v));
+    }
+    Ok(Graph::from_edges(&edges))
+}
+
+/// Load a graph whose first line declares its number of vertices and edges
+///
+/// Expects an `n m` header followed by a 1-Indexed numeric white-space
+/// delimited edgelist. The graph has exactly `n` vertices, so isolated vertices
+/// are kept even if they have the largest indices. A number of edges different
+/// from `m` is returned as a warning.
+pub fn load_header_graph(filepath: &str) -> Result<(Graph<(), (), Directed>, Option<LoadWarning>)> {
+    let mut reader = open_input(filepath)?;
+    load_header_graph_from_buffer(&mut reader)
+}
+
+/// Load a graph with a header from a buffer, see [`load_header_graph`].
+pub fn load_header_graph_from_buffer<B: BufRead>(
+    buffer: &mut B,
+) -> Result<(Graph<(), (), Directed>, Option<LoadWarning>)> {
+    let mut lines = buffer
+        .lines()
+        .enumerate()
+        .filter(|(_, line)| line.as_ref().map_or(true, |l| !l.trim().is_empty()));
+    let Some((_, header)) = lines.next() else {
+        bail!("Expected a header with the number of vertices and edges, found an empty file");
+    };
+    let header = header?;
+    let mut split = header.split_whitespace();
+    let (Some(n), Some(m), None) = (split.next(), split.next(), split.next()) else {
+        bail!(
+            "Line 1: expected a header with the number of vertices and edges, found `{}`",
+            header
+        );
+    };
+    let n = n.parse::<usize>()?;
+    let m = m.parse::<usize>()?;
+
+    let mut graph = Graph::with_capacity(n, m);
+    for _ in 0..n {
+        graph.add_node(());
+    }
+    for (idx, line) in lines {
+        let line = line?;
+        let mut split = line.split_whitespace();
+        let (Some(u), Some(v)) = (split.next(), split.next()) else {
+            bail!("Line {}: expected two vertices, found `{}`", idx + 1, line);
+        };
+        let u = u.parse::<usize>()?;
+        let v = v.parse::<usize>()?;
+        if u == 0 || v == 0 {
+            bail!("ERROR: Found a node index: 0; Please use 1-indexed node indices.");
+        }
+        if u > n || v > n {
+            bail!(
+                "Line {}: edge {} -> {} is out of range for the {} vertices of the header",
+                idx + 1,
+                u,
+                v,
+                n
+            );
+        }
+        graph.add_edge(((u - 1) as u32).into(), ((v - 1) as u32).into(), ());
+    }
+    let warning = LoadWarning::edge_count(m, graph.edge_count());
+    Ok((graph, warning))
+}
+
+/// Load a graph in the DIMACS edge format from a file, or from stdin if the
+/// path is `-`
+///
+/// Expects a `p edge n m` problem line followed by `e u v` lines of 1-indexed
+/// directed edges. Comment lines starting with `c` are ignored. The graph has
+/// exactly `n` vertices and self-loops are handled as in [`load_numeric_graph`].
+/// A number of edges different from `m` is returned as a warning.
+pub fn load_dimacs_graph(
+    filepath: &str,
+    include_loops: bool,
+) -> Result<(Graph<(), (), Directed>, Option<LoadWarning>)> {
+    let mut reader = open_input(filepath)?;
+    load_dimacs_graph_from_buffer(&mut reader, include_loops)
+}
+
+/// Load a DIMACS graph from a buffer, see [`load_dimacs_graph`].
+pub fn load_dimacs_graph_from_buffer<B: BufRead>(
+    buffer: &mut B,
+    include_loops: bool,
+) -> Result<(Graph<(), (), Directed>, Option<LoadWarning>)> {
+    let mut graph: Option<Graph<(), (), Directed>> = None;
+    let mut n_edges = 0;
+    let mut n_read = 0;
+    for (idx, line) in buffer.lines().enumerate() {
+        let line = line?;
+        let fields = line.split_whitespace().collect::<Vec<_>>();
+        match fields[..] {
+            [] | ["c", ..] => continue,
+            ["p", _, n, m] => {
+                if graph.is_some() {
+                    bail!("Line {}: found a second problem line `{}`", idx + 1, line);
+                }
+                let n = n.parse::<usize>()?;
+                n_edges = m.parse::<usize>()?;
+                let mut g = Graph::with_capacity(n, n_edges);
+                for _ in 0..n {
+                    g.add_node(());
+                }
+                graph = Some(g);
+            }
+            ["e", u, v] => {
+                let Some(graph) = graph.as_mut() else {
+                    bail!("Line {}: found an edge before the problem line", idx + 1);
+                };
+                let u = u.parse::<usize>()?;
+                let v = v.parse::<usize>()?;
+                if u == 0 || v == 0 {
+                    bail!("ERROR: Found a node index: 0; Please use 1-indexed node indices.");
+                }
+                if u > graph.node_count() || v > graph.node_count() {
+                    bail!(
+                        "Line {}: edge {} -> {} is out of range for the {} vertices of the problem line",
+                        idx + 1,
+                        u,
+                        v,
+                        graph.node_count()
+                    );
+                }
+                n_read += 1;
+                if !include_loops && u == v {
+                    continue;
+                }
+                graph.add_edge(((u - 1) as u32).into(), ((v - 1) as u32).into(), ());
+            }
+            _ => bail!(
+                "Line {}: expected a `c`, `p edge n m` or `e u v` line, found `{}`",
+                idx + 1,
+                line
+            ),
+        }
+    }
+    let Some(graph) = graph else {
+        bail!("Expected a `p edge n m` problem line, found none");
+    };
+    let warning = LoadWarning::edge_count(n_edges, n_read);
+    Ok((graph, warning))
+}
+
+/// Load a graph from an adjacency list file, or from stdin if the path is `-`
+///
+/// Expects one line per source vertex: its 1-indexed id followed by its
+/// out-neighbors, white-space delimited. The number of vertices is the
+/// largest index, including sources without neighbors. Self-loops are
+/// handled as in [`load_numeric_graph`].
+pub fn load_adjacency_list(filepath: &str, include_loops: bool) -> Result<Graph<(), (), Directed>> {
+    let mut reader = open_input(filepath)?;
+    load_adjacency_list_from_buffer(&mut reader, include_loops)
+}
+
+/// Load an adjacency list from a buffer, see [`load_adjacency_list`].
+pub fn load_adjacency_list_from_buffer<B: BufRead>(
+    buffer: &mut B,
+    include_loops: bool,
+) -> Result<Graph<(), (), Directed>> {
+    let mut edges = Vec::new();
+    let mut n_nodes = 0;
+    for (idx, line) in buffer.lines().enumerate() {
+        let line = line?;
+        let mut split = line.split_whitespace();
+        let Some(u) = split.next() else {
+            continue;
+        };
+        let u = u
+            .parse::<usize>()
+            .map_err(|e| anyhow!("Line {}: `{}`: {}", idx + 1, u, e))?;
+        if u == 0 {
+            bail!("ERROR: Found a node index: 0; Please use 1-indexed node indices.");
+        }
+        n_nodes = n_nodes.max(u);
+        for v in split {
+            let v = v
+                .parse::<usize>()
+                .map_err(|e| anyhow!("Line {}: `{}`: {}", idx + 1, v, e))?;
+            if v == 0 {
+                bail!("ERROR: Found a node index: 0; Please use 1-indexed node indices.");
+            }
+            n_nodes = n_nodes.max(v);
+            if !include_loops && u == v {
+                continue;
+            }
             edges.push((u - 1, v - 1));
         }
     }
+    let mut graph = Graph::with_capacity(n_nodes, edges.len());
+    for _ in 0..n_nodes {
+        graph.add_node(());
+    }
+    for (u, v) in edges {
+        graph.add_edge((u as u32).into(), (v as u32).into(), ());
+    }
+    Ok(graph)
+}
+
+/// Load a graph with typed edges from a file
+///
+/// Expects a 1-Indexed numeric white-space delimited edgelist with the type of
+/// each edge as a third column. Edges whose type is in `undirected_types` are
+/// reciprocated while the others keep their orientation. The types are not
+/// kept in the graph, so a pair of opposite directed edges and an undirected
+/// edge match the same motifs. Self-loops are dropped.
+pub fn load_typed_graph(
+    filepath: &str,
+    undirected_types: &[String],
+) -> Result<Graph<(), (), Directed>> {
+    let mut reader = open_input(filepath)?;
+    load_typed_graph_from_buffer(&mut reader, undirected_types)
+}
+
+/// Load a graph with typed edges from a buffer, see [`load_typed_graph`].
+pub fn load_typed_graph_from_buffer<B: BufRead>(
+    buffer: &mut B,
+    undirected_types: &[String],
+) -> Result<Graph<(), (), Directed>> {
+    let mut edges = Vec::new();
+    for (idx, line) in buffer.lines().enumerate() {
+        let line = line?;
+        if line.trim().is_empty() {
+            continue;
+        }
+        let mut split = line.split_whitespace();
+        let (Some(u), Some(v), Some(edge_type)) = (split.next(), split.next(), split.next()) else {
+            bail!(
+                "Line {}: expected two vertices and an edge type, found `{}`",
+                idx + 1,
+                line
+            );
+        };
+        let u = u.parse::<u32>()?;
+        let v = v.parse::<u32>()?;
+        if u == 0 || v == 0 {
+            bail!("ERROR: Found a node index: 0; Please use 1-indexed node indices.");
+        }
+        if u == v {
+            continue;
+        }
+        edges.push((u - 1, v - 1));
+        if undirected_types.iter().any(|t| t == edge_type) {
+            edges.push((v - 1, u - 1));
+        }
+    }
+    // An undirected edge given both ways is kept once.
+    edges.sort_unstable();
+    edges.dedup();
     Ok(Graph::from_edges(&edges))
 }
+
+/// Load a graph from the two integer columns of a Parquet edge table
+///
+/// Each row is an edge from its `src_col` vertex to its `dst_col` vertex,
+/// 1-Indexed unless `zero_indexed` is set.
+#[cfg(feature = "parquet")]
+pub fn load_parquet(
+    filepath: &str,
+    src_col: &str,
+    dst_col: &str,
+    zero_indexed: bool,
+) -> Result<Graph<(), (), Directed>> {
+    use parquet::file::reader::{FileReader, SerializedFileReader};
+
+    let reader = SerializedFileReader::new(File::open(filepath)?)?;
+    let offset = if zero_indexed { 0 } else { 1 };
+    let mut edges = Vec::new();
+    for (idx, row) in reader.get_row_iter(None)?.enumerate() {
+        let row = row?;
+        let (mut u, mut v) = (None, None);
+        for (name, field) in row.get_column_iter() {
+            if name == src_col {
+                u = Some(parquet_vertex(field, offset)?);
+            } else if name == dst_col {
+                v = Some(parquet_vertex(field, offset)?);
+            }
+        }
+        let (Some(u), Some(v)) = (u, v) else {
+            bail!(
+                "Row {}: expected the columns `{}` and `{}`",
+                idx + 1,
+                src_col,
+                dst_col
+            );
+        };
+        edges.push((u, v));
+    }
+    Ok(Graph::from_edges(&edges))
+}
+
+/// Fails as Parquet support is not compiled in, see the `parquet` feature.
+#[cfg(not(feature = "parquet"))]
+pub fn load_parquet(
+    _filepath: &str,
+    _src_col: &str,
+    _dst_col: &str,
+    _zero_indexed: bool,
+) -> Result<Graph<(), (), Directed>> {
+    bail!("ERROR: Reading Parquet requires building graphtries with the `parquet` feature")
+}
+
+/// Converts an integer field of a Parquet row to a 0-Indexed vertex.
+#[cfg(feature = "parquet")]
+fn parquet_vertex(field: &parquet::record::Field, offset: i64) -> Result<u32> {
+    use parquet::record::Field;
+
+    let index = match *field {
+        Field::Byte(x) => x as i64,
+        Field::Short(x) => x as i64,
+        Field::Int(x) => x as i64,
+        Field::Long(x) => x,
+        Field::UByte(x) => x as i64,
+        Field::UShort(x) => x as i64,
+        Field::UInt(x) => x as i64,
+        Field::ULong(x) => i64::try_from(x)?,
+        _ => bail!("ERROR: Expected an integer vertex column"),
+    };
+    if index < offset {
+        if offset == 1 && index == 0 {
+            bail!("ERROR: Found a node index: 0; Please use 1-indexed node indices.");
+        }
+        bail!("ERROR: Found a negative node index: {}", index);
+    }
+    Ok(u32::try_from(index - offset)?)
+}
+
+/// Load a numeric attribute for each vertex from a file
+///
+/// Expects one 1-Indexed vertex id and its value per line, white-space
+/// delimited. The returned map is keyed by the 0-Indexed vertex.
+pub fn load_vertex_attributes(filepath: &str) -> Result<HashMap<usize, f64>> {
+    let mut reader = open_file(filepath)?;
+    load_vertex_attributes_from_buffer(&mut reader)
+}
+
+/// Load vertex attributes from a buffer, see [`load_vertex_attributes`].
+pub fn load_vertex_attributes_from_buffer<B: BufRead>(
+    buffer: &mut B,
+) -> Result<HashMap<usize, f64>> {
+    let mut attributes = HashMap::new();
+    for (idx, line) in buffer.lines().enumerate() {
+        let line = line?;
+        if line.trim().is_empty() {
+            continue;
+        }
+        let mut split = line.split_whitespace();
+        let (Some(u), Some(value)) = (split.next(), split.next()) else {
+            bail!(
+                "Line {}: expected a vertex and a value, found `{}`",
+                idx + 1,
+                line
+            );
+        };
+        let u = u.parse::<usize>()?;
+        if u == 0 {
+            bail!("ERROR: Found a node index: 0; Please use 1-indexed node indices.");
+        }
+        attributes.insert(u - 1, value.parse::<f64>()?);
+    }
+    Ok(attributes)
+}
+
+#[cfg(test)]
+mod testing {
+    use super::*;
+
+    #[cfg(feature = "parquet")]
+    #[test]
+    fn parquet_edge_table() {
+        use crate::{bitgraph::Bitgraph, gtrie::Gtrie};
+        use parquet::{
+            data_type::Int64Type, file::writer::SerializedFileWriter,
+            schema::parser::parse_message_type,
+        };
+        use std::sync::Arc;
+
+        let expected = load_numeric_graph("example/graphs/example.txt", true, false).unwrap();
+        let (sources, targets): (Vec<i64>, Vec<i64>) = expected
+            .raw_edges()
+            .iter()
+            .map(|e| (e.source().index() as i64, e.target().index() as i64))
+            .unzip();
+
+        let path = std::env::temp_dir().join("graphtries_example.parquet");
+        let path = path.to_str().unwrap();
+        let schema = "message edges { REQUIRED INT64 src; REQUIRED INT64 dst; }";
+        let schema = Arc::new(parse_message_type(schema).unwrap());
+        let mut writer =
+            SerializedFileWriter::new(File::create(path).unwrap(), schema, Default::default())
+                .unwrap();
+        let mut row_group = writer.next_row_group().unwrap();
+        for column in [&sources, &targets] {
+            let mut column_writer = row_group.next_column().unwrap().unwrap();
+            column_writer
+                .typed::<Int64Type>()
+                .write_batch(column, None, None)
+                .unwrap();
+            column_writer.close().unwrap();
+        }
+        row_group.close().unwrap();
+        writer.close().unwrap();
+
+        let graph = load_parquet(path, "src", "dst", true).unwrap();
+        std::fs::remove_file(path).unwrap();
+        assert_eq!(graph.edge_count(), expected.edge_count());
+
+        let mut gtrie = Gtrie::read_from_file("example/gtries/dir3.gt").unwrap();
+        gtrie.census(&Bitgraph::from_graph(&expected));
+        let expected = gtrie.get_nonzero();
+        let mut gtrie = Gtrie::read_from_file("example/gtries/dir3.gt").unwrap();
+        gtrie.census(&Bitgraph::from_graph(&graph));
+        assert_eq!(gtrie.get_nonzero(), expected);
+        assert!(load_parquet(path, "src", "weight", true).is_err());
+    }
+
+    #[test]
+    fn open_input_file() {
+        let mut reader = open_input("example/graphs/example.txt").unwrap();
+        let from_input = load_numeric_graph_from_buffer(&mut reader, true, false).unwrap();
+        let from_path = load_numeric_graph("example/graphs/example.txt", true, false).unwrap();
+        assert_eq!(from_input.edge_count(), from_path.edge_count());
+        assert!(open_input("example/graphs/missing.txt").is_err());
+    }
+
+    #[test]
+    fn gzipped_inputs() {
+        let dir = std::env::temp_dir();
+        let edges = dir.join("graphtries_example.txt.gz");
+        let reprs = dir.join("graphtries_dir3.g6.gz");
+        let (edges, reprs) = (edges.to_str().unwrap(), reprs.to_str().unwrap());
+        for (path, source) in [
+            (edges, "example/graphs/example.txt"),
+            (reprs, "example/subgraphs/dir3.g6"),
+        ] {
+            let text = std::fs::read(source).unwrap();
+            write_file(path, |writer| Ok(writer.write_all(&text)?)).unwrap();
+            assert_ne!(std::fs::read(path).unwrap(), text);
+        }
+
+        let graph = load_numeric_graph(edges, true, false).unwrap();
+        let expected = load_numeric_graph("example/graphs/example.txt", true, false).unwrap();
+        assert_eq!(graph.edge_count(), expected.edge_count());
+        let n_graphs = iter_graphs_from_file(reprs).unwrap().count();
+        assert_eq!(
+            n_graphs,
+            iter_graphs_from_file("example/subgraphs/dir3.g6")
+                .unwrap()
+                .count()
+        );
+        std::fs::remove_file(edges).unwrap();
+        std::fs::remove_file(reprs).unwrap();
+    }
+
+    #[test]
+    fn undirected_repr_symmetric() {
+        assert_eq!(undirected_repr("&AW"), Some("A_".to_string()));
+        assert_eq!(undirected_repr("&AG"), None);
+        assert_eq!(output_repr("&AW", false), Some("A_".to_string()));
+        assert_eq!(output_repr("&AG", true), Some("&AG".to_string()));
+        assert_eq!(output_repr("&AG", false), None);
+    }
+
+    #[test]
+    fn load_repr_valid() {
+        let graph = load_repr("&AG").unwrap();
+        assert_eq!(graph.node_count(), 2);
+        assert_eq!(graph.edge_count(), 1);
+    }
+
+    #[test]
+    fn load_repr_undirected() {
+        // The connected graphs on 3 nodes as written by `geng -c 3`.
+        let path = load_repr("Bg").unwrap();
+        assert_eq!(path.node_count(), 3);
+        assert_eq!(path.edge_count(), 4);
+        let triangle = load_repr("Bw").unwrap();
+        assert_eq!(triangle.edge_count(), 6);
+        for edge in path.edge_indices() {
+            let (u, v) = path.edge_endpoints(edge).unwrap();
+            assert!(path.contains_edge(v, u));
+        }
+    }
+
+    #[test]
+    fn load_repr_missing_header() {
+        // A directed representation without its header is not valid undirected graph6.
+        let err = load_repr("AGG").unwrap_err().to_string();
+        assert!(err.contains("must start with '&'"));
+    }
+
+    #[test]
+    fn load_repr_empty() {
+        assert!(load_repr("").is_err());
+        assert!(load_repr("&").is_err());
+    }
+
+    #[test]
+    fn load_repr_truncated() {
+        assert!(load_repr(r"&B").is_err());
+        assert!(load_repr(r"&B\oo").is_err());
+    }
+
+    #[test]
+    fn load_repr_invalid_character() {
+        assert!(load_repr("&A ").is_err());
+    }
+
+    #[test]
+    fn vertex_attributes() {
+        let mut buffer = "1\t0.5\n3 2\n\n".as_bytes();
+        let attributes = load_vertex_attributes_from_buffer(&mut buffer).unwrap();
+        assert_eq!(attributes.len(), 2);
+        assert_eq!(attributes[&0], 0.5);
+        assert_eq!(attributes[&2], 2.0);
+        assert!(load_vertex_attributes_from_buffer(&mut "0 1.0".as_bytes()).is_err());
+        assert!(load_vertex_attributes_from_buffer(&mut "1".as_bytes()).is_err());
+    }
+
+    #[test]
+    fn typed_graph() {
+        let text = "1 2 regulates\n2 3 binds\n3 2 binds\n\n";
+        let undirected = vec!["binds".to_string()];
+        let graph = load_typed_graph_from_buffer(&mut text.as_bytes(), &undirected).unwrap();
+        let mut edges = graph
+            .raw_edges()
+            .iter()
+            .map(|e| (e.source().index(), e.target().index()))
+            .collect::<Vec<_>>();
+        edges.sort_unstable();
+        assert_eq!(edges, vec![(0, 1), (1, 2), (2, 1)]);
+
+        // Without undirected types every edge keeps its orientation.
+        let graph = load_typed_graph_from_buffer(&mut "1 2 a\n2 3 b".as_bytes(), &[]).unwrap();
+        assert_eq!(graph.edge_count(), 2);
+
+        assert!(load_typed_graph_from_buffer(&mut "1 2".as_bytes(), &undirected).is_err());
+        assert!(load_typed_graph_from_buffer(&mut "0 2 a".as_bytes(), &undirected).is_err());
+    }
+
+    #[test]
+    fn typed_graph_census() {
+        use crate::{bitgraph::Bitgraph, gtrie::Gtrie};
+
+        let text = "1 2 regulates\n2 3 binds\n";
+        let gtrie = Gtrie::read_from_file("example/gtries/dir3.gt").unwrap();
+        let census = |undirected_types: &[String]| {
+            let graph = load_typed_graph_from_buffer(&mut text.as_bytes(), undirected_types);
+            let graph = Bitgraph::from_graph(&graph.unwrap());
+            let mut counts = HashMap::new();
+            gtrie.census_into(&graph, &mut counts);
+            counts
+                .into_iter()
+                .filter(|(_, c)| *c > 0)
+                .collect::<Vec<_>>()
+        };
+        let directed = census(&[]);
+        let mixed = census(&["binds".to_string()]);
+        assert_eq!(directed.len(), 1);
+        assert_eq!(mixed.len(), 1);
+        assert_ne!(directed[0].0, mixed[0].0);
+    }
+
+    #[test]
+    fn adjacency_triangle_roundtrip() {
+        // A path on three vertices: 0 - 1 - 2.
+        let repr = "&BTO";
+        let full = adjacency_bits(repr).unwrap();
+        assert_eq!(full, "010101010");
+        let triangle = adjacency_triangle(repr).unwrap().unwrap();
+        assert_eq!(triangle, "101");
+        assert_eq!(adjacency_from_triangle(&triangle).unwrap(), full);
+
+        // The same motif given as undirected graph6.
+        let repr = undirected_repr("&BTO").unwrap();
+        assert_eq!(adjacency_triangle(&repr).unwrap().unwrap(), "101");
+
+        // Directed motifs have no triangle.
+        assert_eq!(adjacency_bits("&AG").unwrap(), "0010");
+        assert_eq!(adjacency_triangle("&AG").unwrap(), None);
+
+        assert!(adjacency_from_triangle("10").is_err());
+        assert!(adjacency_from_triangle("1x1").is_err());
+    }
+
+    #[test]
+    fn header_graph() {
+        let text = "6 3\n1 2\n2 3\n3 1\n";
+        let (graph, warning) = load_header_graph_from_buffer(&mut text.as_bytes()).unwrap();
+        assert_eq!(warning, None);
+        assert_eq!(graph.node_count(), 6);
+        assert_eq!(graph.edge_count(), 3);
+        let bgraph = crate::bitgraph::Bitgraph::from_graph(&graph);
+        assert_eq!(bgraph.n_nodes(), 6);
+        assert!(bgraph.neighbors(5).is_empty());
+
+        // A mismatched edge count only warns.
+        let (graph, warning) = load_header_graph_from_buffer(&mut "4 5\n1 2".as_bytes()).unwrap();
+        assert_eq!((graph.node_count(), graph.edge_count()), (4, 1));
+        assert_eq!(
+            warning,
+            Some(LoadWarning::EdgeCount {
+                declared: 5,
+                read: 1
+            })
+        );
+
+        assert!(load_header_graph_from_buffer(&mut "".as_bytes()).is_err());
+        assert!(load_header_graph_from_buffer(&mut "1 2\n".as_bytes()).is_ok());
+        assert!(load_header_graph_from_buffer(&mut "3\n1 2".as_bytes()).is_err());
+        assert!(load_header_graph_from_buffer(&mut "3 1\n1 4".as_bytes()).is_err());
+        assert!(load_header_graph_from_buffer(&mut "3 1\n0 1".as_bytes()).is_err());
+    }
+
+    #[test]
+    fn numeric_graph_malformed_lines() {
+        let graph = load_numeric_graph_from_buffer(&mut "1 2\n2 3\n\n  \n".as_bytes(), true, false)
+            .unwrap();
+        assert_eq!(graph.node_count(), 3);
+        assert_eq!(graph.edge_count(), 2);
+
+        let err =
+            load_numeric_graph_from_buffer(&mut "source target\n1 2\n".as_bytes(), true, false);
+        assert!(err.unwrap_err().to_string().contains("Line 1: "));
+        let err = load_numeric_graph_from_buffer(&mut "1 2\n3\n".as_bytes(), true, false);
+        assert!(err.unwrap_err().to_string().contains("Line 2: "));
+        assert!(load_numeric_graph_from_buffer(&mut "1 x\n".as_bytes(), true, false).is_err());
+        assert!(load_numeric_graph_from_buffer(&mut "0 1\n".as_bytes(), true, false).is_err());
+    }
+
+    #[test]
+    fn numeric_graph_comments_and_header() {
+        let expected = load_numeric_graph("example/graphs/example.txt", true, false).unwrap();
+        let graph =
+            load_numeric_graph_skipping("example/graphs/example_commented.txt", true, false, 1)
+                .unwrap();
+        assert_eq!(graph.node_count(), expected.node_count());
+        assert!(graph
+            .raw_edges()
+            .iter()
+            .zip(expected.raw_edges())
+            .all(|(a, b)| (a.source(), a.target()) == (b.source(), b.target())));
+        assert_eq!(graph.edge_count(), expected.edge_count());
+
+        // Without skipping it the header row is malformed.
+        assert!(load_numeric_graph("example/graphs/example_commented.txt", true, false).is_err());
+
+        // Skipped lines are data lines, and loops are filtered after skipping.
+        let text = "# comment\n1 1\n  # indented\n1 2\n2 2\n";
+        let graph =
+            load_numeric_graph_skipping_from_buffer(&mut text.as_bytes(), false, false, 1).unwrap();
+        assert_eq!(graph.edge_count(), 1);
+    }
+
+    #[test]
+    fn weighted_graph() {
+        let text = "# u v w\n1 2 0.5\n\n2 3\n3 1 2\n";
+        let (graph, weights) =
+            load_weighted_graph_from_buffer(&mut text.as_bytes(), true, false, 0).unwrap();
+        assert_eq!(graph.edge_count(), 3);
+        assert_eq!(weights.get(0, 1), 0.5);
+        assert_eq!(weights.get(1, 2), 1.0);
+        assert_eq!(weights.get(2, 0), 2.0);
+
+        let (_, weights) =
+            load_weighted_graph_from_buffer(&mut "0 1 3\n".as_bytes(), true, true, 0).unwrap();
+        assert_eq!(weights.get(0, 1), 3.0);
+
+        let err = load_weighted_graph_from_buffer(&mut "1 2\n2 3 x\n".as_bytes(), true, false, 0);
+        assert!(err.unwrap_err().to_string().contains("Line 2: "));
+        assert!(
+            load_weighted_graph_from_buffer(&mut "1 2 -1\n".as_bytes(), true, false, 0).is_err()
+        );
+
+        // The third column is ignored when the weights are not read.
+        let graph = load_numeric_graph_from_buffer(&mut "1 2 x\n".as_bytes(), true, false);
+        assert_eq!(graph.unwrap().edge_count(), 1);
+    }
+
+    #[test]
+    fn numeric_graph_zero_indexed() {
+        let text = "0 1\n1 2\n";
+        assert!(load_numeric_graph_from_buffer(&mut text.as_bytes(), true, false).is_err());
+        let graph = load_numeric_graph_from_buffer(&mut text.as_bytes(), true, true).unwrap();
+        assert_eq!(graph.node_count(), 3);
+        assert!(graph.contains_edge(0.into(), 1.into()));
+        assert!(graph.contains_edge(1.into(), 2.into()));
+
+        // A 1-indexed file is shifted by one, leaving vertex 0 isolated.
+        let graph = load_numeric_graph_from_buffer(&mut "1 2\n".as_bytes(), true, true).unwrap();
+        assert_eq!(graph.node_count(), 3);
+        assert!(graph.contains_edge(1.into(), 2.into()));
+    }
+
+    #[test]
+    fn adjacency_list() {
+        let text = "1 2 3\n\n2 3 2\n4\n";
+        let graph = load_adjacency_list_from_buffer(&mut text.as_bytes(), true).unwrap();
+        assert_eq!(graph.node_count(), 4);
+        assert_eq!(graph.edge_count(), 4);
+        assert_eq!(
+            LoadWarning::self_loops(&graph),
+            Some(LoadWarning::SelfLoops(1))
+        );
+        assert!(graph.contains_edge(0.into(), 2.into()));
+        assert!(graph.contains_edge(1.into(), 1.into()));
+        assert!(!graph.contains_edge(2.into(), 1.into()));
+
+        let graph = load_adjacency_list_from_buffer(&mut text.as_bytes(), false).unwrap();
+        assert_eq!(graph.edge_count(), 3);
+        assert_eq!(LoadWarning::self_loops(&graph), None);
+
+        assert!(load_adjacency_list_from_buffer(&mut "0 1\n".as_bytes(), true).is_err());
+        assert!(load_adjacency_list_from_buffer(&mut "0\n".as_bytes(), true).is_err());
+        assert!(load_adjacency_list_from_buffer(&mut "1 x\n".as_bytes(), true).is_err());
+    }
+
+    #[test]
+    fn dimacs_graph() {
+        let text = "c a comment\np edge 4 3\ne 1 2\nc another\ne 2 3\n\ne 3 1\n";
+        let (graph, warning) = load_dimacs_graph_from_buffer(&mut text.as_bytes(), true).unwrap();
+        assert_eq!(warning, None);
+        assert_eq!(graph.node_count(), 4);
+        assert_eq!(graph.edge_count(), 3);
+        assert!(graph.contains_edge(0.into(), 1.into()));
+        assert!(!graph.contains_edge(1.into(), 0.into()));
+
+        let text = "p edge 2 2\ne 1 1\ne 1 2\n";
+        let (graph, warning) = load_dimacs_graph_from_buffer(&mut text.as_bytes(), false).unwrap();
+        assert_eq!((graph.edge_count(), warning), (1, None));
+        let text = "p edge 2 3\ne 1 2\n";
+        let (_, warning) = load_dimacs_graph_from_buffer(&mut text.as_bytes(), true).unwrap();
+        assert_eq!(
+            warning,
+            Some(LoadWarning::EdgeCount {
+                declared: 3,
+                read: 1
+            })
+        );
+
+        assert!(load_dimacs_graph_from_buffer(&mut "".as_bytes(), true).is_err());
+        assert!(
+            load_dimacs_graph_from_buffer(&mut "e 1 2\np edge 2 1\n".as_bytes(), true).is_err()
+        );
+        assert!(
+            load_dimacs_graph_from_buffer(&mut "p edge 2 1\ne 1 3\n".as_bytes(), true).is_err()
+        );
+        assert!(
+            load_dimacs_graph_from_buffer(&mut "p edge 2 1\ne 0 1\n".as_bytes(), true).is_err()
+        );
+        assert!(load_dimacs_graph_from_buffer(&mut "p edge 2 1\n1 2\n".as_bytes(), true).is_err());
+    }
+}
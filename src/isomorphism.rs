@@ -1,10 +1,35 @@
-use crate::symmetry::{Condition, Conditions};
+use crate::{
+    bitgraph::Bitgraph,
+    symmetry::{Condition, Conditions},
+};
 use fixedbitset::FixedBitSet;
-use graph_canon::autom::AutoGroups;
+use graph_canon::{autom::AutoGroups, CanonLabeling};
 use itertools::Itertools;
 use petgraph::{Directed, Graph};
+use serde::{Deserialize, Serialize};
+
+/// When the canonical labeling excludes articulation points from the vertices
+/// it removes next, see `canonical_based_nauty`.
+///
+/// Every mode but `Auto` changes the labels, and so the structure of the tries
+/// built with it. Excluding articulation points is what keeps every prefix of
+/// a motif connected: with `Never` a prefix may be disconnected, which the
+/// census cannot match (see `prefixes_connected`).
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq, Hash, Serialize, Deserialize)]
+pub enum ApMode {
+    /// Always exclude articulation points.
+    Always,
+
+    /// Never exclude articulation points.
+    Never,
+
+    /// Exclude articulation points while more than three vertices remain.
+    #[default]
+    Auto,
+}
 
 /// A struct that holds the adjacency matrix and orbits of a graph
+#[derive(Clone, Debug, Serialize, Deserialize)]
 pub struct CanonicalBasedNauty {
     pub adj: FixedBitSet,
     pub orbits: Vec<usize>,
@@ -26,11 +51,20 @@ impl CanonicalBasedNauty {
         &self.adj
     }
 
+    /// Returns the symmetry breaking conditions of the graph or `None` if
+    /// the graph has no symmetries to break.
     pub fn conditions(&self) -> Option<&Conditions> {
         self.conditions.as_ref()
     }
 
-    #[allow(dead_code)]
+    /// Returns the directed graph6 representation of the canonical adjacency matrix.
+    pub fn repr(&self) -> String {
+        let bitvec = (0..self.size * self.size)
+            .map(|idx| usize::from(self.adj.contains(idx)))
+            .collect();
+        graph6_rs::write_graph6(bitvec, self.size, true)
+    }
+
     /// Pretty print the adjacency matrix, orbits, and symmetry breaking conditions
     /// for debugging purposes
     pub fn pprint(&self) {
@@ -64,11 +98,128 @@ impl CanonicalBasedNauty {
     }
 }
 
+/// Canonicalizes a graph as it is inserted into a gtrie: nauty's canonical
+/// labeling followed by `canonical_based_nauty`.
+///
+/// Returns the graph relabeled in its canonical order along with its
+/// canonical form.
+pub fn canonicalize(graph: &Graph<(), (), Directed>) -> (Bitgraph, CanonicalBasedNauty) {
+    canonicalize_with(graph, ApMode::Auto)
+}
+
+/// Equivalent to `canonicalize` with the given handling of articulation points.
+pub fn canonicalize_with(
+    graph: &Graph<(), (), Directed>,
+    ap_mode: ApMode,
+) -> (Bitgraph, CanonicalBasedNauty) {
+    let canon_graph: Graph<(), (), Directed> = CanonLabeling::new(graph).into();
+    let mut bgraph = Bitgraph::from_graph(&canon_graph);
+    let canon = canonical_based_nauty(bgraph.adjacency(), bgraph.n_nodes(), ap_mode);
+    bgraph.overwrite_adjacency(canon.adjacency());
+    (bgraph, canon)
+}
+
+/// Prepares a graph already in canonical form for insertion into a gtrie,
+/// keeping its labeling and only computing its orbits and conditions.
+///
+/// Nothing checks that the graph is canonical: isomorphic graphs given in
+/// different labelings are inserted as distinct motifs.
+pub fn assume_canonical(graph: &Graph<(), (), Directed>) -> (Bitgraph, CanonicalBasedNauty) {
+    let bgraph = Bitgraph::from_graph(graph);
+    let canon = with_symmetries(bgraph.adjacency().clone(), bgraph.n_nodes());
+    (bgraph, canon)
+}
+
+/// Checks that every vertex of a labeled graph is connected to a vertex of
+/// lower label, i.e. that every prefix of the graph is connected.
+pub fn prefixes_connected(adj: &FixedBitSet, size: usize) -> bool {
+    (1..size).all(|v| (0..v).any(|u| adj.contains(u * size + v) || adj.contains(v * size + u)))
+}
+
+/// Checks that a graph is connected, ignoring the direction of its edges.
+///
+/// Every vertex must be reached by the depth-first search used to find
+/// articulation points.
+pub fn is_connected(adj: &FixedBitSet, n: usize) -> bool {
+    if n == 0 {
+        return true;
+    }
+    let mut timer = 0;
+    let mut visited = vec![false; n];
+    dfs_articulation(
+        adj,
+        n,
+        0,
+        None,
+        &mut timer,
+        &mut visited,
+        &mut vec![-1; n],
+        &mut vec![-1; n],
+        &mut vec![false; n],
+        &vec![false; n],
+    );
+    visited.into_iter().all(|v| v)
+}
+
+/// Counts the automorphisms of a graph, including the identity.
+pub fn automorphism_group_size(graph: &Graph<(), (), Directed>) -> usize {
+    let aut = AutoGroups::from_petgraph(graph);
+    let n_nontrivial = aut
+        .automorphisms()
+        .iter()
+        .filter(|g| g.iter().enumerate().any(|(i, x)| *x as usize != i))
+        .count();
+    n_nontrivial + 1
+}
+
+/// The isomorphisms from `graph` onto `target`, as permutations `p` placing
+/// vertex `p[i]` of `graph` at vertex `i` of `target`.
+///
+/// Found by backtracking over the vertices of `target`, so meant for motifs
+/// rather than large graphs.
+pub fn isomorphisms(graph: &Bitgraph, target: &Bitgraph) -> Vec<Vec<usize>> {
+    let mut found = Vec::new();
+    if graph.n_nodes() == target.n_nodes() {
+        let mut placed = vec![false; graph.n_nodes()];
+        extend_isomorphism(graph, target, &mut Vec::new(), &mut placed, &mut found);
+    }
+    found
+}
+
+fn extend_isomorphism(
+    graph: &Bitgraph,
+    target: &Bitgraph,
+    perm: &mut Vec<usize>,
+    placed: &mut [bool],
+    found: &mut Vec<Vec<usize>>,
+) {
+    let i = perm.len();
+    if i == target.n_nodes() {
+        found.push(perm.clone());
+        return;
+    }
+    for v in 0..graph.n_nodes() {
+        let consistent = !placed[v]
+            && perm.iter().enumerate().all(|(j, u)| {
+                graph.is_connected(*u, v) == target.is_connected(j, i)
+                    && graph.is_connected(v, *u) == target.is_connected(i, j)
+            });
+        if consistent {
+            placed[v] = true;
+            perm.push(v);
+            extend_isomorphism(graph, target, perm, placed, found);
+            perm.pop();
+            placed[v] = false;
+        }
+    }
+}
+
 /// Algorithm: Converting a graph to a canonical form
 ///
 /// Require: Graph G
 /// Ensure: Canonical form of G
 ///
+/// ```text
 /// 1. function GTCanon(G)
 /// 2.    G := NautyLabeling(G)
 /// 3.    for all i in V(G) do
@@ -85,7 +236,14 @@ impl CanonicalBasedNauty {
 /// 14.       last_degree[] := current_degree[]
 /// 15.       update current_degree[] removing u_min connections
 /// 16.   return label_canon
-pub fn canonical_based_nauty(adj: &FixedBitSet, size: usize) -> CanonicalBasedNauty {
+/// ```
+///
+/// `ap_mode` selects when articulation points are excluded at line 9.
+pub fn canonical_based_nauty(
+    adj: &FixedBitSet,
+    size: usize,
+    ap_mode: ApMode,
+) -> CanonicalBasedNauty {
     let mut new_adj = FixedBitSet::with_capacity(size * size);
 
     let mut degree = vec![0; size];
@@ -106,11 +264,18 @@ pub fn canonical_based_nauty(adj: &FixedBitSet, size: usize) -> CanonicalBasedNa
         &mut last_degree,
         &mut used,
         &mut labels,
+        ap_mode,
     );
 
     // write the new adjacency matrix given the labels
     relabel_adj(adj, &mut new_adj, size, &labels);
 
+    with_symmetries(new_adj, size)
+}
+
+/// Computes the orbits and symmetry breaking conditions of a graph kept in
+/// its current labeling.
+fn with_symmetries(new_adj: FixedBitSet, size: usize) -> CanonicalBasedNauty {
     // Create the new graph to calculate the automorphism group
     let mut edges = Vec::with_capacity(size * size);
     for u in 0..size {
@@ -139,10 +304,16 @@ fn calculate_relabels(
     last_degree: &mut [usize],
     used: &mut [bool],
     labels: &mut [usize],
+    ap_mode: ApMode,
 ) {
     for pos in (0..size).rev() {
         // Find articulation points
-        let ap = if pos > 2 {
+        let exclude_ap = match ap_mode {
+            ApMode::Always => true,
+            ApMode::Never => false,
+            ApMode::Auto => pos > 2,
+        };
+        let ap = if exclude_ap {
             find_articulation_points(adj, size, used)
         } else {
             vec![false; size]
@@ -259,6 +430,10 @@ fn relabel_adj(adj: &FixedBitSet, new_adj: &mut FixedBitSet, size: usize, labels
     }
 }
 
+/// Identifies the orbit-fixing conditions of the automorphism group.
+///
+/// An empty set of conditions (i.e. the group is trivial) is represented as `None`
+/// so that it is treated identically to an unconditioned node in the gtrie.
 fn symmetry_breaking_conditions(aut: &AutoGroups, orbits: &[usize]) -> Option<Conditions> {
     if aut.size() == 0 {
         None
@@ -284,7 +459,11 @@ fn symmetry_breaking_conditions(aut: &AutoGroups, orbits: &[usize]) -> Option<Co
             }
         }
 
-        Some(Conditions::from_vec(conditions))
+        if conditions.is_empty() {
+            None
+        } else {
+            Some(Conditions::from_vec(conditions))
+        }
     }
 }
 
@@ -381,12 +560,34 @@ fn dfs_articulation(
 
 #[cfg(test)]
 mod testing {
+    use super::{
+        automorphism_group_size, canonical_based_nauty, canonicalize, isomorphisms, ApMode,
+    };
+    use crate::{
+        bitgraph::Bitgraph,
+        node::GtrieNode,
+        symmetry::{Condition, Conditions},
+    };
     use fixedbitset::FixedBitSet;
 
     fn insert_graph(adj: &mut FixedBitSet, n: usize, u: usize, v: usize) {
         adj.insert(u * n + v);
     }
 
+    #[test]
+    fn connectivity() {
+        let n = 4;
+        let mut adj = fixedbitset::FixedBitSet::with_capacity(n * n);
+        insert_graph(&mut adj, n, 0, 1);
+        insert_graph(&mut adj, n, 2, 1);
+        assert!(!super::is_connected(&adj, n));
+
+        // Direction is ignored.
+        insert_graph(&mut adj, n, 3, 2);
+        assert!(super::is_connected(&adj, n));
+        assert!(super::is_connected(&adj, 0));
+    }
+
     #[test]
     fn articulation_points_no_used_a() {
         let n = 4;
@@ -521,4 +722,88 @@ mod testing {
         let ap = super::find_articulation_points(&adj, n, &used);
         assert_eq!(ap, vec![false, true, false, true, false, true, false]);
     }
+
+    #[test]
+    fn conditions_asymmetric_motif() {
+        // feed-forward loop has a trivial automorphism group
+        let n = 3;
+        let mut adj = FixedBitSet::with_capacity(n * n);
+        insert_graph(&mut adj, n, 0, 1);
+        insert_graph(&mut adj, n, 1, 2);
+        insert_graph(&mut adj, n, 0, 2);
+
+        let canon = canonical_based_nauty(&adj, n, ApMode::Auto);
+        assert!(canon.conditions().is_none());
+    }
+
+    #[test]
+    fn conditions_symmetric_motif() {
+        let n = 3;
+        let mut adj = FixedBitSet::with_capacity(n * n);
+        insert_graph(&mut adj, n, 0, 1);
+        insert_graph(&mut adj, n, 0, 2);
+
+        let canon = canonical_based_nauty(&adj, n, ApMode::Auto);
+        let conditions = canon.conditions().unwrap();
+        assert!(!conditions.is_empty());
+    }
+
+    #[test]
+    fn empty_conditions_are_unconditioned() {
+        let empty = Conditions::from_vec(vec![]);
+        let node = GtrieNode::new_conditional(2, &empty);
+        assert!(node.conditions().is_none());
+
+        let conditions = Conditions::from_vec(vec![Condition::new(0, 1)]);
+        let mut node = GtrieNode::new_conditional(2, &conditions);
+        node.intersect_conditions(Some(&empty));
+        assert!(node.conditions().is_none());
+
+        let mut node = GtrieNode::new_conditional(2, &conditions);
+        node.intersect_conditions(None);
+        assert!(node.conditions().is_none());
+    }
+
+    #[test]
+    fn canonicalize_is_label_invariant() {
+        let path: petgraph::Graph<(), (), petgraph::Directed> =
+            petgraph::Graph::from_edges([(0, 1), (1, 2)]);
+        let relabeled: petgraph::Graph<(), (), petgraph::Directed> =
+            petgraph::Graph::from_edges([(2, 0), (0, 1)]);
+        let (graph, canon) = canonicalize(&path);
+        let (_, other) = canonicalize(&relabeled);
+        assert_eq!(canon.repr(), other.repr());
+        assert_eq!(canon.orbits, other.orbits);
+        assert_eq!(graph.edges().len(), 2);
+        assert_eq!(crate::io::load_repr(&canon.repr()).unwrap().edge_count(), 2);
+    }
+
+    #[test]
+    fn automorphism_group_sizes() {
+        let size = |edges: &[(u32, u32)]| {
+            automorphism_group_size(&petgraph::Graph::from_edges(edges.iter().copied()))
+        };
+        assert_eq!(size(&[(0, 1), (1, 2)]), 1);
+        assert_eq!(size(&[(0, 1), (0, 2)]), 2);
+        assert_eq!(size(&[(0, 1), (1, 2), (2, 0)]), 3);
+        assert_eq!(size(&[(0, 1), (1, 0), (1, 2), (2, 1), (2, 0), (0, 2)]), 6);
+    }
+
+    #[test]
+    fn isomorphisms_onto_target() {
+        let bitgraph = |edges: &[(u32, u32)]| {
+            Bitgraph::from_graph(&petgraph::Graph::<(), (), petgraph::Directed>::from_edges(
+                edges.iter().copied(),
+            ))
+        };
+        // The center of the out-star is vertex 2, then vertex 0 of the target.
+        let star = bitgraph(&[(2, 0), (2, 1)]);
+        let target = bitgraph(&[(0, 1), (0, 2)]);
+        let mut found = isomorphisms(&star, &target);
+        found.sort();
+        assert_eq!(found, vec![vec![2, 0, 1], vec![2, 1, 0]]);
+
+        // An in-star is not an out-star.
+        assert!(isomorphisms(&bitgraph(&[(0, 2), (1, 2)]), &target).is_empty());
+    }
 }
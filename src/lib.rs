@@ -0,0 +1,74 @@
+pub mod bitgraph;
+pub mod canon_cache;
+mod census;
+pub mod color;
+pub mod dynamic;
+pub mod explain;
+pub mod generate;
+pub mod gtrie;
+pub mod io;
+pub mod isomorphism;
+mod motif;
+mod node;
+mod probability;
+mod random;
+#[cfg(test)]
+mod reference;
+pub mod results;
+mod sampling;
+pub mod significance;
+mod symmetry;
+pub mod weight;
+
+pub use bitgraph::{Bitgraph, GraphQuery, Neighborhood};
+pub use gtrie::Gtrie;
+pub use io::{
+    load_header_graph, load_header_graph_from_buffer, load_numeric_graph,
+    load_numeric_graph_from_buffer, load_repr, load_typed_graph, load_typed_graph_from_buffer,
+};
+pub use isomorphism::CanonicalBasedNauty;
+pub use motif::MotifRef;
+pub use node::GtrieNode;
+pub use probability::EdgeProbs;
+pub use results::CensusSummary;
+pub use symmetry::Conditions;
+
+/// Building blocks of the census for composing custom enumerators.
+pub use census::{
+    extend_conditionally, extend_shared, match_child_conditionally, match_child_shared,
+    matches_structure, matching_vertices_conditionally, minimal_possible_index,
+    used_respects_conditions, Candidates,
+};
+
+#[cfg(test)]
+mod testing {
+    use super::*;
+    use petgraph::{Directed, Graph};
+
+    #[test]
+    fn census_in_memory() {
+        // A feed-forward loop and a cycle sharing no vertices.
+        let mut graph = Bitgraph::with_capacity(6, true);
+        for (u, v) in [(0, 1), (0, 2), (1, 2), (3, 4), (4, 5), (5, 3)] {
+            graph.insert_edge(u, v);
+        }
+
+        let mut gtrie = Gtrie::new(3);
+        let (feed_forward, _) = gtrie.insert_canonical(&Graph::<(), (), Directed>::from_edges([
+            (0, 1),
+            (0, 2),
+            (1, 2),
+        ]));
+        let (cycle, _) = gtrie.insert_canonical(&Graph::<(), (), Directed>::from_edges([
+            (0, 1),
+            (1, 2),
+            (2, 0),
+        ]));
+        gtrie.census(&graph);
+
+        let nonzero = gtrie.get_nonzero();
+        assert_eq!(nonzero.len(), 2);
+        assert_eq!(nonzero[&feed_forward], 1);
+        assert_eq!(nonzero[&cycle], 1);
+    }
+}
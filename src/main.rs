@@ -1,103 +1,1336 @@
-mod bitgraph;
-mod census;
 mod cli;
-mod gtrie;
-mod io;
-mod isomorphism;
-mod node;
-mod symmetry;
-
-use anyhow::Result;
-use bitgraph::Bitgraph;
+
+use anyhow::{anyhow, bail, Result};
 use clap::Parser;
-use cli::{Cli, Mode};
-use graph_canon::CanonLabeling;
-use gtrie::Gtrie;
-use indicatif::ProgressBar;
-use isomorphism::canonical_based_nauty;
-use petgraph::{Directed, Graph};
-
-fn build_gtrie(input: &str, output: String, size: usize, visualize: bool) -> Result<()> {
+use cli::{
+    AdjacencyFormat, ApModeKind, Cli, GtrieFormatKind, InputFormat, Mode, MotifFilter,
+    NullModelKind, OutputFormat, ResultOrder, SignificanceFormat,
+};
+use fixedbitset::FixedBitSet;
+use graphtries::{
+    bitgraph::Bitgraph,
+    canon_cache::CanonCache,
+    color::VertexColors,
+    explain::{Explanation, Rejection},
+    generate,
+    gtrie::{concentration, Gtrie, GtrieFormat},
+    io,
+    isomorphism::{canonicalize, canonicalize_with, is_connected, prefixes_connected, ApMode},
+    results::{Provenance, SampledEstimate},
+    significance::{self, significance, significance_profile, XorShift},
+    weight::EdgeWeights,
+};
+use hashbrown::HashMap;
+use indicatif::{ProgressBar, ProgressStyle};
+use itertools::Itertools;
+use rayon::prelude::*;
+use std::{
+    io::Write,
+    time::{Duration, Instant},
+};
+
+/// Options of `build` changing which graphs are inserted and how.
+#[derive(Default)]
+struct BuildOptions {
+    skip_bad: bool,
+    motif_filter: Option<MotifFilter>,
+    dedup_summary: bool,
+    assume_canonical: bool,
+    ap_mode: ApModeKind,
+    merge_reversals: bool,
+    undirected: bool,
+    format: Option<GtrieFormatKind>,
+    strict: bool,
+    cache: bool,
+    stats: bool,
+}
+
+/// Builds a gtrie from a file of graph6 graphs.
+///
+/// The input is streamed line by line so only the trie itself is held in memory.
+fn build_gtrie(
+    input: &str,
+    output: String,
+    size: usize,
+    visualize: bool,
+    options: &BuildOptions,
+) -> Result<()> {
+    let now = std::time::Instant::now();
     let mut gtrie = Gtrie::new(size);
+    let mut occurrences: HashMap<String, usize> = HashMap::new();
+    let ap_mode = match options.ap_mode {
+        ApModeKind::Always => ApMode::Always,
+        ApModeKind::Never => ApMode::Never,
+        ApModeKind::Auto => ApMode::Auto,
+    };
+
+    let cache_path = format!("{}.canon", output);
+    let mut cache = if options.cache && !options.assume_canonical {
+        match CanonCache::read_from_file(&cache_path) {
+            Ok(cache) => Some(cache),
+            Err(e) => {
+                eprintln!(
+                    "WARNING: Ignoring unreadable canonical label cache {} => {}",
+                    cache_path, e
+                );
+                Some(CanonCache::new())
+            }
+        }
+    } else {
+        None
+    };
+    let mut canon_cached = |line: &str, reversed: bool, graph: &_| match cache.as_mut() {
+        Some(cache) => cache.canonicalize_with(line, reversed, graph, ap_mode),
+        None => canonicalize_with(graph, ap_mode),
+    };
 
     let sp = ProgressBar::new_spinner();
     sp.set_message("Building gtrie...");
 
     let mut num_graphs = 0;
-    io::iter_graphs_from_file(input).enumerate().for_each(|(idx, graph)| {
-        // Create the canonical label of the graph
-        let canon_label = CanonLabeling::new(&graph);
-
-        // Convert the canonical label to a new graph
-        let canon_graph: Graph<(), (), Directed> = canon_label.into();
+    let mut num_skipped = 0;
+    let mut num_filtered = 0;
+    let mut num_disconnected = 0;
+    for (idx, graph) in io::iter_reprs_from_file(input)?.enumerate() {
+        let (line, graph) = match graph {
+            Ok(graph) => graph,
+            Err(e) if options.skip_bad => {
+                sp.suspend(|| eprintln!("Skipping malformed graph => {}", e));
+                num_skipped += 1;
+                continue;
+            }
+            Err(e) => bail!("Unable to parse input graph => {}", e),
+        };
 
-        // Convert to a bitgraph
-        let mut bgraph = Bitgraph::from_graph(&canon_graph);
+        let motif = Bitgraph::from_graph(&graph);
+        if !is_connected(motif.adjacency(), motif.n_nodes()) {
+            if options.strict {
+                bail!("ERROR: Input graph {} is disconnected", idx + 1);
+            }
+            sp.suspend(|| {
+                eprintln!(
+                    "WARNING: Skipping disconnected input graph {}; it can never be matched by the census",
+                    idx + 1
+                )
+            });
+            num_disconnected += 1;
+            continue;
+        }
 
-        // Compute the nauty-based canonical labeling
-        let canon_based_nauty = canonical_based_nauty(bgraph.adjacency(), size);
+        if options.undirected && !motif.is_symmetric() {
+            bail!(
+                "ERROR: Input graph {} is directed; --undirected expects undirected graphs",
+                idx + 1
+            );
+        }
 
-        // Overwrite the adjacency matrix with the new nauty-based one
-        bgraph.overwrite_adjacency(canon_based_nauty.adjacency());
+        if let Some(filter) = options.motif_filter {
+            let accepted = match filter {
+                MotifFilter::StronglyConnected => motif.is_strongly_connected(),
+                MotifFilter::Acyclic => motif.is_acyclic(),
+            };
+            if !accepted {
+                num_filtered += 1;
+                continue;
+            }
+        }
 
-        // Generate the nauty-representation of the new graph
-        let repr = graph6_rs::write_graph6(bgraph.as_bitvec(), bgraph.n_nodes(), bgraph.is_dir());
+        let (repr, _) = if options.assume_canonical {
+            gtrie.insert_assume_canonical(&graph)
+        } else {
+            let mut forms = vec![canon_cached(&line, false, &graph)];
+            if options.merge_reversals {
+                let mut reversed = graph.clone();
+                reversed.reverse();
+                forms.push(canon_cached(&line, true, &reversed));
+            }
+            if options.undirected {
+                forms = forms
+                    .into_iter()
+                    .map(|(bgraph, canon)| (bgraph.into_undirected(), canon))
+                    .collect();
+            }
+            for (bgraph, canon) in &forms {
+                if !prefixes_connected(bgraph.adjacency(), bgraph.n_nodes()) {
+                    bail!(
+                        "ERROR: The labeling of {} with --ap-mode {:?} has a disconnected prefix and cannot be censused",
+                        canon.repr(),
+                        options.ap_mode
+                    );
+                }
+            }
 
-        // Insert the graph into the gtrie
-        gtrie.insert(&bgraph, canon_based_nauty.conditions(), Some(repr));
+            // Both orientations are inserted so that the census matches either,
+            // under the smaller of their reprs.
+            let repr = forms.iter().map(|(_, canon)| canon.repr()).min().unwrap();
+            let mut is_new = false;
+            for (bgraph, canon) in &forms {
+                is_new |= gtrie.insert_canonical_form(bgraph, canon, repr.clone());
+            }
+            (repr, is_new)
+        };
+        if options.dedup_summary {
+            *occurrences.entry(repr).or_insert(0) += 1;
+        }
 
         if idx % 1000 == 0 {
             sp.set_message(format!("Building gtrie... {} graphs", idx));
         }
 
         num_graphs += 1;
-    });
+    }
+
+    sp.finish_with_message(format!(
+        "Finished building gtrie. {} graphs found.",
+        num_graphs
+    ));
+    if num_skipped > 0 {
+        eprintln!("Skipped {} malformed graphs.", num_skipped);
+    }
+    if num_filtered > 0 {
+        eprintln!("Filtered out {} graphs.", num_filtered);
+    }
+    if num_disconnected > 0 {
+        eprintln!("Skipped {} disconnected graphs.", num_disconnected);
+    }
+    if options.dedup_summary {
+        print_dedup_summary(num_graphs, &occurrences);
+    }
+    if options.stats {
+        eprintln!("Nodes: {}", gtrie.node_count());
+        eprintln!("Graph nodes: {}", gtrie.n_leaves());
+        eprintln!("Depth: {}", gtrie.depth());
+        eprintln!("Built gtrie: {:?}", now.elapsed());
+    }
+    if let Some(cache) = &cache {
+        eprintln!(
+            "Canonical label cache: {} hits, {} misses.",
+            cache.n_hits(),
+            cache.n_misses()
+        );
+        if cache.n_misses() > 0 {
+            if let Err(e) = cache.write_to_file(&cache_path) {
+                eprintln!(
+                    "WARNING: Unable to write canonical label cache {} => {}",
+                    cache_path, e
+                );
+            }
+        }
+    }
 
-    sp.finish_with_message(format!("Finished building gtrie. {} graphs found.", num_graphs));
+    eprintln!("Writing gtrie to file: {}", output);
+    match options.format {
+        Some(GtrieFormatKind::Msgpack) => {
+            gtrie.write_to_file_as(&output, GtrieFormat::MessagePack)?
+        }
+        Some(GtrieFormatKind::Json) => gtrie.write_to_file_as(&output, GtrieFormat::Json)?,
+        None => gtrie.write_to_file(&output)?,
+    }
+
+    if visualize {
+        gtrie.pprint(false, true);
+    }
+    Ok(())
+}
+
+/// Reports the distinct motifs of a build and those given more than once.
+fn print_dedup_summary(num_graphs: usize, occurrences: &HashMap<String, usize>) {
+    let mut duplicated = occurrences
+        .iter()
+        .filter(|(_, count)| **count > 1)
+        .collect::<Vec<_>>();
+    duplicated.sort_by(|a, b| b.1.cmp(a.1).then_with(|| a.0.cmp(b.0)));
+    eprintln!(
+        "Read {} graphs: {} distinct motifs, {} given more than once ({} redundant graphs).",
+        num_graphs,
+        occurrences.len(),
+        duplicated.len(),
+        num_graphs - occurrences.len()
+    );
+    for (repr, count) in duplicated {
+        eprintln!("{}\t{}", repr, count);
+    }
+}
+
+/// Builds a gtrie of all connected motifs from `min_size` to `max_size`.
+///
+/// With `low_memory` the motifs are inserted as they are generated rather than
+/// after generating every size, see `generate::for_each_connected_digraph`.
+fn generate_gtrie(
+    output: String,
+    min_size: usize,
+    max_size: usize,
+    visualize: bool,
+    low_memory: bool,
+) -> Result<()> {
+    if min_size == 0 || min_size > max_size {
+        bail!(
+            "Invalid motif size range: {}..={}; sizes must be positive and ordered",
+            min_size,
+            max_size
+        );
+    }
+    let mut gtrie = Gtrie::new(max_size);
+
+    let now = std::time::Instant::now();
+    let mut counts = vec![0; max_size];
+    if low_memory {
+        generate::for_each_connected_digraph(min_size, max_size, |graph| {
+            gtrie.insert_canonical(graph);
+            counts[graph.node_count() - 1] += 1;
+        });
+    } else {
+        let by_size = generate::connected_digraphs(max_size);
+        for (size, graphs) in by_size.iter().enumerate().skip(min_size - 1) {
+            for graph in graphs {
+                gtrie.insert_canonical(graph);
+            }
+            counts[size] = graphs.len();
+        }
+    }
+    for (size, count) in counts.iter().enumerate().skip(min_size - 1) {
+        eprintln!("Generated {} motifs of size {}", count, size + 1);
+    }
+    eprintln!("Generated gtrie: {:?}", now.elapsed());
 
     eprintln!("Writing gtrie to file: {}", output);
     gtrie.write_to_file(&output)?;
 
     if visualize {
-        gtrie.pprint(false);
+        gtrie.pprint(false, true);
     }
     Ok(())
 }
 
-fn visualize_gtrie(gtrie: &str) -> Result<()> {
-    let gtrie = Gtrie::read_from_file(&gtrie)?;
-    gtrie.pprint(false);
+fn visualize_gtrie(gtrie: &str, dot: bool) -> Result<()> {
+    let gtrie = Gtrie::read_from_file(gtrie)?;
+    if dot {
+        print!("{}", gtrie.to_dot());
+    } else {
+        gtrie.pprint(false, true);
+    }
     Ok(())
 }
 
-fn enumerate_subgraphs(gtrie: &str, input: &str) -> Result<Gtrie> {
-    let now = std::time::Instant::now();
-    let graph = io::load_numeric_graph(&input, true)?;
-    eprintln!("Loaded graph: {:?}", now.elapsed());
+/// Options of `enumerate` restricting the census or changing its report.
+#[derive(Default)]
+struct EnumerateOptions<'a> {
+    auto_undirected: bool,
+    undirected: bool,
+    kcore: Option<usize>,
+    timeout: Option<f64>,
+    require_edge: Option<(usize, usize)>,
+    vertex_filter: Option<(&'a str, f64)>,
+    ego: Option<(usize, usize)>,
+    embeddings: bool,
+    by_size: bool,
+    provenance: bool,
+    undirected_types: Option<&'a [String]>,
+    adjacency_format: Option<AdjacencyFormat>,
+    input_format: InputFormat,
+    skip_header: usize,
+    zero_indexed: bool,
+    parquet_columns: (&'a str, &'a str),
+    format: OutputFormat,
+    sort: ResultOrder,
+    dump_subgraphs: Option<&'a str>,
+    shard: Option<(usize, usize)>,
+    sample: Option<(&'a [f64], u64)>,
+    colors: Option<&'a str>,
+    threads: Option<usize>,
+    dump_occurrences: Option<&'a str>,
+    participation: Option<&'a str>,
+    min_edge_weight: Option<f64>,
+    progress: bool,
+}
+
+fn enumerate_subgraphs(
+    gtrie_path: &str,
+    inputs: &[&str],
+    options: &EnumerateOptions,
+) -> Result<Gtrie> {
+    if inputs.len() > 1
+        && (options.sample.is_some()
+            || options.colors.is_some()
+            || options.dump_subgraphs.is_some()
+            || options.dump_occurrences.is_some()
+            || options.participation.is_some())
+    {
+        bail!("ERROR: Sampling, color breakdowns and dumps are limited to a single input graph");
+    }
+    if options.zero_indexed
+        && !matches!(
+            options.input_format,
+            InputFormat::Edgelist | InputFormat::Parquet
+        )
+    {
+        bail!("ERROR: --zero-indexed only applies to edgelist and parquet inputs");
+    }
+    if options.min_edge_weight.is_some()
+        && (options.undirected_types.is_some()
+            || !matches!(options.input_format, InputFormat::Edgelist))
+    {
+        bail!("ERROR: --min-edge-weight reads the weights of an edgelist input");
+    }
 
     let now = std::time::Instant::now();
+    let mut gtrie = Gtrie::read_from_file(gtrie_path)?;
+    eprintln!("Loaded gtrie: {:?}", now.elapsed());
+
+    let colors = options.colors.map(VertexColors::from_file).transpose()?;
+
+    // Frequencies accumulate across the inputs, each census starting from
+    // fresh candidates and blacklist.
+    let mut estimates = None;
+    let mut breakdown = None;
+    let mut is_dir = false;
+    let mut timed_out = false;
+    for input in inputs {
+        let now = std::time::Instant::now();
+        let mut weights = None;
+        let (graph, warning) = match (options.undirected_types, options.input_format) {
+            (Some(undirected_types), _) => (io::load_typed_graph(input, undirected_types)?, None),
+            (None, InputFormat::Edgelist) if options.min_edge_weight.is_some() => {
+                let (graph, edge_weights) = io::load_weighted_graph(
+                    input,
+                    true,
+                    options.zero_indexed,
+                    options.skip_header,
+                )?;
+                weights = Some(edge_weights);
+                (graph, None)
+            }
+            (None, InputFormat::Edgelist) => (
+                io::load_numeric_graph_skipping(
+                    input,
+                    true,
+                    options.zero_indexed,
+                    options.skip_header,
+                )?,
+                None,
+            ),
+            (None, InputFormat::HeaderEdgelist) => io::load_header_graph(input)?,
+            (None, InputFormat::Dimacs) => io::load_dimacs_graph(input, true)?,
+            (None, InputFormat::Adjlist) => (io::load_adjacency_list(input, true)?, None),
+            (None, InputFormat::Parquet) => {
+                let (src_col, dst_col) = options.parquet_columns;
+                let graph = io::load_parquet(input, src_col, dst_col, options.zero_indexed)?;
+                (graph, None)
+            }
+            (None, InputFormat::Graph6) => (
+                io::iter_graphs_from_file(input)?
+                    .next()
+                    .ok_or_else(|| anyhow!("Expected a graph6 line in {}, found none", input))??,
+                None,
+            ),
+        };
+        report_load_warnings(&graph, warning);
+        eprintln!("Loaded graph: {:?}", now.elapsed());
+
+        let now = std::time::Instant::now();
+        let mut query = Bitgraph::from_graph(&graph);
+        if let Some(weights) = weights {
+            query = query.with_weights(weights);
+        }
+        if options.undirected {
+            query = query.symmetrize();
+        }
+        eprintln!("Converted to bitgraph: {:?}", now.elapsed());
+
+        // The vertex of the input graph of each vertex of `query`, once it is
+        // restricted to a subgraph. Vertex ids given and reported are those of
+        // the input graph.
+        let mut vertices: Option<Vec<usize>> = None;
+        if let Some((seed, hops)) = options.ego {
+            if seed >= query.n_nodes() {
+                bail!(
+                    "ERROR: Ego vertex {} is out of range for a graph of {} nodes",
+                    seed + 1,
+                    query.n_nodes()
+                );
+            }
+            let (ego, mapping) = query.ego_subgraph(seed, hops);
+            eprintln!(
+                "Restricted to {}-hop neighborhood of {}: {} of {} nodes",
+                hops,
+                seed + 1,
+                mapping.len(),
+                query.n_nodes()
+            );
+            query = ego;
+            vertices = Some(mapping);
+        }
+
+        if let Some(k) = options.kcore {
+            let (core, mapping) = query.k_core(k);
+            eprintln!(
+                "Restricted to {}-core: {} of {} nodes",
+                k,
+                mapping.len(),
+                query.n_nodes()
+            );
+            query = core;
+            vertices = Some(match vertices {
+                Some(vertices) => mapping.into_iter().map(|u| vertices[u]).collect(),
+                None => mapping,
+            });
+        }
+        let input_vertex = |u: usize| vertices.as_ref().map_or(u, |vertices| vertices[u]);
+
+        if options.auto_undirected && query.is_symmetric() {
+            eprintln!("Input graph is symmetric: counting undirected subgraphs");
+            query = query.into_undirected();
+        }
+
+        let mut excluded = FixedBitSet::with_capacity(query.n_nodes());
+        if let Some((path, min_attr)) = options.vertex_filter {
+            let attributes = io::load_vertex_attributes(path)?;
+            for u in 0..query.n_nodes() {
+                if !attributes
+                    .get(&input_vertex(u))
+                    .is_some_and(|x| *x >= min_attr)
+                {
+                    excluded.insert(u);
+                }
+            }
+            eprintln!(
+                "Excluded {} of {} nodes with an attribute below {}",
+                excluded.count_ones(..),
+                query.n_nodes(),
+                min_attr
+            );
+        }
+
+        gtrie.check_directedness(&query)?;
+        let now = std::time::Instant::now();
+        if let Some((probs, seed)) = options.sample {
+            estimates = Some(gtrie.census_sampled(&query, probs, seed)?);
+        } else if let Some(colors) = &colors {
+            breakdown = Some(match &vertices {
+                Some(vertices) => gtrie.census_by_color(&query, &colors.restricted(vertices))?,
+                None => gtrie.census_by_color(&query, colors)?,
+            });
+        } else if let Some(threads) = options.threads {
+            let pool = rayon::ThreadPoolBuilder::new()
+                .num_threads(threads)
+                .build()?;
+            pool.install(|| gtrie.census_parallel(&query));
+        } else if let Some(min_weight) = options.min_edge_weight {
+            gtrie.census_min_edge_weight(&query, &excluded, min_weight);
+        } else if options.progress {
+            let pb =
+                ProgressBar::new(query.n_nodes() as u64).with_style(ProgressStyle::with_template(
+                    "{bar:40} {percent}% ({pos}/{len} vertices, ETA {eta}) {msg}",
+                )?);
+            gtrie.census_with_progress(&query, |done, total_subgraphs| {
+                pb.set_position(done as u64);
+                pb.set_message(format!("{} subgraphs", total_subgraphs));
+            });
+            pb.finish();
+        } else if let Some(path) = options.dump_occurrences {
+            let mut writer = std::io::BufWriter::new(std::fs::File::create(path)?);
+            let mut result = Ok(());
+            let is_dir = query.is_dir();
+            gtrie.census_with_orbits(&query, |repr, used, orbits| {
+                if let (Ok(()), Some(repr)) = (&result, io::output_repr(repr, is_dir)) {
+                    let used = used.iter().map(|u| input_vertex(*u));
+                    let orbits = match orbits {
+                        Some(orbits) => orbits.iter().join(","),
+                        None => "-".to_string(),
+                    };
+                    result = writeln!(writer, "{}\t{}\t{}", repr, used.format("\t"), orbits);
+                }
+            });
+            result?;
+            writer.flush()?;
+            eprintln!("Wrote occurrences to file: {}", path);
+        } else if let Some(path) = options.participation {
+            let table = gtrie.census_participation(&query);
+            let mut writer = std::io::BufWriter::new(std::fs::File::create(path)?);
+            let offset = usize::from(!options.zero_indexed);
+            table.write_tsv(&mut writer, query.is_dir(), |v| input_vertex(v) + offset)?;
+            writer.flush()?;
+            eprintln!("Wrote participation counts to file: {}", path);
+        } else {
+            match (options.require_edge, options.timeout, options.shard) {
+                (Some((a, b)), _, _) => {
+                    if a >= graph.node_count() || b >= graph.node_count() {
+                        bail!(
+                            "ERROR: Edge {} -> {} is out of range for a graph of {} nodes",
+                            a + 1,
+                            b + 1,
+                            graph.node_count()
+                        );
+                    }
+                    let query_vertex =
+                        |v: usize| (0..query.n_nodes()).find(|u| input_vertex(*u) == v);
+                    match (query_vertex(a), query_vertex(b)) {
+                        (Some(a), Some(b)) => gtrie.census_with_edge(&query, a, b),
+                        _ => eprintln!(
+                            "WARNING: Edge {} -> {} is outside of the restricted graph; no subgraphs counted",
+                            a + 1,
+                            b + 1
+                        ),
+                    }
+                }
+                (None, Some(seconds), _) => {
+                    if !gtrie.census_until(&query, timeout_deadline(now, seconds)?) {
+                        timed_out = true;
+                        eprintln!(
+                            "WARNING: Census timed out after {}s; counts are partial",
+                            seconds
+                        );
+                    }
+                }
+                (None, None, Some((i, n))) => {
+                    let n_nodes = query.n_nodes();
+                    let range = i * n_nodes / n..(i + 1) * n_nodes / n;
+                    eprintln!(
+                        "Counting shard {} of {}: vertices {} to {}",
+                        i + 1,
+                        n,
+                        range.start + 1,
+                        range.end
+                    );
+                    gtrie.census_shard(&query, range);
+                }
+                (None, None, None) => gtrie.census_excluding(&query, &excluded),
+            }
+        }
+        eprintln!("Processed graph: {:?}", now.elapsed());
+
+        if let Some(path) = options.dump_subgraphs {
+            let mut writer = std::io::BufWriter::new(std::fs::File::create(path)?);
+            let n_written = gtrie.write_subgraphs(&query, &excluded, &mut writer)?;
+            writer.flush()?;
+            eprintln!("Wrote {} subgraphs to file: {}", n_written, path);
+        }
+
+        is_dir |= query.is_dir();
+    }
+    let total_subgraphs = match &breakdown {
+        Some(breakdown) => breakdown.values().sum::<u64>() as usize,
+        None => gtrie.total_subgraphs(),
+    };
+    eprintln!("Total subgraphs: {}", total_subgraphs);
+    if estimates.is_none() && breakdown.is_none() {
+        eprintln!("Summary: {}", gtrie.summary());
+    }
+
+    let provenance = options
+        .provenance
+        .then(|| provenance(gtrie_path, &gtrie, &inputs.join(","), None));
+    if options.format != OutputFormat::Json {
+        if let Some(provenance) = &provenance {
+            provenance.write_comments(&mut std::io::stdout().lock())?;
+        }
+        if timed_out {
+            println!("# timed_out: true");
+        }
+    }
+    if let Some(estimates) = estimates {
+        print_sampled_estimates(&estimates, is_dir);
+    } else if let Some((breakdown, colors)) = breakdown.zip(colors) {
+        print_color_breakdown(&breakdown, &colors, is_dir);
+    } else if let Some(format) = options.adjacency_format {
+        print_results_with_adjacency(&gtrie, is_dir, format)?;
+    } else if options.format == OutputFormat::Json {
+        let timed_out = options.timeout.map(|_| timed_out);
+        let json = results_as_json(&gtrie, is_dir, timed_out, provenance.as_ref());
+        println!("{}", json);
+    } else if options.format == OutputFormat::LongTsv {
+        write_long_tsv(&gtrie, is_dir, &mut std::io::stdout().lock())?;
+    } else if options.embeddings {
+        print_embedding_counts(&gtrie, is_dir)?;
+    } else if options.by_size {
+        print_results_by_size(&gtrie, is_dir);
+    } else if options.sort != ResultOrder::None {
+        print_sorted_results(&gtrie, is_dir, options.sort);
+    } else if is_dir {
+        gtrie.pprint_results();
+    } else {
+        gtrie.pprint_results_undirected();
+    }
+
+    Ok(gtrie)
+}
+
+/// The results of an enumerate census in the JSON format: the array of
+/// `Gtrie::results_as_json`, or an object of these `results` along with
+/// whether the census `timed_out` if it had a timeout and its `provenance`
+/// if requested.
+fn results_as_json(
+    gtrie: &Gtrie,
+    is_dir: bool,
+    timed_out: Option<bool>,
+    provenance: Option<&Provenance>,
+) -> serde_json::Value {
+    let results = gtrie.results_as_json(is_dir);
+    if timed_out.is_none() && provenance.is_none() {
+        return results;
+    }
+    let mut json = serde_json::json!({ "results": results });
+    if let Some(timed_out) = timed_out {
+        json["timed_out"] = timed_out.into();
+    }
+    if let Some(provenance) = provenance {
+        json["provenance"] = serde_json::json!(provenance);
+    }
+    json
+}
+
+/// Prints the frequency of every motif in the given order.
+fn print_sorted_results(gtrie: &Gtrie, is_dir: bool, sort: ResultOrder) {
+    let results = gtrie
+        .ranked_results()
+        .into_iter()
+        .filter_map(|(repr, freq)| io::output_repr(&repr, is_dir).map(|repr| (repr, freq)));
+    let results = match sort {
+        ResultOrder::Repr => results.sorted_unstable().collect::<Vec<_>>(),
+        _ => results.collect(),
+    };
+    for (repr, freq) in results {
+        println!("{}\t{}", repr, freq);
+    }
+}
+
+/// Prints the estimated frequency of every motif of a sampled census followed
+/// by the bounds of its 95% confidence interval.
+fn print_sampled_estimates(estimates: &[SampledEstimate], is_dir: bool) {
+    for estimate in estimates {
+        if let Some(repr) = io::output_repr(&estimate.repr, is_dir) {
+            println!(
+                "{}\t{:.1}\t{:.1}\t{:.1}",
+                repr, estimate.estimate, estimate.ci_low, estimate.ci_high
+            );
+        }
+    }
+}
+
+/// Prints the count of every motif for each combination of vertex colors, the
+/// colors joined by `-` in the order of the vertices of the repr.
+fn print_color_breakdown(
+    breakdown: &HashMap<(String, Vec<usize>), u64>,
+    colors: &VertexColors,
+    is_dir: bool,
+) {
+    let mut rows = breakdown
+        .iter()
+        .map(|((repr, tuple), count)| {
+            let names = tuple.iter().map(|c| colors.name(*c)).collect::<Vec<_>>();
+            (repr.as_str(), names.join("-"), *count)
+        })
+        .collect::<Vec<_>>();
+    rows.sort_unstable();
+    for (repr, names, count) in rows {
+        if let Some(repr) = io::output_repr(repr, is_dir) {
+            println!("{}\t{}\t{}", repr, names, count);
+        }
+    }
+}
+
+/// Parses a shard given as `i/n` (1-indexed) into `(i - 1, n)`.
+fn parse_shard(shard: &str) -> Result<(usize, usize)> {
+    let Some((i, n)) = shard.split_once('/') else {
+        bail!("ERROR: --shard expects a shard and a number of shards: i/n");
+    };
+    let (i, n) = (i.trim().parse::<usize>()?, n.trim().parse::<usize>()?);
+    if i == 0 || i > n {
+        bail!(
+            "ERROR: Shard {} is out of range for {} shards; Please use 1-indexed shards.",
+            i,
+            n
+        );
+    }
+    Ok((i - 1, n))
+}
+
+/// The instant `seconds` after `now`, at which a `--timeout` expires.
+fn timeout_deadline(now: Instant, seconds: f64) -> Result<Instant> {
+    Duration::try_from_secs_f64(seconds)
+        .ok()
+        .and_then(|timeout| now.checked_add(timeout))
+        .ok_or_else(|| {
+            anyhow!(
+                "ERROR: Timeout of {}s is not a valid non-negative number of seconds",
+                seconds
+            )
+        })
+}
+
+/// The invocation that produced the results, from the command line.
+fn provenance(gtrie_path: &str, gtrie: &Gtrie, input: &str, seed: Option<u64>) -> Provenance {
+    let command = std::env::args().collect();
+    Provenance::new(command, gtrie_path, gtrie, input, seed)
+}
+
+/// Prints the frequency of every motif followed by its adjacency matrix.
+fn print_results_with_adjacency(
+    gtrie: &Gtrie,
+    is_dir: bool,
+    format: AdjacencyFormat,
+) -> Result<()> {
+    for (repr, freq) in gtrie.results() {
+        let adjacency = match format {
+            AdjacencyFormat::Full => io::adjacency_bits(repr)?,
+            AdjacencyFormat::Triangle => match io::adjacency_triangle(repr)? {
+                Some(triangle) => triangle,
+                None => io::adjacency_bits(repr)?,
+            },
+        };
+        if let Some(repr) = io::output_repr(repr, is_dir) {
+            println!("{}\t{}\t{}", repr, freq, adjacency);
+        }
+    }
+    Ok(())
+}
+
+/// Prints the frequency and number of embeddings of every motif.
+fn print_embedding_counts(gtrie: &Gtrie, is_dir: bool) -> Result<()> {
+    for (repr, freq, embeddings) in gtrie.embedding_counts()? {
+        if let Some(repr) = io::output_repr(repr, is_dir) {
+            println!("{}\t{}\t{}", repr, freq, embeddings);
+        }
+    }
+    Ok(())
+}
+
+/// Prints a section per motif size with its total followed by its nonzero
+/// motifs by decreasing frequency.
+fn print_results_by_size(gtrie: &Gtrie, is_dir: bool) {
+    for (size, motifs) in gtrie.results_by_size() {
+        let mut motifs = motifs
+            .into_iter()
+            .filter(|(_, freq)| *freq > 0)
+            .filter_map(|(repr, freq)| io::output_repr(repr, is_dir).map(|repr| (repr, freq)))
+            .collect::<Vec<_>>();
+        motifs.sort_by(|a, b| b.1.cmp(&a.1).then_with(|| a.0.cmp(&b.0)));
+        let total = motifs.iter().map(|(_, freq)| freq).sum::<usize>();
+        println!("# size {}\ttotal {}", size, total);
+        for (repr, freq) in motifs {
+            println!("{}\t{}", repr, freq);
+        }
+    }
+}
+
+/// Writes the motifs as a table with a header, sorted by size then decreasing
+/// frequency. The concentration of a motif is relative to the total of its size.
+///
+/// Each motif is annotated with its number of edges and whether it is acyclic.
+/// For undirected graphs these are those of the undirected motif, which is
+/// acyclic if it is a tree.
+fn write_long_tsv<W: Write>(gtrie: &Gtrie, is_dir: bool, writer: &mut W) -> Result<()> {
+    writeln!(
+        writer,
+        "motif_size\tgraph6\tfrequency\tconcentration\tedges\tacyclic"
+    )?;
+    let mut motifs = gtrie.motifs();
+    motifs.sort_by_key(|motif| motif.size());
+    for (size, motifs) in &motifs.into_iter().group_by(|motif| motif.size()) {
+        // The orientations of a motif in a trie merging reversals share a repr
+        // and are reported as one row.
+        let mut index: HashMap<String, usize> = HashMap::new();
+        let mut rows: Vec<(String, usize, (usize, bool))> = Vec::new();
+        for motif in motifs {
+            let Some(repr) = io::output_repr(motif.repr(), is_dir) else {
+                continue;
+            };
+            let summary = if is_dir {
+                (motif.edge_count(), motif.is_acyclic())
+            } else {
+                let n_edges = motif.edge_count() / 2;
+                (n_edges, n_edges + 1 == size)
+            };
+            let row = (repr, motif.frequency(), summary);
+            match index.get(&row.0) {
+                Some(i) => rows[*i].1 += row.1,
+                None => {
+                    index.insert(row.0.clone(), rows.len());
+                    rows.push(row);
+                }
+            }
+        }
+        rows.sort_by(|a, b| b.1.cmp(&a.1).then_with(|| a.0.cmp(&b.0)));
+        let total = rows.iter().map(|(_, freq, _)| freq).sum::<usize>();
+        for (repr, freq, (n_edges, is_acyclic)) in rows {
+            writeln!(
+                writer,
+                "{}\t{}\t{}\t{:.6}\t{}\t{}",
+                size,
+                repr,
+                freq,
+                concentration(freq, total),
+                n_edges,
+                is_acyclic
+            )?;
+        }
+    }
+    Ok(())
+}
+
+fn explain_subgraph(gtrie: &str, input: &str, vertices: &[usize]) -> Result<()> {
+    if vertices.contains(&0) {
+        bail!("ERROR: Found a node index: 0; Please use 1-indexed node indices.");
+    }
+    let graph = load_graph(input)?;
+    let query = Bitgraph::from_graph(&graph);
+    let gtrie = Gtrie::read_from_file(gtrie)?;
+
+    let vertices = vertices.iter().map(|v| v - 1).collect::<Vec<_>>();
+    let one_indexed = |used: &[usize]| {
+        used.iter()
+            .map(|v| (v + 1).to_string())
+            .collect::<Vec<_>>()
+            .join(",")
+    };
+    match gtrie.explain_vertices(&query, &vertices) {
+        Explanation::Matched { repr, used } => {
+            println!(
+                "Matched: [{}] -> {}",
+                one_indexed(&used),
+                repr.unwrap_or_else(|| "<no repr>".to_string())
+            );
+        }
+        Explanation::Unmatched { used, reason } => {
+            println!("Not matched after placing [{}]", one_indexed(&used));
+            match reason {
+                Rejection::Empty => println!("Reason: no vertices given"),
+                Rejection::OutOfRange { vertex } => {
+                    println!("Reason: vertex {} is not in the graph", vertex + 1)
+                }
+                Rejection::Duplicate { vertex } => {
+                    println!("Reason: vertex {} was given more than once", vertex + 1)
+                }
+                Rejection::NoPath => {
+                    println!("Reason: the gtrie has no motifs of size {}", vertices.len())
+                }
+                Rejection::Structure {
+                    vertex,
+                    position,
+                    src,
+                    dst,
+                    expected,
+                } => println!(
+                    "Reason: vertex {} at position {} expects edge {} -> {} to be {}",
+                    vertex + 1,
+                    position,
+                    src + 1,
+                    dst + 1,
+                    if expected { "present" } else { "absent" }
+                ),
+                Rejection::Condition { condition, u, v } => println!(
+                    "Reason: symmetry condition {} requires {} < {}",
+                    condition,
+                    u + 1,
+                    v + 1
+                ),
+                Rejection::NotAGraph => println!("Reason: the path reached is not a motif"),
+            }
+        }
+    }
+    Ok(())
+}
+
+fn compare_subgraphs(gtrie: &str, input_a: &str, input_b: &str) -> Result<()> {
+    let graph_a = Bitgraph::from_graph(&load_graph(input_a)?);
+    let graph_b = Bitgraph::from_graph(&load_graph(input_b)?);
+    let gtrie = Gtrie::read_from_file(gtrie)?;
+
+    println!("graph6\tcount_a\tcount_b\tdiff\tlog2fc");
+    for (repr, count_a, count_b) in gtrie.compare(&graph_a, &graph_b) {
+        println!(
+            "{}\t{}\t{}\t{}\t{:.4}",
+            repr,
+            count_a,
+            count_b,
+            count_a as i64 - count_b as i64,
+            log2_fold_change(count_a, count_b)
+        );
+    }
+    Ok(())
+}
+
+/// Log2 fold change of `a` over `b` with a pseudocount of one so that motifs
+/// missing from either graph remain finite.
+fn log2_fold_change(a: usize, b: usize) -> f64 {
+    ((a as f64 + 1.0) / (b as f64 + 1.0)).log2()
+}
+
+struct SignificanceOptions {
+    num_random: usize,
+    null_model: NullModelKind,
+    swaps_per_edge: usize,
+    seed: u64,
+    jobs: usize,
+    provenance: bool,
+    format: SignificanceFormat,
+}
+
+fn significance_subgraphs(gtrie: &str, input: &str, options: &SignificanceOptions) -> Result<()> {
+    let graph = load_graph(input)?;
     let query = Bitgraph::from_graph(&graph);
-    eprintln!("Converted to bitgraph: {:?}", now.elapsed());
+    let gtrie_path = gtrie;
+    let gtrie = Gtrie::read_from_file(gtrie_path)?;
+    let (kind, swaps_per_edge) = (options.null_model, options.swaps_per_edge);
+    let null_model = move |graph: &Bitgraph, rng: &mut XorShift| match kind {
+        NullModelKind::Swap => significance::edge_swap_n(graph, rng, swaps_per_edge),
+        NullModelKind::Er => significance::erdos_renyi(graph, rng),
+        NullModelKind::Config => significance::configuration(graph, rng),
+    };
 
+    let (num_random, seed) = (options.num_random, options.seed);
+    let pool = rayon::ThreadPoolBuilder::new()
+        .num_threads(options.jobs)
+        .build()?;
     let now = std::time::Instant::now();
-    let mut gtrie = Gtrie::read_from_file(&gtrie)?;
-    eprintln!("Loaded gtrie: {:?}", now.elapsed());
+    let results = pool.install(|| significance(&gtrie, &query, null_model, num_random, seed));
+    eprintln!("Censused {} random graphs: {:?}", num_random, now.elapsed());
+
+    if options.provenance {
+        provenance(gtrie_path, &gtrie, input, Some(seed))
+            .write_comments(&mut std::io::stdout().lock())?;
+    }
+    match options.format {
+        SignificanceFormat::Table => {
+            println!("graph6\tcount\tmean\tstd\tzscore\tpvalue");
+            for stats in results {
+                println!(
+                    "{}\t{}\t{:.4}\t{:.4}\t{:.4}\t{:.4}",
+                    stats.repr, stats.count, stats.mean, stats.std, stats.z_score, stats.p_value
+                );
+            }
+        }
+        SignificanceFormat::Sp => {
+            println!("graph6\tsp");
+            for (repr, sp) in significance_profile(&results) {
+                println!("{}\t{:.6}", repr, sp);
+            }
+        }
+    }
+    Ok(())
+}
+
+fn batch_subgraphs(
+    gtrie: &str,
+    inputs: &[String],
+    jobs: usize,
+    timeout: Option<f64>,
+) -> Result<()> {
+    let gtrie = Gtrie::read_from_file(gtrie)?;
+    let pool = rayon::ThreadPoolBuilder::new().num_threads(jobs).build()?;
 
     let now = std::time::Instant::now();
-    gtrie.census(&query);
-    eprintln!("Processed graph: {:?}", now.elapsed());
-    eprintln!("Total subgraphs: {}", gtrie.total_subgraphs());
+    let results = pool.install(|| {
+        inputs
+            .par_iter()
+            .map(|input| {
+                let graph = load_graph(input)?;
+                let query = Bitgraph::from_graph(&graph);
+                let mut counts = HashMap::new();
+                match timeout {
+                    Some(seconds) => {
+                        let deadline = timeout_deadline(std::time::Instant::now(), seconds)?;
+                        if gtrie
+                            .census_into_until(&query, &mut counts, deadline)
+                            .is_none()
+                        {
+                            eprintln!("Census of {} timed out after {}s", input, seconds);
+                            return Ok(None);
+                        }
+                    }
+                    None => {
+                        gtrie.census_into(&query, &mut counts);
+                    }
+                }
+                Ok(Some(counts))
+            })
+            .collect::<Result<Vec<_>>>()
+    })?;
+    eprintln!("Processed {} graphs: {:?}", inputs.len(), now.elapsed());
+
+    println!("graph6\t{}", inputs.join("\t"));
+    for repr in gtrie.reprs() {
+        let row = results
+            .iter()
+            .map(|counts| match counts {
+                Some(counts) => counts.get(repr).unwrap_or(&0).to_string(),
+                None => "NA".to_string(),
+            })
+            .collect::<Vec<_>>();
+        println!("{}\t{}", repr, row.join("\t"));
+    }
+    Ok(())
+}
 
-    gtrie.pprint_results();
+/// Loads a file holding either a single graph6 line or an edge list.
+/// Loads an edge list keeping its self-loops, see `report_load_warnings`.
+fn load_graph(input: &str) -> Result<petgraph::Graph<(), (), petgraph::Directed>> {
+    let graph = io::load_numeric_graph(input, true, false)?;
+    report_load_warnings(&graph, None);
+    Ok(graph)
+}
 
-    Ok(gtrie)
+/// Prints the warning returned by the loader of an input graph, if any, and
+/// warns about the self-loops it kept.
+fn report_load_warnings(
+    graph: &petgraph::Graph<(), (), petgraph::Directed>,
+    warning: Option<io::LoadWarning>,
+) {
+    for warning in warning
+        .into_iter()
+        .chain(io::LoadWarning::self_loops(graph))
+    {
+        eprintln!("WARNING: {}", warning);
+    }
+}
+
+fn load_motif(input: &str) -> Result<petgraph::Graph<(), (), petgraph::Directed>> {
+    let contents = std::fs::read_to_string(input)?;
+    let trimmed = contents.trim();
+    if trimmed.lines().count() == 1 && trimmed.split_whitespace().count() == 1 {
+        io::load_repr(trimmed)
+    } else {
+        io::load_numeric_graph(input, false, false)
+    }
+}
+
+fn canon_motif(input: &str) -> Result<()> {
+    let graph = load_motif(input)?;
+    let (bgraph, canon) = canonicalize(&graph);
+    println!("Repr: {}", canon.repr());
+    println!("Canonical adjacency:");
+    bgraph.pprint();
+    canon.pprint();
+    Ok(())
+}
+
+/// Prints a weighted reservoir sample of the occurrences of every motif as the
+/// motif, the weight of the occurrence and its 1-indexed vertices.
+fn sample_occurrences(
+    gtrie: &str,
+    input: &str,
+    k: usize,
+    edge_weights: Option<&str>,
+    seed: u64,
+) -> Result<()> {
+    let graph = load_graph(input)?;
+    let query = Bitgraph::from_graph(&graph);
+    let gtrie = Gtrie::read_from_file(gtrie)?;
+    gtrie.check_directedness(&query)?;
+    let weights = edge_weights.map(EdgeWeights::from_file).transpose()?;
+
+    let samples = gtrie.sample_occurrences(&query, k, weights.as_ref(), seed);
+    for repr in gtrie.reprs() {
+        for used in samples.get(repr).into_iter().flatten() {
+            let weight = weights
+                .as_ref()
+                .map_or(1.0, |w| w.occurrence_weight(&query, used));
+            let vertices = used.iter().map(|v| (v + 1).to_string()).join(",");
+            println!("{}\t{}\t{}", repr, weight, vertices);
+        }
+    }
+    Ok(())
+}
+
+fn validate_gtrie(gtrie: &str, input: &str) -> Result<()> {
+    let graph = load_graph(input)?;
+    let query = Bitgraph::from_graph(&graph);
+    let gtrie = Gtrie::read_from_file(gtrie)?;
+    gtrie.check_directedness(&query)?;
+    gtrie.validate_against_brute_force(&query)?;
+    println!(
+        "Census agrees with brute force on {} motifs",
+        gtrie.reprs().len()
+    );
+    Ok(())
+}
+
+/// Checks whether two graphs are isomorphic by comparing the reprs of their
+/// canonical forms, printing the verdict and both reprs.
+fn iso_graphs(a: &str, b: &str) -> Result<bool> {
+    let repr_a = canonicalize(&load_motif(a)?).1.repr();
+    let repr_b = canonicalize(&load_motif(b)?).1.repr();
+    let is_isomorphic = repr_a == repr_b;
+    if is_isomorphic {
+        println!("isomorphic");
+    } else {
+        println!("not isomorphic");
+    }
+    println!("{}\t{}", a, repr_a);
+    println!("{}\t{}", b, repr_b);
+    Ok(is_isomorphic)
+}
+
+fn repair_gtrie(input: &str, output: &str) -> Result<()> {
+    let mut gtrie = Gtrie::read_from_file(input)?;
+    let n_set = gtrie.recompute_reprs();
+    eprintln!("Recomputed {} missing reprs", n_set);
+    eprintln!("Writing gtrie to file: {}", output);
+    gtrie.write_to_file(output)?;
+    Ok(())
+}
+
+fn merge_gtries(inputs: &[String], output: &str) -> Result<()> {
+    let mut gtrie = Gtrie::read_from_file(&inputs[0])?;
+    for input in &inputs[1..] {
+        gtrie
+            .merge(Gtrie::read_from_file(input)?)
+            .map_err(|e| anyhow!("ERROR: Unable to merge {}: {}", input, e))?;
+    }
+    eprintln!("Merged {} gtries", inputs.len());
+    eprintln!("Writing gtrie to file: {}", output);
+    gtrie.write_to_file(output)?;
+    Ok(())
+}
+
+fn export_motif(gtrie_path: &str, repr: &str, output: &str) -> Result<()> {
+    let gtrie = Gtrie::read_from_file(gtrie_path)?;
+    let Some(motif) = gtrie.motif(repr) else {
+        bail!("ERROR: Motif {} is not in the gtrie {}", repr, gtrie_path);
+    };
+    let mut writer = std::io::BufWriter::new(std::fs::File::create(output)?);
+    motif.write_graphml(&mut writer)?;
+    writer.flush()?;
+    eprintln!("Wrote motif {} to file: {}", repr, output);
+    Ok(())
 }
 
 fn main() -> Result<()> {
     let cli = Cli::parse();
     match cli.mode {
-        Mode::Enumerate { gtrie, input } => {
-            enumerate_subgraphs(&gtrie, &input)?;
+        Mode::Enumerate {
+            gtrie,
+            input,
+            explain,
+            auto_undirected,
+            undirected,
+            kcore,
+            timeout,
+            require_edge,
+            vertex_filter,
+            min_attr,
+            ego,
+            hops,
+            embeddings,
+            by_size,
+            provenance,
+            undirected_types,
+            adjacency_format,
+            input_format,
+            skip_header,
+            zero_indexed,
+            src_col,
+            dst_col,
+            format,
+            sort,
+            dump_subgraphs,
+            shard,
+            sample,
+            seed,
+            colors,
+            breakdown_by_color: _,
+            threads,
+            dump_occurrences,
+            participation,
+            min_edge_weight,
+            progress,
+        } => {
+            if let Some(vertices) = explain {
+                let [input] = &input[..] else {
+                    bail!("ERROR: --explain is limited to a single input graph");
+                };
+                explain_subgraph(&gtrie, input, &vertices)?;
+            } else {
+                let require_edge = match require_edge.as_deref() {
+                    Some([a, b]) => {
+                        if *a == 0 || *b == 0 {
+                            bail!(
+                                "ERROR: Found a node index: 0; Please use 1-indexed node indices."
+                            );
+                        }
+                        Some((a - 1, b - 1))
+                    }
+                    Some(_) => bail!("ERROR: --require-edge expects exactly two nodes: a,b"),
+                    None => None,
+                };
+                let options = EnumerateOptions {
+                    auto_undirected,
+                    undirected,
+                    kcore,
+                    timeout,
+                    require_edge,
+                    vertex_filter: vertex_filter.as_deref().zip(min_attr),
+                    ego: match ego {
+                        Some(0) => bail!(
+                            "ERROR: Found a node index: 0; Please use 1-indexed node indices."
+                        ),
+                        Some(seed) => Some((seed - 1, hops)),
+                        None => None,
+                    },
+                    embeddings,
+                    by_size,
+                    provenance,
+                    undirected_types: undirected_types.as_deref(),
+                    adjacency_format,
+                    input_format,
+                    skip_header,
+                    zero_indexed,
+                    parquet_columns: (&src_col, &dst_col),
+                    format,
+                    sort,
+                    dump_subgraphs: dump_subgraphs.as_deref(),
+                    shard: shard.as_deref().map(parse_shard).transpose()?,
+                    sample: sample.as_deref().map(|probs| (probs, seed)),
+                    colors: colors.as_deref(),
+                    threads,
+                    dump_occurrences: dump_occurrences.as_deref(),
+                    participation: participation.as_deref(),
+                    min_edge_weight,
+                    progress,
+                };
+                let inputs = input.iter().map(String::as_str).collect::<Vec<_>>();
+                enumerate_subgraphs(&gtrie, &inputs, &options)?;
+            }
+        }
+
+        Mode::Compare {
+            gtrie,
+            input_a,
+            input_b,
+        } => {
+            compare_subgraphs(&gtrie, &input_a, &input_b)?;
+        }
+
+        Mode::Significance {
+            gtrie,
+            input,
+            num_random,
+            null_model,
+            swaps_per_edge,
+            seed,
+            jobs,
+            provenance,
+            format,
+        } => {
+            let options = SignificanceOptions {
+                num_random,
+                null_model,
+                swaps_per_edge,
+                seed,
+                jobs,
+                provenance,
+                format,
+            };
+            significance_subgraphs(&gtrie, &input, &options)?;
+        }
+
+        Mode::Batch {
+            gtrie,
+            input,
+            jobs,
+            timeout,
+        } => {
+            batch_subgraphs(&gtrie, &input, jobs, timeout)?;
         }
 
         Mode::Build {
@@ -105,12 +1338,92 @@ fn main() -> Result<()> {
             output,
             size,
             visualize,
+            skip_bad,
+            motif_filter,
+            dedup_summary,
+            assume_canonical,
+            ap_mode,
+            merge_reversals,
+            undirected,
+            format,
+            strict,
+            no_cache,
+            stats,
+        } => {
+            let options = BuildOptions {
+                skip_bad,
+                motif_filter,
+                dedup_summary,
+                assume_canonical,
+                ap_mode,
+                merge_reversals,
+                undirected,
+                format,
+                strict,
+                cache: !no_cache,
+                stats,
+            };
+            build_gtrie(&input, output, size, visualize, &options)?;
+        }
+
+        Mode::Generate {
+            output,
+            size,
+            min_size,
+            max_size,
+            visualize,
+            low_memory,
         } => {
-            build_gtrie(&input, output, size, visualize)?;
+            let min_size = size.or(min_size).unwrap_or(3);
+            let max_size = size.or(max_size).unwrap_or(min_size);
+            generate_gtrie(output, min_size, max_size, visualize, low_memory)?;
         }
 
-        Mode::Visualize { input } => {
-            visualize_gtrie(&input)?;
+        Mode::Canon { input } => {
+            canon_motif(&input)?;
+        }
+
+        Mode::SampleOccurrences {
+            gtrie,
+            input,
+            k,
+            edge_weights,
+            seed,
+        } => {
+            sample_occurrences(&gtrie, &input, k, edge_weights.as_deref(), seed)?;
+        }
+
+        Mode::Validate { gtrie, input } => {
+            validate_gtrie(&gtrie, &input)?;
+        }
+
+        Mode::Iso { a, b } => match iso_graphs(&a, &b) {
+            Ok(true) => {}
+            Ok(false) => std::process::exit(1),
+            Err(e) => {
+                eprintln!("Error: {:?}", e);
+                std::process::exit(2);
+            }
+        },
+
+        Mode::Merge { input, output } => {
+            merge_gtries(&input, &output)?;
+        }
+
+        Mode::Repair { input, output } => {
+            repair_gtrie(&input, &output)?;
+        }
+
+        Mode::ExportMotif {
+            gtrie,
+            repr,
+            output,
+        } => {
+            export_motif(&gtrie, &repr, &output)?;
+        }
+
+        Mode::Visualize { input, dot } => {
+            visualize_gtrie(&input, dot)?;
         }
     }
 
@@ -119,13 +1432,24 @@ fn main() -> Result<()> {
 
 #[cfg(test)]
 mod testing {
-    use crate::enumerate_subgraphs;
+    use crate::{
+        build_gtrie, cli::MotifFilter, enumerate_subgraphs, iso_graphs, log2_fold_change,
+        parse_shard, provenance, results_as_json, timeout_deadline, write_long_tsv, BuildOptions,
+        EnumerateOptions,
+    };
+    use graphtries::{
+        bitgraph::Bitgraph, canon_cache::CanonCache, generate, gtrie::Gtrie, io,
+        isomorphism::ApMode,
+    };
+    use hashbrown::HashMap;
+    use itertools::Itertools;
 
     #[test]
     fn test_example_dir3() {
         let graph_path = "example/graphs/example.txt";
         let gtrie_path = "example/gtries/dir3.gt";
-        let gtrie = enumerate_subgraphs(gtrie_path, graph_path).unwrap();
+        let gtrie =
+            enumerate_subgraphs(gtrie_path, &[graph_path], &EnumerateOptions::default()).unwrap();
         let nonzero = gtrie.get_nonzero();
         assert_eq!(gtrie.total_subgraphs(), 16);
         assert_eq!(nonzero.len(), 4);
@@ -135,11 +1459,205 @@ mod testing {
         });
     }
 
+    #[test]
+    fn test_example_dir3_shared() {
+        let graph =
+            crate::io::load_numeric_graph("example/graphs/example.txt", true, false).unwrap();
+        let query = crate::Bitgraph::from_graph(&graph);
+        let gtrie = crate::Gtrie::read_from_file("example/gtries/dir3.gt").unwrap();
+        let mut counts = hashbrown::HashMap::new();
+        assert_eq!(gtrie.census_into(&query, &mut counts), 16);
+        assert_eq!(gtrie.census_into(&query, &mut counts), 16);
+        let expected = enumerate_subgraphs(
+            "example/gtries/dir3.gt",
+            &["example/graphs/example.txt"],
+            &EnumerateOptions::default(),
+        )
+        .unwrap()
+        .get_nonzero();
+        assert_eq!(counts.len(), expected.len());
+        for (repr, count) in counts {
+            assert_eq!(count, 2 * expected[&repr]);
+        }
+    }
+
+    #[test]
+    fn test_multiple_inputs_accumulate() {
+        let gtrie_path = "example/gtries/dir3.gt";
+        let options = EnumerateOptions::default();
+        let example = enumerate_subgraphs(gtrie_path, &["example/graphs/example.txt"], &options)
+            .unwrap()
+            .get_nonzero();
+        let yeast = enumerate_subgraphs(gtrie_path, &["example/graphs/yeast.txt"], &options)
+            .unwrap()
+            .get_nonzero();
+        let gtrie = enumerate_subgraphs(
+            gtrie_path,
+            &["example/graphs/example.txt", "example/graphs/yeast.txt"],
+            &options,
+        )
+        .unwrap();
+        assert_eq!(gtrie.total_subgraphs(), 16 + 13150);
+        for (repr, count) in gtrie.get_nonzero() {
+            let expected = example.get(&repr).unwrap_or(&0) + yeast.get(&repr).unwrap_or(&0);
+            assert_eq!(count, expected);
+        }
+    }
+
+    #[test]
+    fn test_example_kcore() {
+        let graph_path = "example/graphs/example.txt";
+        let gtrie_path = "example/gtries/dir3.gt";
+        let gtrie = enumerate_subgraphs(
+            gtrie_path,
+            &[graph_path],
+            &EnumerateOptions {
+                kcore: Some(0),
+                ..Default::default()
+            },
+        )
+        .unwrap();
+        assert_eq!(gtrie.total_subgraphs(), 16);
+        let gtrie = enumerate_subgraphs(
+            gtrie_path,
+            &[graph_path],
+            &EnumerateOptions {
+                kcore: Some(100),
+                ..Default::default()
+            },
+        )
+        .unwrap();
+        assert_eq!(gtrie.total_subgraphs(), 0);
+    }
+
+    #[test]
+    fn test_kcore_of_cycle_with_tail() {
+        // A directed triangle with a tail: the 2-core is the triangle alone,
+        // while the full graph also has the triads along the tail.
+        let path = std::env::temp_dir().join("graphtries_cycle_with_tail.txt");
+        std::fs::write(&path, "1 2\n2 3\n3 1\n3 4\n4 5\n").unwrap();
+        let path = path.to_str().unwrap();
+        let census = |kcore| {
+            let options = EnumerateOptions {
+                kcore,
+                ..Default::default()
+            };
+            enumerate_subgraphs("example/gtries/dir3.gt", &[path], &options).unwrap()
+        };
+        let (full, core, empty) = (census(None), census(Some(2)), census(Some(3)));
+        std::fs::remove_file(path).unwrap();
+
+        assert_eq!(full.total_subgraphs(), 4);
+        assert_eq!(core.total_subgraphs(), 1);
+        assert_eq!(empty.total_subgraphs(), 0);
+
+        let cycle = io::load_repr(core.get_nonzero().keys().next().unwrap()).unwrap();
+        assert_eq!(cycle.edge_count(), 3);
+        assert!(Bitgraph::from_graph(&cycle).is_strongly_connected());
+    }
+
+    #[test]
+    fn test_ego_reports_input_vertices() {
+        // A tail leading into a directed triangle of vertices 2 to 4.
+        let dir = std::env::temp_dir();
+        let path = dir.join("graphtries_ego_tail_into_cycle.txt");
+        let occurrences = dir.join("graphtries_ego_occurrences.tsv");
+        std::fs::write(&path, "1 2\n2 3\n3 4\n4 2\n").unwrap();
+        let (path, occurrences) = (path.to_str().unwrap(), occurrences.to_str().unwrap());
+        let dumped = |ego, kcore| {
+            let options = EnumerateOptions {
+                ego: Some(ego),
+                kcore,
+                dump_occurrences: Some(occurrences),
+                ..Default::default()
+            };
+            enumerate_subgraphs("example/gtries/dir3.gt", &[path], &options).unwrap();
+            let dumped = std::fs::read_to_string(occurrences).unwrap();
+            let mut used = dumped
+                .lines()
+                .map(|line| line.split('\t').skip(1).take(3).sorted().join(","))
+                .collect::<Vec<_>>();
+            used.sort_unstable();
+            used
+        };
+
+        // The 1-hop neighborhood of vertex 3 is the triangle.
+        assert_eq!(dumped((2, 1), None), ["1,2,3"]);
+        // The 2-hop neighborhood of vertex 1 is the whole graph, of which the
+        // 2-core is the triangle.
+        assert_eq!(dumped((0, 2), None), ["0,1,2", "0,1,3", "1,2,3"]);
+        assert_eq!(dumped((0, 2), Some(2)), ["1,2,3"]);
+
+        std::fs::remove_file(path).unwrap();
+        std::fs::remove_file(occurrences).unwrap();
+    }
+
+    #[test]
+    fn test_kcore_reports_input_vertices() {
+        // A tail leading into a directed triangle: the 2-core is vertices 2 to 4.
+        let dir = std::env::temp_dir();
+        let path = dir.join("graphtries_tail_into_cycle.txt");
+        let occurrences = dir.join("graphtries_kcore_occurrences.tsv");
+        let participation = dir.join("graphtries_kcore_participation.tsv");
+        std::fs::write(&path, "1 2\n2 3\n3 4\n4 2\n").unwrap();
+        let (path, occurrences, participation) = (
+            path.to_str().unwrap(),
+            occurrences.to_str().unwrap(),
+            participation.to_str().unwrap(),
+        );
+        let census = |options: EnumerateOptions| {
+            let options = EnumerateOptions {
+                kcore: Some(2),
+                ..options
+            };
+            enumerate_subgraphs("example/gtries/dir3.gt", &[path], &options).unwrap()
+        };
+
+        census(EnumerateOptions {
+            dump_occurrences: Some(occurrences),
+            ..Default::default()
+        });
+        census(EnumerateOptions {
+            participation: Some(participation),
+            ..Default::default()
+        });
+        let dumped = std::fs::read_to_string(occurrences).unwrap();
+        let table = std::fs::read_to_string(participation).unwrap();
+        let mut used = dumped
+            .trim()
+            .split('\t')
+            .skip(1)
+            .take(3)
+            .collect::<Vec<_>>();
+        used.sort_unstable();
+        assert_eq!(used, ["1", "2", "3"]);
+        let rows = table
+            .lines()
+            .skip(1)
+            .map(|line| line.split('\t').next().unwrap());
+        assert_eq!(rows.collect::<Vec<_>>(), ["2", "3", "4"]);
+
+        let with_edge = |a, b| {
+            census(EnumerateOptions {
+                require_edge: Some((a, b)),
+                ..Default::default()
+            })
+            .total_subgraphs()
+        };
+        assert_eq!(with_edge(1, 2), 1);
+        assert_eq!(with_edge(0, 1), 0);
+
+        for path in [path, occurrences, participation] {
+            std::fs::remove_file(path).unwrap();
+        }
+    }
+
     #[test]
     fn test_example_dir4() {
         let graph_path = "example/graphs/example.txt";
         let gtrie_path = "example/gtries/dir4.gt";
-        let gtrie = enumerate_subgraphs(gtrie_path, graph_path).unwrap();
+        let gtrie =
+            enumerate_subgraphs(gtrie_path, &[graph_path], &EnumerateOptions::default()).unwrap();
         let nonzero = gtrie.get_nonzero();
         assert_eq!(gtrie.total_subgraphs(), 24);
         assert_eq!(nonzero.len(), 8);
@@ -153,7 +1671,8 @@ mod testing {
     fn test_yeast_dir3() {
         let graph_path = "example/graphs/yeast.txt";
         let gtrie_path = "example/gtries/dir3.gt";
-        let gtrie = enumerate_subgraphs(gtrie_path, graph_path).unwrap();
+        let gtrie =
+            enumerate_subgraphs(gtrie_path, &[graph_path], &EnumerateOptions::default()).unwrap();
         let nonzero = gtrie.get_nonzero();
         assert_eq!(gtrie.total_subgraphs(), 13150);
         assert_eq!(nonzero.len(), 7);
@@ -167,7 +1686,8 @@ mod testing {
     fn test_yeast_dir4() {
         let graph_path = "example/graphs/yeast.txt";
         let gtrie_path = "example/gtries/dir4.gt";
-        let gtrie = enumerate_subgraphs(gtrie_path, graph_path).unwrap();
+        let gtrie =
+            enumerate_subgraphs(gtrie_path, &[graph_path], &EnumerateOptions::default()).unwrap();
         let nonzero = gtrie.get_nonzero();
         assert_eq!(gtrie.total_subgraphs(), 183174);
         assert_eq!(nonzero.len(), 34);
@@ -205,7 +1725,8 @@ mod testing {
     fn test_yeast_dir5() {
         let graph_path = "example/graphs/yeast.txt";
         let gtrie_path = "example/gtries/dir5.gt";
-        let gtrie = enumerate_subgraphs(gtrie_path, graph_path).unwrap();
+        let gtrie =
+            enumerate_subgraphs(gtrie_path, &[graph_path], &EnumerateOptions::default()).unwrap();
         let nonzero = gtrie.get_nonzero();
         assert_eq!(gtrie.total_subgraphs(), 2508149);
         assert_eq!(nonzero.len(), 174);
@@ -314,6 +1835,430 @@ mod testing {
                 || v == 1771524;
             assert!(cond);
         });
+    }
+
+    #[test]
+    fn test_generated_mixed_sizes() {
+        let graph = io::load_numeric_graph("example/graphs/yeast.txt", true, false).unwrap();
+        let query = Bitgraph::from_graph(&graph);
+        let by_size = generate::connected_digraphs(4);
+
+        let mut mixed = Gtrie::new(4);
+        let mut separate = HashMap::new();
+        for size in 2..=4 {
+            let mut gtrie = Gtrie::new(size);
+            for graph in by_size[size - 1].iter() {
+                gtrie.insert_canonical(graph);
+                mixed.insert_canonical(graph);
+            }
+            gtrie.census_into(&query, &mut separate);
+        }
+
+        let mut counts = HashMap::new();
+        mixed.census_into(&query, &mut counts);
+        assert_eq!(counts, separate);
+    }
+
+    #[test]
+    fn test_log2_fold_change() {
+        assert_eq!(log2_fold_change(3, 3), 0.0);
+        assert_eq!(log2_fold_change(7, 1), 2.0);
+        assert_eq!(log2_fold_change(0, 1), -1.0);
+    }
+
+    #[test]
+    fn test_build_acyclic_filter() {
+        let output = std::env::temp_dir().join("graphtries_acyclic_dir3.gt");
+        let output = output.to_str().unwrap().to_string();
+        build_gtrie(
+            "example/subgraphs/dir3.g6",
+            output.clone(),
+            3,
+            false,
+            &BuildOptions {
+                motif_filter: Some(MotifFilter::Acyclic),
+                ..Default::default()
+            },
+        )
+        .unwrap();
+
+        let gtrie = Gtrie::read_from_file(&output).unwrap();
+        std::fs::remove_file(&output).unwrap();
+        let reprs = gtrie.reprs();
+        assert_eq!(reprs.len(), 4);
+        for repr in reprs {
+            let motif = Bitgraph::from_graph(&io::load_repr(repr).unwrap());
+            assert!(motif.is_acyclic());
+        }
+    }
+
+    #[test]
+    fn test_vertex_filter() {
+        let graph_path = "example/graphs/yeast.txt";
+        let gtrie_path = "example/gtries/dir3.gt";
+        let graph = Bitgraph::from_graph(&io::load_numeric_graph(graph_path, true, false).unwrap());
+
+        // Every third vertex has a low attribute.
+        let attributes = std::env::temp_dir().join("graphtries_vertex_filter.tsv");
+        let contents = (0..graph.n_nodes())
+            .map(|u| format!("{}\t{}\n", u + 1, if u % 3 == 0 { 0.1 } else { 2.0 }))
+            .collect::<String>();
+        std::fs::write(&attributes, contents).unwrap();
+        let options = EnumerateOptions {
+            vertex_filter: Some((attributes.to_str().unwrap(), 1.0)),
+            ..Default::default()
+        };
+        let gtrie = enumerate_subgraphs(gtrie_path, &[graph_path], &options).unwrap();
+        std::fs::remove_file(&attributes).unwrap();
+
+        let kept = (0..graph.n_nodes())
+            .filter(|u| u % 3 != 0)
+            .collect::<Vec<_>>();
+        let mut expected = Gtrie::read_from_file(gtrie_path).unwrap();
+        expected.census(&graph.induced_subgraph(&kept));
+        assert!(expected.total_subgraphs() > 0);
+        assert_eq!(gtrie.total_subgraphs(), expected.total_subgraphs());
+        assert_eq!(gtrie.get_nonzero(), expected.get_nonzero());
+    }
+
+    #[test]
+    fn test_long_tsv() {
+        let graph = io::load_numeric_graph("example/graphs/example.txt", true, false).unwrap();
+        let mut gtrie = Gtrie::read_from_file("example/gtries/dir3.gt").unwrap();
+        gtrie.census(&Bitgraph::from_graph(&graph));
+
+        let mut buffer = Vec::new();
+        write_long_tsv(&gtrie, true, &mut buffer).unwrap();
+        let text = String::from_utf8(buffer).unwrap();
+        let lines = text.lines().collect::<Vec<_>>();
+        assert_eq!(
+            lines[0],
+            "motif_size\tgraph6\tfrequency\tconcentration\tedges\tacyclic"
+        );
+        assert_eq!(lines[1], "3\t&BK?\t9\t0.562500\t2\ttrue");
+        assert_eq!(lines[2], "3\t&BC_\t3\t0.187500\t2\ttrue");
+        assert_eq!(lines[3], "3\t&BW?\t3\t0.187500\t2\ttrue");
+        assert_eq!(lines[4], "3\t&BKO\t1\t0.062500\t3\tfalse");
+        assert_eq!(lines.len(), gtrie.reprs().len() + 1);
+    }
+
+    #[test]
+    fn test_parse_shard() {
+        assert_eq!(parse_shard("1/3").unwrap(), (0, 3));
+        assert_eq!(parse_shard("3/3").unwrap(), (2, 3));
+        assert!(parse_shard("0/3").is_err());
+        assert!(parse_shard("4/3").is_err());
+        assert!(parse_shard("3").is_err());
+    }
+
+    #[test]
+    fn test_results_as_json_metadata() {
+        let options = EnumerateOptions {
+            timeout: Some(0.0),
+            ..Default::default()
+        };
+        let gtrie = enumerate_subgraphs(
+            "example/gtries/dir3.gt",
+            &["example/graphs/yeast.txt"],
+            &options,
+        )
+        .unwrap();
+        let json = results_as_json(&gtrie, true, Some(true), None);
+        assert_eq!(json["timed_out"], true);
+        assert_eq!(json["results"], gtrie.results_as_json(true));
+        assert!(json.get("provenance").is_none());
+        assert!(results_as_json(&gtrie, true, None, None).is_array());
+
+        let provenance = provenance("example/gtries/dir3.gt", &gtrie, "yeast.txt", None);
+        let json = results_as_json(&gtrie, true, None, Some(&provenance));
+        assert_eq!(json["provenance"]["gtrie_hash"], provenance.gtrie_hash);
+        assert!(json.get("timed_out").is_none());
+    }
 
+    #[test]
+    fn test_timeout_deadline() {
+        let now = std::time::Instant::now();
+        assert_eq!(timeout_deadline(now, 0.0).unwrap(), now);
+        assert!(timeout_deadline(now, 1.5).unwrap() > now);
+        for seconds in [-1.0, f64::NAN, f64::INFINITY, 1e300] {
+            assert!(timeout_deadline(now, seconds).is_err());
+        }
+    }
+
+    #[test]
+    fn test_min_edge_weight_undirected() {
+        let path = std::env::temp_dir().join("graphtries_weighted_path.txt");
+        std::fs::write(&path, "1 2 3\n2 3 3\n").unwrap();
+        let path = path.to_str().unwrap();
+
+        // The symmetrized reverse edges are as heavy as the written ones.
+        let gtrie = enumerate_subgraphs(
+            "example/gtries/dir3.gt",
+            &[path],
+            &EnumerateOptions {
+                undirected: true,
+                min_edge_weight: Some(2.0),
+                ..Default::default()
+            },
+        )
+        .unwrap();
+        std::fs::remove_file(path).unwrap();
+        assert_eq!(gtrie.total_subgraphs(), 1);
+    }
+
+    #[test]
+    fn test_build_with_canon_cache() {
+        let output = std::env::temp_dir().join("graphtries_cached_dir3.gt");
+        let output = output.to_str().unwrap().to_string();
+        let cache_path = format!("{}.canon", output);
+        let _ = std::fs::remove_file(&cache_path);
+        let options = BuildOptions {
+            cache: true,
+            ..Default::default()
+        };
+        let build = || {
+            build_gtrie(
+                "example/subgraphs/dir3.g6",
+                output.clone(),
+                3,
+                false,
+                &options,
+            )
+            .unwrap();
+            Gtrie::read_from_file(&output).unwrap().reprs().len()
+        };
+        assert_eq!(build(), 13);
+        let cache = CanonCache::read_from_file(&cache_path).unwrap();
+        assert_eq!(cache.len(), 13);
+
+        // Cache every line under the canonical form of the first one: a build
+        // reusing the entries then inserts a single motif.
+        let mut lines = io::iter_reprs_from_file("example/subgraphs/dir3.g6").unwrap();
+        let (_, first) = lines.next().unwrap().unwrap();
+        let mut poisoned = CanonCache::new();
+        for result in io::iter_reprs_from_file("example/subgraphs/dir3.g6").unwrap() {
+            let (line, _) = result.unwrap();
+            poisoned.canonicalize_with(&line, false, &first, ApMode::Auto);
+        }
+        poisoned.write_to_file(&cache_path).unwrap();
+        assert_eq!(build(), 1);
+
+        let no_cache = BuildOptions::default();
+        build_gtrie(
+            "example/subgraphs/dir3.g6",
+            output.clone(),
+            3,
+            false,
+            &no_cache,
+        )
+        .unwrap();
+        assert_eq!(Gtrie::read_from_file(&output).unwrap().reprs().len(), 13);
+        std::fs::remove_file(&output).unwrap();
+        std::fs::remove_file(&cache_path).unwrap();
+    }
+
+    #[test]
+    fn test_build_malformed_lines() {
+        let dir = std::env::temp_dir();
+        let motifs = dir.join("graphtries_malformed3.g6");
+        let output = dir.join("graphtries_malformed3.gt");
+        std::fs::write(&motifs, "BW\nB?!\nBw\n").unwrap();
+        let (motifs, output) = (motifs.to_str().unwrap(), output.to_str().unwrap());
+
+        let err = build_gtrie(
+            motifs,
+            output.to_string(),
+            3,
+            false,
+            &BuildOptions::default(),
+        )
+        .unwrap_err();
+        assert!(err.to_string().contains("Line 2: `B?!`"));
+
+        let skip_bad = BuildOptions {
+            skip_bad: true,
+            ..Default::default()
+        };
+        build_gtrie(motifs, output.to_string(), 3, false, &skip_bad).unwrap();
+        assert_eq!(Gtrie::read_from_file(output).unwrap().reprs().len(), 2);
+        std::fs::remove_file(motifs).unwrap();
+        std::fs::remove_file(output).unwrap();
+    }
+
+    #[test]
+    fn test_build_skips_disconnected() {
+        let dir = std::env::temp_dir();
+        let motifs = dir.join("graphtries_disconnected3.g6");
+        let output = dir.join("graphtries_disconnected3.gt");
+        // The path, and a single edge with an isolated vertex.
+        std::fs::write(&motifs, "BW\nB_\n").unwrap();
+        let (motifs, output) = (motifs.to_str().unwrap(), output.to_str().unwrap());
+
+        build_gtrie(
+            motifs,
+            output.to_string(),
+            3,
+            false,
+            &BuildOptions::default(),
+        )
+        .unwrap();
+        assert_eq!(Gtrie::read_from_file(output).unwrap().reprs().len(), 1);
+
+        let strict = BuildOptions {
+            strict: true,
+            ..Default::default()
+        };
+        assert!(build_gtrie(motifs, output.to_string(), 3, false, &strict).is_err());
+        std::fs::remove_file(motifs).unwrap();
+        std::fs::remove_file(output).unwrap();
+    }
+
+    #[test]
+    fn test_undirected_end_to_end() {
+        let dir = std::env::temp_dir();
+        let motifs = dir.join("graphtries_undirected3.g6");
+        let output = dir.join("graphtries_undirected3.gt");
+        let graph_path = dir.join("graphtries_undirected_cycle.txt");
+        // The path and the triangle, and a 4-cycle given one way.
+        std::fs::write(&motifs, "BW\nBw\n").unwrap();
+        std::fs::write(&graph_path, "1 2\n2 3\n3 4\n4 1\n").unwrap();
+        let (motifs, output, graph_path) = (
+            motifs.to_str().unwrap(),
+            output.to_str().unwrap(),
+            graph_path.to_str().unwrap(),
+        );
+
+        let build_options = BuildOptions {
+            undirected: true,
+            ..Default::default()
+        };
+        build_gtrie(motifs, output.to_string(), 3, false, &build_options).unwrap();
+        let gtrie = Gtrie::read_from_file(output).unwrap();
+        let directed =
+            Bitgraph::from_graph(&io::load_numeric_graph(graph_path, true, false).unwrap());
+        assert!(gtrie.check_directedness(&directed).is_err());
+
+        // Each of the 4 paths of the cycle is counted once.
+        let options = EnumerateOptions {
+            undirected: true,
+            ..Default::default()
+        };
+        let gtrie = enumerate_subgraphs(output, &[graph_path], &options).unwrap();
+        assert_eq!(gtrie.total_subgraphs(), 4);
+        assert_eq!(gtrie.get_nonzero().into_values().collect::<Vec<_>>(), [4]);
+
+        // Occurrences are dumped under the undirected repr of the path, `Bo`.
+        let occurrences = dir.join("graphtries_undirected_occurrences.tsv");
+        let occurrences = occurrences.to_str().unwrap();
+        let options = EnumerateOptions {
+            undirected: true,
+            dump_occurrences: Some(occurrences),
+            ..Default::default()
+        };
+        enumerate_subgraphs(output, &[graph_path], &options).unwrap();
+        let dumped = std::fs::read_to_string(occurrences).unwrap();
+        std::fs::remove_file(occurrences).unwrap();
+        assert_eq!(dumped.lines().count(), 4);
+        assert!(dumped.lines().all(|line| line.starts_with("Bo\t")));
+
+        // Directed motifs are rejected.
+        let err = build_gtrie(
+            "example/subgraphs/dir3.g6",
+            output.to_string(),
+            3,
+            false,
+            &build_options,
+        );
+        assert!(err.unwrap_err().to_string().contains("--undirected"));
+
+        for path in [motifs, output, graph_path] {
+            std::fs::remove_file(path).unwrap();
+        }
+    }
+
+    #[test]
+    fn test_build_merge_reversals() {
+        let build = |name: &str, merge_reversals: bool| {
+            let output = std::env::temp_dir().join(name);
+            let output = output.to_str().unwrap().to_string();
+            build_gtrie(
+                "example/subgraphs/dir3.g6",
+                output.clone(),
+                3,
+                false,
+                &BuildOptions {
+                    merge_reversals,
+                    ..Default::default()
+                },
+            )
+            .unwrap();
+            let gtrie = Gtrie::read_from_file(&output).unwrap();
+            std::fs::remove_file(&output).unwrap();
+            gtrie
+        };
+        let mut plain = build("graphtries_plain_dir3.gt", false);
+        let mut merged = build("graphtries_merged_dir3.gt", true);
+
+        // The out-star and in-star are reversals of each other.
+        let star_reprs = |edges: &[(u32, u32)]| {
+            let mut graph = petgraph::Graph::<(), (), petgraph::Directed>::new();
+            (0..3).for_each(|_| {
+                graph.add_node(());
+            });
+            graph.extend_with_edges(edges);
+            Gtrie::new(3).insert_canonical(&graph).0
+        };
+        let out_star = star_reprs(&[(0, 1), (0, 2)]);
+        let in_star = star_reprs(&[(1, 0), (2, 0)]);
+        assert!(plain.reprs().contains(&out_star.as_str()));
+        assert!(plain.reprs().contains(&in_star.as_str()));
+        let merged_reprs = merged.reprs();
+        assert_eq!(
+            usize::from(merged_reprs.contains(&out_star.as_str()))
+                + usize::from(merged_reprs.contains(&in_star.as_str())),
+            1
+        );
+        assert!(merged_reprs.len() < plain.reprs().len());
+
+        // Each merged motif counts the occurrences of both orientations.
+        let graph = io::load_numeric_graph("example/graphs/yeast.txt", true, false).unwrap();
+        let query = Bitgraph::from_graph(&graph);
+        plain.census(&query);
+        merged.census(&query);
+        assert_eq!(merged.total_subgraphs(), plain.total_subgraphs());
+        let plain_counts = plain.get_nonzero();
+        let star = out_star.as_str().min(in_star.as_str());
+        assert_eq!(
+            merged.get_nonzero()[star],
+            plain_counts[&out_star] + plain_counts[&in_star]
+        );
+        assert_eq!(
+            merged.results().iter().map(|(_, freq)| freq).sum::<usize>(),
+            plain.total_subgraphs()
+        );
+    }
+
+    #[test]
+    fn test_iso_graphs() {
+        let write = |name: &str, contents: &str| {
+            let path = std::env::temp_dir().join(name);
+            std::fs::write(&path, contents).unwrap();
+            path.to_str().unwrap().to_string()
+        };
+        let cycle = write("graphtries_iso_cycle.txt", "1\t2\n2\t3\n3\t4\n4\t1\n1\t3\n");
+        let relabeled = write(
+            "graphtries_iso_relabeled.txt",
+            "3\t1\n1\t4\n4\t2\n2\t3\n3\t4\n",
+        );
+        let acyclic = write(
+            "graphtries_iso_acyclic.txt",
+            "1\t2\n2\t3\n3\t4\n1\t4\n1\t3\n",
+        );
+
+        assert!(iso_graphs(&cycle, &relabeled).unwrap());
+        assert!(!iso_graphs(&cycle, &acyclic).unwrap());
+        for path in [cycle, relabeled, acyclic] {
+            std::fs::remove_file(path).unwrap();
+        }
     }
 }
@@ -0,0 +1,188 @@
+use crate::{bitgraph::Bitgraph, node::GtrieNode};
+use petgraph::{Directed, Graph};
+use std::io::Write;
+
+/// A motif of a gtrie, given by the path of trie nodes from the first
+/// position of the motif to its graph node.
+///
+/// Each node holds the edges between its position and the previous ones, so
+/// the adjacency of the motif is reconstructed from the path alone.
+#[derive(Debug, Clone)]
+pub struct MotifRef<'a> {
+    path: Vec<&'a GtrieNode>,
+    is_dir: bool,
+}
+impl<'a> MotifRef<'a> {
+    /// Creates a motif from a path of trie nodes ending at a graph node, in a
+    /// trie of directed motifs if `is_dir`.
+    pub(crate) fn new(path: Vec<&'a GtrieNode>, is_dir: bool) -> Self {
+        Self { path, is_dir }
+    }
+
+    /// Whether the motif is directed, i.e. from a trie of directed motifs.
+    pub fn is_dir(&self) -> bool {
+        self.is_dir
+    }
+
+    /// The graph6 representation of the motif.
+    pub fn repr(&self) -> &'a str {
+        self.leaf().repr().unwrap_or_default()
+    }
+
+    /// The number of nodes of the motif.
+    pub fn size(&self) -> usize {
+        self.path.len()
+    }
+
+    /// The number of occurrences of the motif found by the last census.
+    pub fn frequency(&self) -> usize {
+        self.leaf().frequency()
+    }
+
+    /// The directed edges of the motif between trie positions, as `(src, dst)`.
+    pub fn edges(&self) -> Vec<(usize, usize)> {
+        let mut edges = Vec::new();
+        for (k, node) in self.path.iter().enumerate() {
+            for u in 0..k {
+                if node.out_contains(u) {
+                    edges.push((u, k));
+                }
+                if node.in_contains(u) {
+                    edges.push((k, u));
+                }
+            }
+        }
+        edges
+    }
+
+    /// The motif as a graph labeled by trie position.
+    pub fn to_graph(&self) -> Graph<(), (), Directed> {
+        let mut graph = Graph::with_capacity(self.size(), 0);
+        for _ in 0..self.size() {
+            graph.add_node(());
+        }
+        graph.extend_with_edges(self.edges().into_iter().map(|(u, v)| (u as u32, v as u32)));
+        graph
+    }
+
+    /// The number of directed edges of the motif. A mutual connection counts
+    /// as two edges.
+    pub fn edge_count(&self) -> usize {
+        self.edges().len()
+    }
+
+    /// Checks if the motif contains no directed cycle. A mutual connection is
+    /// a cycle of length two.
+    pub fn is_acyclic(&self) -> bool {
+        Bitgraph::from_graph(&self.to_graph()).is_acyclic()
+    }
+
+    /// Writes the motif as a GraphML document with one node per trie
+    /// position, undirected motifs having each of their edges written once.
+    pub fn write_graphml<W: Write>(&self, writer: &mut W) -> std::io::Result<()> {
+        let is_dir = self.is_dir;
+        writeln!(writer, r#"<?xml version="1.0" encoding="UTF-8"?>"#)?;
+        writeln!(
+            writer,
+            r#"<graphml xmlns="http://graphml.graphdrawing.org/xmlns">"#
+        )?;
+        writeln!(
+            writer,
+            r#"  <graph id="{}" edgedefault="{}">"#,
+            escape_xml(self.repr()),
+            if is_dir { "directed" } else { "undirected" }
+        )?;
+        for u in 0..self.size() {
+            writeln!(writer, r#"    <node id="n{}"/>"#, u)?;
+        }
+        for (u, v) in self.edges() {
+            if is_dir || u < v {
+                writeln!(writer, r#"    <edge source="n{}" target="n{}"/>"#, u, v)?;
+            }
+        }
+        writeln!(writer, "  </graph>")?;
+        writeln!(writer, "</graphml>")?;
+        Ok(())
+    }
+
+    fn leaf(&self) -> &'a GtrieNode {
+        self.path[self.path.len() - 1]
+    }
+}
+
+/// Escapes the characters of a graph6 representation that are special in XML.
+fn escape_xml(text: &str) -> String {
+    text.replace('&', "&amp;")
+        .replace('<', "&lt;")
+        .replace('>', "&gt;")
+        .replace('"', "&quot;")
+}
+
+#[cfg(test)]
+mod testing {
+    use super::*;
+    use crate::{gtrie::Gtrie, isomorphism::canonicalize};
+
+    fn digraph(n: usize, edges: &[(u32, u32)]) -> Graph<(), (), Directed> {
+        let mut graph = Graph::new();
+        for _ in 0..n {
+            graph.add_node(());
+        }
+        graph.extend_with_edges(edges);
+        graph
+    }
+
+    #[test]
+    fn edge_count_and_acyclicity() {
+        let mut gtrie = Gtrie::new(3);
+        let (feed_forward, _) = gtrie.insert_canonical(&digraph(3, &[(0, 1), (0, 2), (1, 2)]));
+        let (cycle, _) = gtrie.insert_canonical(&digraph(3, &[(0, 1), (1, 2), (2, 0)]));
+        let (mutual, _) = gtrie.insert_canonical(&digraph(3, &[(0, 1), (1, 0), (1, 2)]));
+
+        let motifs = gtrie.motifs();
+        assert_eq!(motifs.len(), 3);
+        let motif = |repr: &str| motifs.iter().find(|m| m.repr() == repr).unwrap();
+
+        assert_eq!(motif(&feed_forward).size(), 3);
+        assert_eq!(motif(&feed_forward).edge_count(), 3);
+        assert!(motif(&feed_forward).is_acyclic());
+        assert_eq!(motif(&cycle).edge_count(), 3);
+        assert!(!motif(&cycle).is_acyclic());
+        assert_eq!(motif(&mutual).edge_count(), 3);
+        assert!(!motif(&mutual).is_acyclic());
+    }
+
+    #[test]
+    fn graphml_of_directed_and_undirected_motifs() {
+        let mut gtrie = Gtrie::new(3);
+        let (feed_forward, _) = gtrie.insert_canonical(&digraph(3, &[(0, 1), (0, 2), (1, 2)]));
+        let motifs = gtrie.motifs();
+        let mut buffer = Vec::new();
+        motifs[0].write_graphml(&mut buffer).unwrap();
+        let graphml = String::from_utf8(buffer).unwrap();
+        assert!(graphml.contains(&format!(
+            r#"<graph id="{}" edgedefault="directed">"#,
+            escape_xml(&feed_forward)
+        )));
+        assert_eq!(graphml.matches("<node ").count(), 3);
+        assert_eq!(graphml.matches("<edge ").count(), 3);
+        assert!(!graphml.contains(r#"id="&B"#));
+
+        // An undirected path, built as `build --undirected` does.
+        let path = digraph(3, &[(0, 1), (1, 0), (1, 2), (2, 1)]);
+        let (bgraph, canon) = canonicalize(&path);
+        let mut gtrie = Gtrie::new(3);
+        gtrie.insert(
+            &bgraph.into_undirected(),
+            canon.conditions(),
+            Some(&canon.orbits),
+            Some(canon.repr()),
+        );
+        let motifs = gtrie.motifs();
+        let mut buffer = Vec::new();
+        motifs[0].write_graphml(&mut buffer).unwrap();
+        let graphml = String::from_utf8(buffer).unwrap();
+        assert!(graphml.contains(r#"edgedefault="undirected""#));
+        assert_eq!(graphml.matches("<edge ").count(), 2);
+    }
+}
@@ -1,7 +1,9 @@
 use crate::{
     bitgraph::Bitgraph,
+    census::used_respects_conditions,
     symmetry::{Condition, Conditions},
 };
+use anyhow::{anyhow, bail, Result};
 use fixedbitset::FixedBitSet;
 use hashbrown::HashMap;
 use serde::{Deserialize, Serialize};
@@ -24,27 +26,24 @@ pub struct GtrieNode {
     connections: Vec<usize>,
     conditions: Option<Conditions>,
     repr: Option<String>,
+
+    /// The exact symmetry breaking conditions of the graph ending at this
+    /// node, used when the node is also a prefix of larger graphs.
+    #[serde(default)]
+    graph_conditions: Option<Conditions>,
+
+    /// The orbit of each position of the graph ending at this node.
+    #[serde(default)]
+    orbits: Option<Vec<usize>>,
+
+    /// Index of the graph ending at this node among the graph nodes of its
+    /// trie, see `Gtrie::n_leaves`.
+    #[serde(default)]
+    leaf: usize,
 }
 impl Display for GtrieNode {
     fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
-        let mut s = String::new();
-        s.push('[');
-        for u in 0..self.n_nodes {
-            if self.edge_out.contains(u) {
-                s.push('1');
-            } else {
-                s.push('0');
-            }
-        }
-        s.push_str("][");
-        for v in 0..self.n_nodes {
-            if self.edge_in.contains(v) {
-                s.push('1');
-            } else {
-                s.push('0');
-            }
-        }
-        s.push(']');
+        let mut s = self.edge_string();
         if let Some(conditions) = &self.conditions {
             s.push_str(" |");
             for (idx, c) in conditions.iter().enumerate() {
@@ -83,6 +82,9 @@ impl GtrieNode {
             connections: Vec::with_capacity(depth),
             depth,
             repr: None,
+            graph_conditions: None,
+            orbits: None,
+            leaf: 0,
         }
     }
 
@@ -111,6 +113,9 @@ impl GtrieNode {
             connections: Vec::with_capacity(depth),
             depth,
             repr: None,
+            graph_conditions: None,
+            orbits: None,
+            leaf: 0,
         }
     }
 
@@ -149,6 +154,9 @@ impl GtrieNode {
             connections: Vec::with_capacity(n_nodes),
             depth: graph.n_nodes(),
             repr: None,
+            graph_conditions: None,
+            orbits: None,
+            leaf: 0,
         }
     }
 
@@ -160,6 +168,36 @@ impl GtrieNode {
         self.edge_out.contains(v)
     }
 
+    /// Checks if two nodes are at the same depth with the same edges to the
+    /// positions before them, i.e. if they stand for the same trie position.
+    pub fn same_edges(&self, other: &Self) -> bool {
+        self.depth == other.depth
+            && (0..self.depth).all(|u| {
+                self.out_contains(u) == other.out_contains(u)
+                    && self.in_contains(u) == other.in_contains(u)
+            })
+    }
+
+    /// Merges a node with the same edges into this one, summing their
+    /// frequencies and merging their children by their edges. Children found
+    /// in only one of the nodes are kept as they are.
+    pub fn merge(&mut self, other: Self) {
+        self.frequency += other.frequency;
+        if other.is_graph && !self.is_graph {
+            self.is_graph = true;
+            self.repr = other.repr;
+            self.graph_conditions = other.graph_conditions;
+            self.orbits = other.orbits;
+        }
+        self.intersect_conditions(other.conditions.as_ref());
+        for child in other.children {
+            match self.children.iter_mut().find(|c| c.same_edges(&child)) {
+                Some(existing) => existing.merge(child),
+                None => self.children.push(child),
+            }
+        }
+    }
+
     pub fn update_adjacency(&mut self, graph: &Bitgraph, k: usize) {
         for u in 0..k {
             let fwd_conn = graph.is_connected(u, k - 1);
@@ -191,20 +229,93 @@ impl GtrieNode {
         self.is_graph = is_graph;
     }
 
+    /// Index of the graph ending at this node among the graph nodes of its
+    /// trie, from 0 to `Gtrie::n_leaves`. Only meaningful for graph nodes.
+    pub fn leaf(&self) -> usize {
+        self.leaf
+    }
+
+    pub(crate) fn set_leaf(&mut self, leaf: usize) {
+        self.leaf = leaf;
+    }
+
+    /// Numbers the graph nodes of the subtree in traversal order from `next`.
+    pub(crate) fn index_leaves(&mut self, next: &mut usize) {
+        if self.is_graph {
+            self.leaf = *next;
+            *next += 1;
+        }
+        for child in self.children.iter_mut() {
+            child.index_leaves(next);
+        }
+    }
+
     pub fn set_repr(&mut self, repr: Option<String>) {
         self.repr = repr;
     }
 
+    /// The orbit of each position of the graph ending at this node, aligned
+    /// with the vertices of its occurrences. Positions sharing an orbit id play
+    /// the same structural role. Only available for tries built with orbits.
+    pub fn orbits(&self) -> Option<&[usize]> {
+        self.orbits.as_deref()
+    }
+
+    pub fn set_orbits(&mut self, orbits: Option<&[usize]>) {
+        self.orbits = orbits.map(|o| o.to_vec());
+    }
+
+    pub fn graph_conditions(&self) -> Option<&Conditions> {
+        self.graph_conditions.as_ref()
+    }
+
+    pub fn set_graph_conditions(&mut self, conditions: Option<&Conditions>) {
+        self.graph_conditions = conditions.cloned();
+    }
+
+    /// Checks if the vertices placed up to and including this graph node
+    /// respect the conditions of its own graph.
+    ///
+    /// Graph nodes without children carry exactly their graph's conditions so
+    /// the check only applies to nodes shared with larger graphs.
+    pub fn counts_occurrence(&self, used: &[usize]) -> bool {
+        self.is_graph
+            && (self.children.is_empty()
+                || used_respects_conditions(used, self.graph_conditions.as_ref()))
+    }
+
     pub fn iter_children_mut(&mut self) -> impl Iterator<Item = &mut Self> {
         self.children.iter_mut()
     }
 
-    #[allow(dead_code)]
     pub fn iter_children(&self) -> impl Iterator<Item = &Self> {
         self.children.iter()
     }
 
-    #[allow(dead_code)]
+    /// The out and in edges of the node to the previous positions as bit
+    /// strings, e.g. `[01][10]`.
+    pub fn edge_string(&self) -> String {
+        let mut s = String::new();
+        s.push('[');
+        for u in 0..self.n_nodes {
+            if self.edge_out.contains(u) {
+                s.push('1');
+            } else {
+                s.push('0');
+            }
+        }
+        s.push_str("][");
+        for v in 0..self.n_nodes {
+            if self.edge_in.contains(v) {
+                s.push('1');
+            } else {
+                s.push('0');
+            }
+        }
+        s.push(']');
+        s
+    }
+
     pub fn depth(&self) -> usize {
         self.depth
     }
@@ -213,10 +324,47 @@ impl GtrieNode {
         self.is_graph
     }
 
+    pub fn frequency(&self) -> usize {
+        self.frequency
+    }
+
     pub fn increment_frequency(&mut self) {
         self.frequency += 1;
     }
 
+    /// Sets the frequencies of all nodes of the subtree back to zero.
+    pub fn reset_frequency(&mut self) {
+        self.frequency = 0;
+        for child in self.iter_children_mut() {
+            child.reset_frequency();
+        }
+    }
+
+    /// Adds the counts keyed by repr to the frequencies of the matching graph
+    /// nodes of the subtree.
+    ///
+    /// Each count is taken out of `counts` by the first graph node with its
+    /// repr, so graph nodes sharing a repr are not counted twice.
+    pub fn add_frequencies(&mut self, counts: &mut HashMap<String, usize>) {
+        if let Some(count) = self.repr.as_ref().and_then(|repr| counts.remove(repr)) {
+            self.frequency += count;
+        }
+        for child in self.iter_children_mut() {
+            child.add_frequencies(counts);
+        }
+    }
+
+    /// Adds the counts indexed by leaf to the frequencies of the graph nodes
+    /// of the subtree.
+    pub fn add_leaf_frequencies(&mut self, counts: &[usize]) {
+        if self.is_graph() {
+            self.frequency += counts[self.leaf];
+        }
+        for child in self.iter_children_mut() {
+            child.add_leaf_frequencies(counts);
+        }
+    }
+
     pub fn intersect_conditions(&mut self, conditions: Option<&Conditions>) {
         if self.conditions.is_none() {
             return;
@@ -253,7 +401,7 @@ impl GtrieNode {
     pub fn get_nonzero(&self, map: &mut HashMap<String, usize>) {
         if let Some(repr) = &self.repr {
             if self.frequency > 0 {
-                map.insert(repr.clone(), self.frequency);
+                *map.entry(repr.clone()).or_insert(0) += self.frequency;
             }
         }
         for child in self.iter_children() {
@@ -261,6 +409,63 @@ impl GtrieNode {
         }
     }
 
+    pub fn repr(&self) -> Option<&str> {
+        self.repr.as_deref()
+    }
+
+    /// Collects the reprs of all graph nodes of the subtree in traversal order.
+    pub fn collect_reprs<'a>(&'a self, reprs: &mut Vec<&'a str>) {
+        if let Some(repr) = self.repr() {
+            reprs.push(repr);
+        }
+        for child in self.iter_children() {
+            child.collect_reprs(reprs);
+        }
+    }
+
+    /// Collects the repr and frequency of all graph nodes of the subtree in traversal order.
+    pub fn collect_results<'a>(&'a self, results: &mut Vec<(&'a str, usize)>) {
+        if let Some(repr) = self.repr() {
+            results.push((repr, self.frequency));
+        }
+        for child in self.iter_children() {
+            child.collect_results(results);
+        }
+    }
+
+    /// Collects the depth, repr and frequency of all graph nodes of the subtree
+    /// in traversal order.
+    pub fn collect_sized_results<'a>(&'a self, results: &mut Vec<(usize, &'a str, usize)>) {
+        if let Some(repr) = self.repr() {
+            results.push((self.depth, repr, self.frequency));
+        }
+        for child in self.iter_children() {
+            child.collect_sized_results(results);
+        }
+    }
+
+    /// Counts the nodes of the subtree, including this one.
+    pub fn count_nodes(&self) -> usize {
+        1 + self.iter_children().map(|c| c.count_nodes()).sum::<usize>()
+    }
+
+    /// The largest depth of a node of the subtree.
+    pub fn deepest(&self) -> usize {
+        self.iter_children()
+            .map(|c| c.deepest())
+            .max()
+            .unwrap_or(self.depth)
+    }
+
+    /// Counts the graph nodes at a given depth of the subtree.
+    pub fn count_graphs_at_depth(&self, depth: usize) -> usize {
+        let own = usize::from(self.is_graph && self.depth == depth);
+        own + self
+            .iter_children()
+            .map(|c| c.count_graphs_at_depth(depth))
+            .sum::<usize>()
+    }
+
     pub fn pprint_results(&self) {
         if let Some(repr) = &self.repr {
             println!("{}\t{}", repr, self.frequency);
@@ -271,36 +476,19 @@ impl GtrieNode {
     }
 
     #[allow(dead_code)]
-    pub fn pprint(&self, frequency: bool) {
+    pub fn pprint(&self, frequency: bool, repr: bool) {
         print!("{}:", self.depth);
         for _ in 0..self.depth {
             print!("  ");
         }
-        println!("{}", self.custom_display(frequency));
+        println!("{}", self.custom_display(frequency, repr));
         for child in self.iter_children() {
-            child.pprint(frequency);
+            child.pprint(frequency, repr);
         }
     }
 
-    fn custom_display(&self, frequency: bool) -> String {
-        let mut s = String::new();
-        s.push('[');
-        for u in 0..self.n_nodes {
-            if self.edge_out.contains(u) {
-                s.push('1');
-            } else {
-                s.push('0');
-            }
-        }
-        s.push_str("][");
-        for v in 0..self.n_nodes {
-            if self.edge_in.contains(v) {
-                s.push('1');
-            } else {
-                s.push('0');
-            }
-        }
-        s.push(']');
+    fn custom_display(&self, frequency: bool, repr: bool) -> String {
+        let mut s = self.edge_string();
         if let Some(conditions) = &self.conditions {
             s.push_str(" |");
             for (idx, c) in conditions.iter().enumerate() {
@@ -314,8 +502,10 @@ impl GtrieNode {
             s.push_str(" {}+");
         }
 
-        if let Some(repr) = self.repr.as_ref() {
-            s.push_str(&format!(" [ {} ]", repr));
+        if repr {
+            if let Some(repr) = self.repr.as_ref() {
+                s.push_str(&format!(" [ {} ]", repr));
+            }
         }
 
         if frequency && self.is_graph {
@@ -323,4 +513,117 @@ impl GtrieNode {
         }
         s
     }
+
+    /// Writes the node as a line of the text format of a trie: its adjacency
+    /// as in `pprint` followed by its flags, without its frequency.
+    ///
+    /// `[110][001] graph conditions=0<1 graph_conditions=0<1 orbits=0,0,1 repr=&BW_`
+    pub fn text_line(&self) -> String {
+        let bits = |set: &FixedBitSet| {
+            (0..self.n_nodes)
+                .map(|u| if set.contains(u) { '1' } else { '0' })
+                .collect::<String>()
+        };
+        let conditions = |conditions: &Conditions| {
+            conditions
+                .iter()
+                .map(|c| c.to_string())
+                .collect::<Vec<_>>()
+                .join(",")
+        };
+        let mut s = format!("[{}][{}]", bits(&self.edge_out), bits(&self.edge_in));
+        if self.is_graph {
+            s.push_str(" graph");
+        }
+        if let Some(c) = &self.conditions {
+            s.push_str(&format!(" conditions={}", conditions(c)));
+        }
+        if let Some(c) = &self.graph_conditions {
+            s.push_str(&format!(" graph_conditions={}", conditions(c)));
+        }
+        if let Some(orbits) = &self.orbits {
+            let orbits = orbits.iter().map(|o| o.to_string()).collect::<Vec<_>>();
+            s.push_str(&format!(" orbits={}", orbits.join(",")));
+        }
+        if let Some(repr) = &self.repr {
+            s.push_str(&format!(" repr={}", repr));
+        }
+        s
+    }
+
+    /// Parses a line written by `text_line` into a childless node at `depth`.
+    pub fn from_text_line(depth: usize, line: &str) -> Result<Self> {
+        let mut tokens = line.split_whitespace();
+        let adjacency = tokens.next().ok_or_else(|| anyhow!("Missing adjacency"))?;
+        let (out, inc) = adjacency
+            .strip_prefix('[')
+            .and_then(|a| a.strip_suffix(']'))
+            .and_then(|a| a.split_once("]["))
+            .ok_or_else(|| anyhow!("Malformed adjacency `{}`", adjacency))?;
+        if out.len() != depth || inc.len() != depth {
+            bail!("Adjacency `{}` does not match depth {}", adjacency, depth);
+        }
+
+        let mut node = GtrieNode::new(depth);
+        for (u, (o, i)) in out.chars().zip(inc.chars()).enumerate() {
+            let (fwd_conn, bwd_conn) = match (o, i) {
+                ('0' | '1', '0' | '1') => (o == '1', i == '1'),
+                _ => bail!("Malformed adjacency `{}`", adjacency),
+            };
+            if fwd_conn || bwd_conn {
+                node.connections.push(u);
+            }
+            if fwd_conn {
+                node.edge_out.insert(u);
+                node.total_out += 1;
+                node.total_edges += 1;
+            }
+            if bwd_conn {
+                node.edge_in.insert(u);
+                node.total_in += 1;
+                node.total_edges += 1;
+            }
+        }
+
+        let parse_conditions = |value: &str| -> Result<Conditions> {
+            let conditions = value.parse::<Conditions>()?;
+            if let Some(c) = conditions.iter().find(|c| c.max() >= depth) {
+                bail!("Invalid condition `{}` at depth {}", c, depth);
+            }
+            Ok(conditions)
+        };
+        for token in tokens {
+            match token.split_once('=') {
+                None if token == "graph" => node.is_graph = true,
+                Some(("conditions", value)) => node.conditions = Some(parse_conditions(value)?),
+                Some(("graph_conditions", value)) => {
+                    node.graph_conditions = Some(parse_conditions(value)?)
+                }
+                Some(("orbits", value)) => {
+                    let orbits = value
+                        .split(',')
+                        .map(|o| o.parse::<usize>())
+                        .collect::<Result<Vec<_>, _>>()?;
+                    node.orbits = Some(orbits);
+                }
+                Some(("repr", value)) => node.repr = Some(value.to_string()),
+                _ => bail!("Unknown field `{}`", token),
+            }
+        }
+        Ok(node)
+    }
+
+    /// Writes the subtree in the text format, indenting each node by its depth.
+    pub fn write_text<W: std::io::Write>(&self, writer: &mut W) -> std::io::Result<()> {
+        for child in self.iter_children() {
+            writeln!(
+                writer,
+                "{}{}",
+                "  ".repeat(child.depth - 1),
+                child.text_line()
+            )?;
+            child.write_text(writer)?;
+        }
+        Ok(())
+    }
 }
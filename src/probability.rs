@@ -0,0 +1,146 @@
+use anyhow::{bail, Result};
+use hashbrown::HashMap;
+use std::{
+    fs::File,
+    io::{BufRead, BufReader},
+};
+
+use crate::bitgraph::Bitgraph;
+
+/// Existence probabilities of the directed edges of a graph.
+///
+/// Pairs without an explicit probability exist with probability 1 if they are
+/// an edge of the graph and 0 otherwise.
+#[derive(Debug, Clone, Default)]
+pub struct EdgeProbs {
+    probs: HashMap<(usize, usize), f64>,
+}
+impl EdgeProbs {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Loads probabilities from a 1-indexed whitespace delimited `u v p` edge list.
+    pub fn from_file(path: &str) -> Result<Self> {
+        let mut reader = File::open(path).map(BufReader::new)?;
+        Self::from_buffer(&mut reader)
+    }
+
+    /// Loads probabilities from a buffer, see [`EdgeProbs::from_file`].
+    pub fn from_buffer<B: BufRead>(buffer: &mut B) -> Result<Self> {
+        let mut probs = Self::new();
+        for (idx, line) in buffer.lines().enumerate() {
+            let line = line?;
+            let fields = line.split_whitespace().collect::<Vec<_>>();
+            let [u, v, p] = fields[..] else {
+                bail!("Line {}: expected `u v p`, found `{}`", idx + 1, line);
+            };
+            let (u, v, p) = (u.parse::<usize>()?, v.parse::<usize>()?, p.parse::<f64>()?);
+            if u == 0 || v == 0 {
+                bail!("ERROR: Found a node index: 0; Please use 1-indexed node indices.");
+            }
+            probs.insert(u - 1, v - 1, p)?;
+        }
+        Ok(probs)
+    }
+
+    /// Sets the probability of the edge `u -> v`.
+    pub fn insert(&mut self, u: usize, v: usize, p: f64) -> Result<()> {
+        if !(0.0..=1.0).contains(&p) {
+            bail!(
+                "Probability of edge {} -> {} is not in [0, 1]: {}",
+                u + 1,
+                v + 1,
+                p
+            );
+        }
+        self.probs.insert((u, v), p);
+        Ok(())
+    }
+
+    /// Returns the probability that the edge `u -> v` of the graph exists.
+    pub fn get(&self, graph: &Bitgraph, u: usize, v: usize) -> f64 {
+        match self.probs.get(&(u, v)) {
+            Some(p) => *p,
+            None => f64::from(u8::from(graph.is_connected(u, v))),
+        }
+    }
+
+    /// Probability that the subgraph induced by `used` is realized exactly as it
+    /// appears in the graph: every edge between the vertices is present and every
+    /// other ordered pair is absent. Self-loops are ignored.
+    pub fn realization_probability(&self, graph: &Bitgraph, used: &[usize]) -> f64 {
+        let mut probability = 1.0;
+        for u in used {
+            for v in used.iter().filter(|v| *v != u) {
+                let p = self.get(graph, *u, *v);
+                probability *= if graph.is_connected(*u, *v) {
+                    p
+                } else {
+                    1.0 - p
+                };
+            }
+        }
+        probability
+    }
+}
+
+#[cfg(test)]
+mod testing {
+    use super::*;
+    use crate::gtrie::Gtrie;
+    use petgraph::{Directed, Graph};
+
+    #[test]
+    fn edge_probs_from_buffer() {
+        let mut buffer = "1 2 0.5\n2 3 1\n".as_bytes();
+        let probs = EdgeProbs::from_buffer(&mut buffer).unwrap();
+        let graph: Graph<(), (), Directed> = Graph::from_edges([(0, 1), (1, 2), (2, 0)]);
+        let graph = Bitgraph::from_graph(&graph);
+        assert_eq!(probs.get(&graph, 0, 1), 0.5);
+        assert_eq!(probs.get(&graph, 2, 0), 1.0);
+        assert_eq!(probs.get(&graph, 0, 2), 0.0);
+
+        assert!(EdgeProbs::from_buffer(&mut "1 2 1.5\n".as_bytes()).is_err());
+        assert!(EdgeProbs::from_buffer(&mut "1 2\n".as_bytes()).is_err());
+        assert!(EdgeProbs::from_buffer(&mut "0 2 0.5\n".as_bytes()).is_err());
+    }
+
+    #[test]
+    fn expected_census() {
+        let gtrie = Gtrie::read_from_file("example/gtries/dir3.gt").unwrap();
+
+        // A cycle where each edge exists with probability one half.
+        let cycle: Graph<(), (), Directed> = Graph::from_edges([(0, 1), (1, 2), (2, 0)]);
+        let cycle = Bitgraph::from_graph(&cycle);
+        let mut probs = EdgeProbs::new();
+        for (u, v) in cycle.edges() {
+            probs.insert(u, v, 0.5).unwrap();
+        }
+        let expected = gtrie.census_expected(&cycle, &probs);
+        assert_eq!(expected.values().copied().collect::<Vec<_>>(), vec![0.125]);
+
+        // A path whose missing shortcut appears with probability one quarter.
+        let path: Graph<(), (), Directed> = Graph::from_edges([(0, 1), (1, 2)]);
+        let path = Bitgraph::from_graph(&path);
+        let mut probs = EdgeProbs::new();
+        probs.insert(0, 2, 0.25).unwrap();
+        let expected = gtrie.census_expected(&path, &probs);
+        assert_eq!(expected.values().copied().collect::<Vec<_>>(), vec![0.75]);
+    }
+
+    #[test]
+    fn expected_census_reduces_to_census() {
+        let graph =
+            crate::io::load_numeric_graph("example/graphs/yeast.txt", false, false).unwrap();
+        let graph = Bitgraph::from_graph(&graph);
+        let mut gtrie = Gtrie::read_from_file("example/gtries/dir3.gt").unwrap();
+        let expected = gtrie.census_expected(&graph, &EdgeProbs::new());
+        gtrie.census(&graph);
+        let counts = gtrie.get_nonzero();
+        assert_eq!(expected.len(), counts.len());
+        for (repr, count) in counts {
+            assert_eq!(expected[&repr], count as f64);
+        }
+    }
+}
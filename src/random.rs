@@ -0,0 +1,217 @@
+use crate::bitgraph::Bitgraph;
+
+/// Number of edge swaps attempted per edge by the default edge-swap null model.
+pub const DEFAULT_SWAPS_PER_EDGE: usize = 3;
+
+/// Number of times the configuration model restarts stub-matching before
+/// giving up on the stubs that could not be matched.
+const MAX_MATCHING_ATTEMPTS: usize = 100;
+
+/// A small deterministic xorshift random number generator.
+///
+/// Randomized graphs only need to be reproducible from a seed, not
+/// cryptographically strong.
+#[derive(Clone, Debug)]
+pub struct XorShift(u64);
+impl XorShift {
+    pub fn new(seed: u64) -> Self {
+        // The state must never be zero.
+        XorShift(seed.wrapping_mul(0x9E37_79B9_7F4A_7C15) | 1)
+    }
+
+    pub fn next_u64(&mut self) -> u64 {
+        self.0 ^= self.0 << 13;
+        self.0 ^= self.0 >> 7;
+        self.0 ^= self.0 << 17;
+        self.0
+    }
+
+    /// Returns a value uniformly in `0..n`.
+    pub fn below(&mut self, n: usize) -> usize {
+        (self.next_u64() % n as u64) as usize
+    }
+
+    /// Returns true with probability `p`.
+    pub fn chance(&mut self, p: f64) -> bool {
+        (self.next_u64() as f64 / u64::MAX as f64) < p
+    }
+
+    /// Shuffles a slice in place (Fisher-Yates).
+    pub fn shuffle<T>(&mut self, items: &mut [T]) {
+        for i in (1..items.len()).rev() {
+            let j = self.below(i + 1);
+            items.swap(i, j);
+        }
+    }
+}
+
+/// Returns the `n`-th output of a SplitMix64 generator seeded with `seed`.
+///
+/// Consecutive outputs are well mixed even for consecutive `n`, so they seed
+/// the generators of parallel tasks from a single seed without correlating
+/// their streams.
+pub fn splitmix64(seed: u64, n: u64) -> u64 {
+    let mut z = seed.wrapping_add(n.wrapping_add(1).wrapping_mul(0x9E37_79B9_7F4A_7C15));
+    z = (z ^ (z >> 30)).wrapping_mul(0xBF58_476D_1CE4_E5B9);
+    z = (z ^ (z >> 27)).wrapping_mul(0x94D0_49BB_1331_11EB);
+    z ^ (z >> 31)
+}
+
+/// A function generating a randomized graph from an observed graph.
+pub type NullModel = fn(&Bitgraph, &mut XorShift) -> Bitgraph;
+
+/// Degree-preserving null model using the default number of swaps per edge.
+pub fn edge_swap(graph: &Bitgraph, rng: &mut XorShift) -> Bitgraph {
+    edge_swap_n(graph, rng, DEFAULT_SWAPS_PER_EDGE)
+}
+
+/// Randomizes a graph while preserving the in and out degree of every vertex.
+///
+/// Repeatedly picks two edges `a -> b` and `c -> d` and rewires them to
+/// `a -> d` and `c -> b` whenever that creates neither a self-loop nor an
+/// existing edge.
+pub fn edge_swap_n(graph: &Bitgraph, rng: &mut XorShift, swaps_per_edge: usize) -> Bitgraph {
+    let mut random = graph.clone();
+    let mut edges = graph.edges();
+    if edges.len() < 2 {
+        return random;
+    }
+
+    for _ in 0..swaps_per_edge * edges.len() {
+        let i = rng.below(edges.len());
+        let j = rng.below(edges.len());
+        let (a, b) = edges[i];
+        let (c, d) = edges[j];
+        if a == d || c == b || random.is_connected(a, d) || random.is_connected(c, b) {
+            continue;
+        }
+        random.remove_edge(a, b);
+        random.remove_edge(c, d);
+        random.insert_edge(a, d);
+        random.insert_edge(c, b);
+        edges[i] = (a, d);
+        edges[j] = (c, b);
+    }
+    random
+}
+
+/// Erdős–Rényi null model placing the same number of edges as the observed
+/// graph uniformly at random between distinct vertices.
+pub fn erdos_renyi(graph: &Bitgraph, rng: &mut XorShift) -> Bitgraph {
+    let n = graph.n_nodes();
+    let mut random = Bitgraph::with_capacity(n, graph.is_dir());
+    let n_edges = graph.edges().len().min(n * n.saturating_sub(1));
+
+    let mut placed = 0;
+    while placed < n_edges {
+        let u = rng.below(n);
+        let v = rng.below(n);
+        if u != v && random.insert_edge(u, v) {
+            placed += 1;
+        }
+    }
+    random
+}
+
+/// Configuration null model matching the in and out degree sequence of the
+/// observed graph by randomly pairing out-stubs with in-stubs.
+///
+/// Pairings creating self-loops or repeated edges are rejected and the
+/// matching restarts. If no simple graph is found after a fixed number of
+/// attempts the stubs that could not be matched in the last attempt are
+/// dropped, so degrees are then only approximately preserved.
+pub fn configuration(graph: &Bitgraph, rng: &mut XorShift) -> Bitgraph {
+    let n = graph.n_nodes();
+    let edges = graph.edges();
+    let out_stubs = edges.iter().map(|(u, _)| *u).collect::<Vec<_>>();
+    let mut in_stubs = edges.iter().map(|(_, v)| *v).collect::<Vec<_>>();
+
+    let mut random = Bitgraph::with_capacity(n, graph.is_dir());
+    for _ in 0..MAX_MATCHING_ATTEMPTS {
+        random = Bitgraph::with_capacity(n, graph.is_dir());
+        rng.shuffle(&mut in_stubs);
+        if match_stubs(&mut random, &out_stubs, &mut in_stubs) {
+            break;
+        }
+    }
+    random
+}
+
+/// Greedily pairs each out-stub with a remaining in-stub that keeps the
+/// graph simple. Returns `false` if some out-stub could not be paired.
+fn match_stubs(random: &mut Bitgraph, out_stubs: &[usize], in_stubs: &mut [usize]) -> bool {
+    let mut complete = true;
+    for (i, u) in out_stubs.iter().enumerate() {
+        let partner = (i..in_stubs.len()).find(|j| {
+            let v = in_stubs[*j];
+            *u != v && !random.is_connected(*u, v)
+        });
+        match partner {
+            Some(j) => {
+                in_stubs.swap(i, j);
+                random.insert_edge(*u, in_stubs[i]);
+            }
+            None => complete = false,
+        }
+    }
+    complete
+}
+
+#[cfg(test)]
+mod testing {
+    use super::*;
+    use crate::io::load_numeric_graph;
+
+    fn degree_sequence(graph: &Bitgraph) -> Vec<(usize, usize)> {
+        (0..graph.n_nodes())
+            .map(|u| (graph.out_degree(u), graph.in_degree(u)))
+            .collect()
+    }
+
+    fn example() -> Bitgraph {
+        let graph = load_numeric_graph("example/graphs/yeast.txt", false, false).unwrap();
+        Bitgraph::from_graph(&graph)
+    }
+
+    #[test]
+    fn splitmix64_reference_outputs() {
+        // The first outputs of SplitMix64 seeded with 0.
+        assert_eq!(splitmix64(0, 0), 0xE220_A839_7B1D_CDAF);
+        assert_eq!(splitmix64(0, 1), 0x6E78_9E6A_A1B9_65F4);
+        assert_ne!(splitmix64(1, 0), splitmix64(0, 1));
+    }
+
+    #[test]
+    fn shuffle_is_permutation() {
+        let mut rng = XorShift::new(7);
+        let mut items = (0..50).collect::<Vec<_>>();
+        rng.shuffle(&mut items);
+        assert_ne!(items, (0..50).collect::<Vec<_>>());
+        items.sort();
+        assert_eq!(items, (0..50).collect::<Vec<_>>());
+    }
+
+    #[test]
+    fn edge_swap_preserves_degrees() {
+        let graph = example();
+        let random = edge_swap(&graph, &mut XorShift::new(0));
+        assert_eq!(degree_sequence(&random), degree_sequence(&graph));
+        assert_ne!(random.edges(), graph.edges());
+    }
+
+    #[test]
+    fn erdos_renyi_preserves_edge_count() {
+        let graph = example();
+        let random = erdos_renyi(&graph, &mut XorShift::new(0));
+        assert_eq!(random.n_nodes(), graph.n_nodes());
+        assert_eq!(random.edges().len(), graph.edges().len());
+    }
+
+    #[test]
+    fn configuration_preserves_degrees() {
+        let graph = example();
+        let random = configuration(&graph, &mut XorShift::new(0));
+        assert_eq!(degree_sequence(&random), degree_sequence(&graph));
+        assert_ne!(random.edges(), graph.edges());
+    }
+}
@@ -0,0 +1,84 @@
+use crate::{bitgraph::Bitgraph, random::XorShift};
+use graph_canon::CanonLabeling;
+use hashbrown::HashMap;
+use itertools::Itertools;
+
+/// Creates a random directed graph with `n` nodes where each ordered pair of
+/// distinct vertices is connected with probability `p`.
+pub fn random_graph(rng: &mut XorShift, n: usize, p: f64) -> Bitgraph {
+    let mut graph = Bitgraph::with_capacity(n, true);
+    for u in 0..n {
+        for v in 0..n {
+            if u != v && rng.chance(p) {
+                graph.insert_edge(u, v);
+            }
+        }
+    }
+    graph
+}
+
+/// Counts every connected induced subgraph of size `k` by enumerating all
+/// vertex subsets, keyed by the canonical labeling of the subgraph.
+pub fn brute_force_census(graph: &Bitgraph, k: usize) -> HashMap<CanonLabeling, usize> {
+    let mut counts = HashMap::new();
+    for subset in (0..graph.n_nodes()).combinations(k) {
+        let induced = graph.induced_subgraph(&subset);
+        if induced.neighborhood(0, k).len() < k {
+            continue;
+        }
+        let label = CanonLabeling::new(&induced.to_graph());
+        *counts.entry(label).or_insert(0) += 1;
+    }
+    counts
+}
+
+#[cfg(test)]
+mod testing {
+    use super::*;
+    use crate::{gtrie::Gtrie, io};
+
+    /// Rekeys the nonzero census results of a gtrie by canonical labeling.
+    fn canonical_counts(results: HashMap<String, usize>) -> HashMap<CanonLabeling, usize> {
+        results
+            .into_iter()
+            .filter(|(_, count)| *count > 0)
+            .map(|(repr, count)| (CanonLabeling::new(&io::load_repr(&repr).unwrap()), count))
+            .collect()
+    }
+
+    #[test]
+    fn brute_force_known_counts() {
+        let graph = io::load_numeric_graph("example/graphs/example.txt", true, false).unwrap();
+        let graph = Bitgraph::from_graph(&graph);
+        let total = brute_force_census(&graph, 3).values().sum::<usize>();
+        assert_eq!(total, 16);
+    }
+
+    #[test]
+    fn census_matches_brute_force() {
+        for (size, path) in [(3, "example/gtries/dir3.gt"), (4, "example/gtries/dir4.gt")] {
+            let reference = Gtrie::read_from_file(path).unwrap();
+            for seed in 0..40 {
+                let mut rng = XorShift::new(seed);
+                let n = size + rng.below(6);
+                let p = 0.1 + 0.6 * rng.below(100) as f64 / 100.0;
+                let graph = random_graph(&mut rng, n, p);
+                let expected = brute_force_census(&graph, size);
+
+                let mut gtrie = Gtrie::read_from_file(path).unwrap();
+                gtrie.census(&graph);
+                assert_eq!(
+                    canonical_counts(gtrie.get_nonzero()),
+                    expected,
+                    "census of size {} differs for seed {}",
+                    size,
+                    seed
+                );
+
+                let mut shared = HashMap::new();
+                reference.census_into(&graph, &mut shared);
+                assert_eq!(canonical_counts(shared), expected);
+            }
+        }
+    }
+}
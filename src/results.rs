@@ -0,0 +1,612 @@
+use anyhow::{bail, Result};
+use hashbrown::HashMap;
+use serde::{Deserialize, Serialize};
+use std::{
+    collections::BTreeMap,
+    io::Write,
+    sync::atomic::{AtomicU64, Ordering},
+    time::Duration,
+};
+
+use crate::{bitgraph::Bitgraph, gtrie::Gtrie, io};
+use petgraph::{Directed, Graph};
+
+/// The frequencies found by a census, stored without the trie that produced them.
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct CensusResults {
+    /// Maximum motif size of the trie used for the census.
+    pub max_depth: usize,
+
+    /// Total number of subgraphs matched by the census.
+    pub total_subgraphs: usize,
+
+    /// Frequency of every motif of the trie, keyed by its graph6 representation.
+    pub counts: BTreeMap<String, usize>,
+}
+impl CensusResults {
+    pub fn read_from_file(path: &str) -> Result<Self> {
+        let file = std::fs::File::open(path)?;
+        let reader = std::io::BufReader::new(file);
+        let results = rmp_serde::from_read(reader)?;
+        Ok(results)
+    }
+
+    pub fn write_to_file(&self, path: &str) -> Result<()> {
+        let mut file = std::fs::File::create(path)?;
+        self.write_to_buffer(&mut file)?;
+        Ok(())
+    }
+
+    pub fn write_to_buffer<W: Write>(&self, writer: &mut W) -> Result<()> {
+        rmp_serde::encode::write(writer, self)?;
+        Ok(())
+    }
+
+    /// Adds the counts of another census performed with the same trie.
+    pub fn merge(&mut self, other: &CensusResults) -> Result<()> {
+        if self.max_depth != other.max_depth {
+            bail!(
+                "Cannot merge census results of motifs up to size {} and {}",
+                self.max_depth,
+                other.max_depth
+            );
+        }
+        self.total_subgraphs += other.total_subgraphs;
+        for (repr, count) in other.counts.iter() {
+            *self.counts.entry(repr.clone()).or_insert(0) += count;
+        }
+        Ok(())
+    }
+}
+
+/// Timing and search effort of a single census, see `Gtrie::census_reporting`.
+#[derive(Debug, Clone, Default, PartialEq)]
+pub struct CensusReport {
+    /// Number of subgraphs matched by the census.
+    pub total_subgraphs: usize,
+
+    /// Wall-clock time taken by the census.
+    pub elapsed: Duration,
+
+    /// Number of trie nodes the search tried to extend a partial match with.
+    pub nodes_visited: usize,
+
+    /// Number of candidate vertices checked against the structure of a trie node.
+    pub candidates_tested: usize,
+}
+
+/// A one-line overview of the motifs found by a census, see `Gtrie::summary`.
+#[derive(Debug, Clone, Default, PartialEq, Eq)]
+pub struct CensusSummary {
+    /// Number of motifs occurring at least once.
+    pub n_distinct: usize,
+
+    /// Total number of subgraphs matched by the census.
+    pub total: usize,
+
+    /// The repr of the most frequent motif, the smallest one among ties, or
+    /// `None` if no subgraph was found.
+    pub most_frequent_repr: Option<String>,
+
+    /// Frequency of the most frequent motif.
+    pub most_frequent_count: usize,
+}
+impl std::fmt::Display for CensusSummary {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        write!(
+            f,
+            "{} distinct motifs in {} subgraphs",
+            self.n_distinct, self.total
+        )?;
+        if let Some(repr) = &self.most_frequent_repr {
+            write!(f, ", most frequent {} ({})", repr, self.most_frequent_count)?;
+        }
+        Ok(())
+    }
+}
+
+/// Two-sided 95% quantile of the standard normal distribution.
+const Z_95: f64 = 1.959_963_984_540_054;
+
+/// The estimated frequency of a motif from a sampled census, see
+/// `Gtrie::census_sampled`.
+#[derive(Debug, Clone, PartialEq)]
+pub struct SampledEstimate {
+    /// The graph6 representation of the motif.
+    pub repr: String,
+
+    /// Number of occurrences found by the sampled census.
+    pub sampled: usize,
+
+    /// Horvitz-Thompson estimate of the number of occurrences in the graph.
+    pub estimate: f64,
+
+    /// Estimated standard error of `estimate`.
+    pub std_error: f64,
+
+    /// Lower bound of the 95% confidence interval from the normal
+    /// approximation, never below `sampled`.
+    pub ci_low: f64,
+
+    /// Upper bound of the 95% confidence interval.
+    pub ci_high: f64,
+}
+impl SampledEstimate {
+    /// Estimates the frequency of a motif from the occurrences sampled, each
+    /// found with probability `inclusion`.
+    ///
+    /// The variance is the Horvitz-Thompson variance estimate: each occurrence
+    /// contributes `(1 - inclusion) / inclusion^2`, and each ordered pair of
+    /// occurrences sampled together `pair_sum / inclusion^2` in total, see
+    /// `sampling::Sampler`.
+    pub fn new(repr: &str, sampled: usize, inclusion: f64, pair_sum: f64) -> Self {
+        let estimate = sampled as f64 / inclusion;
+        let variance = (sampled as f64 * (1.0 - inclusion) + pair_sum) / inclusion.powi(2);
+        let std_error = variance.sqrt();
+        Self {
+            repr: repr.to_string(),
+            sampled,
+            estimate,
+            std_error,
+            ci_low: (estimate - Z_95 * std_error).max(sampled as f64),
+            ci_high: estimate + Z_95 * std_error,
+        }
+    }
+}
+
+/// The invocation that produced a set of results, recorded for traceability.
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct Provenance {
+    /// Version of the crate that produced the results.
+    pub version: String,
+
+    /// The full command line, including every flag.
+    pub command: Vec<String>,
+
+    /// Path to the gtrie used for the census.
+    pub gtrie: String,
+
+    /// `Gtrie::structural_hash` of the gtrie, in hexadecimal.
+    pub gtrie_hash: String,
+
+    /// Path to the input graph.
+    pub input: String,
+
+    /// Maximum motif size of the gtrie.
+    pub motif_size: usize,
+
+    /// Seed of the random number generator, if any was used.
+    pub seed: Option<u64>,
+}
+impl Provenance {
+    pub fn new(
+        command: Vec<String>,
+        gtrie_path: &str,
+        gtrie: &Gtrie,
+        input: &str,
+        seed: Option<u64>,
+    ) -> Self {
+        Provenance {
+            version: env!("CARGO_PKG_VERSION").to_string(),
+            command,
+            gtrie: gtrie_path.to_string(),
+            gtrie_hash: format!("{:016x}", gtrie.structural_hash()),
+            input: input.to_string(),
+            motif_size: gtrie.max_depth(),
+            seed,
+        }
+    }
+
+    pub fn to_json(&self) -> Result<String> {
+        Ok(serde_json::to_string_pretty(self)?)
+    }
+
+    /// Writes the provenance as `# key: value` lines to head a text or CSV output.
+    pub fn write_comments<W: Write>(&self, writer: &mut W) -> Result<()> {
+        writeln!(writer, "# version: {}", self.version)?;
+        writeln!(writer, "# command: {}", self.command.join(" "))?;
+        writeln!(writer, "# gtrie: {}", self.gtrie)?;
+        writeln!(writer, "# gtrie_hash: {}", self.gtrie_hash)?;
+        writeln!(writer, "# input: {}", self.input)?;
+        writeln!(writer, "# motif_size: {}", self.motif_size)?;
+        if let Some(seed) = self.seed {
+            writeln!(writer, "# seed: {}", seed)?;
+        }
+        Ok(())
+    }
+}
+
+/// The number of occurrences of each motif that each vertex of a graph takes
+/// part in, see `Gtrie::census_participation`.
+#[derive(Debug, Clone, Default, PartialEq)]
+pub struct ParticipationTable {
+    n_nodes: usize,
+    reprs: Vec<String>,
+
+    /// Indexed as `[motif][v]`, with motifs in the order of `reprs`.
+    counts: Vec<Vec<usize>>,
+}
+impl ParticipationTable {
+    /// Creates an empty table over the vertices of a graph of `n_nodes` and
+    /// the motifs `reprs`, which are sorted.
+    pub fn new(n_nodes: usize, mut reprs: Vec<String>) -> Self {
+        reprs.sort_unstable();
+        reprs.dedup();
+        let counts = vec![vec![0; n_nodes]; reprs.len()];
+        Self {
+            n_nodes,
+            reprs,
+            counts,
+        }
+    }
+
+    /// The index of the motif `repr` in `reprs`, as taken by `add_occurrence`.
+    pub fn motif_index(&self, repr: &str) -> Option<usize> {
+        self.reprs.binary_search_by(|r| r.as_str().cmp(repr)).ok()
+    }
+
+    /// Counts an occurrence of the motif at `motif_index` for each of the
+    /// vertices of `used`.
+    pub fn add_occurrence(&mut self, motif_index: usize, used: &[usize]) {
+        let counts = &mut self.counts[motif_index];
+        for v in used {
+            counts[*v] += 1;
+        }
+    }
+
+    /// The number of occurrences of the motif `repr` that vertex `v` takes part in.
+    pub fn get(&self, repr: &str, v: usize) -> usize {
+        self.motif_index(repr)
+            .and_then(|idx| self.counts[idx].get(v))
+            .copied()
+            .unwrap_or(0)
+    }
+
+    /// The motifs of the table, sorted.
+    pub fn reprs(&self) -> &[String] {
+        &self.reprs
+    }
+
+    /// Writes a vertex by motif matrix as TSV: a `vertex` header followed by
+    /// the reprs, and a row per vertex `v` labeled `vertex_id(v)`.
+    ///
+    /// Unless `is_dir`, the motifs are given by their undirected reprs as in
+    /// `Gtrie::undirected_results`, dropping those an undirected graph can't
+    /// contain.
+    pub fn write_tsv<W: Write, F: Fn(usize) -> usize>(
+        &self,
+        writer: &mut W,
+        is_dir: bool,
+        vertex_id: F,
+    ) -> Result<()> {
+        let (reprs, counts): (Vec<_>, Vec<_>) = self
+            .reprs
+            .iter()
+            .zip(&self.counts)
+            .filter_map(|(repr, counts)| io::output_repr(repr, is_dir).map(|r| (r, counts)))
+            .unzip();
+        writeln!(writer, "vertex\t{}", reprs.join("\t"))?;
+        for v in 0..self.n_nodes {
+            write!(writer, "{}", vertex_id(v))?;
+            for counts in &counts {
+                write!(writer, "\t{}", counts[v])?;
+            }
+            writeln!(writer)?;
+        }
+        Ok(())
+    }
+}
+
+/// Census totals that many threads can accumulate into at once.
+///
+/// Each graph node of the trie has its own atomic counter, indexed by its
+/// `GtrieNode::leaf`, so concurrent censuses never take a lock or look up a
+/// repr. The counters are only mapped to reprs when reporting.
+#[derive(Debug)]
+pub struct SharedCensusResults {
+    max_depth: usize,
+    reprs: Vec<String>,
+
+    /// The index in `reprs` of the motif of each leaf.
+    motifs: Vec<Option<usize>>,
+    counts: Vec<AtomicU64>,
+    total_subgraphs: AtomicU64,
+}
+impl SharedCensusResults {
+    /// Creates zeroed counters for every graph node of the trie.
+    pub fn new(gtrie: &Gtrie) -> Self {
+        let reprs = gtrie
+            .reprs()
+            .into_iter()
+            .map(|repr| repr.to_string())
+            .collect::<Vec<_>>();
+        let motifs = {
+            let index = reprs
+                .iter()
+                .enumerate()
+                .map(|(idx, repr)| (repr.as_str(), idx))
+                .collect::<HashMap<_, _>>();
+            gtrie
+                .leaf_reprs()
+                .into_iter()
+                .map(|repr| index.get(repr).copied())
+                .collect::<Vec<_>>()
+        };
+        let counts = motifs.iter().map(|_| AtomicU64::new(0)).collect();
+        SharedCensusResults {
+            max_depth: gtrie.max_depth(),
+            reprs,
+            motifs,
+            counts,
+            total_subgraphs: AtomicU64::new(0),
+        }
+    }
+
+    /// Counts one occurrence of the graph node numbered `leaf`.
+    pub fn increment(&self, leaf: usize) {
+        self.counts[leaf].fetch_add(1, Ordering::Relaxed);
+        self.total_subgraphs.fetch_add(1, Ordering::Relaxed);
+    }
+
+    /// Returns the current count of the motif, summed over its graph nodes.
+    pub fn get(&self, repr: &str) -> Option<u64> {
+        let idx = self.reprs.iter().position(|r| r == repr)?;
+        Some(self.motif_count(idx))
+    }
+
+    /// The count of the motif at `idx` of `reprs`, summed over its graph nodes.
+    fn motif_count(&self, idx: usize) -> u64 {
+        self.motifs
+            .iter()
+            .zip(self.counts.iter())
+            .filter(|(motif, _)| **motif == Some(idx))
+            .map(|(_, count)| count.load(Ordering::Relaxed))
+            .sum()
+    }
+
+    pub fn total_subgraphs(&self) -> u64 {
+        self.total_subgraphs.load(Ordering::Relaxed)
+    }
+
+    /// Takes a snapshot of the counters as standalone results.
+    pub fn to_results(&self) -> CensusResults {
+        CensusResults {
+            max_depth: self.max_depth,
+            total_subgraphs: self.total_subgraphs() as usize,
+            counts: self
+                .reprs
+                .iter()
+                .enumerate()
+                .map(|(idx, repr)| (repr.clone(), self.motif_count(idx) as usize))
+                .collect(),
+        }
+    }
+}
+
+/// Censuses a graph given as a list of 0-indexed edges without going through a file.
+///
+/// Undirected edges are reciprocated and the graph is censused as undirected.
+/// The counts are keyed by the reprs of the trie, which is left untouched.
+pub fn census_edges(gtrie: &Gtrie, edges: &[(u32, u32)], directed: bool) -> CensusResults {
+    let reciprocated = edges.iter().flat_map(|(u, v)| [(*u, *v), (*v, *u)]);
+    let graph: Graph<(), (), Directed> = if directed {
+        Graph::from_edges(edges)
+    } else {
+        Graph::from_edges(reciprocated)
+    };
+    let mut graph = Bitgraph::from_graph(&graph);
+    if !directed {
+        graph = graph.into_undirected();
+    }
+
+    let mut counts = HashMap::new();
+    let total_subgraphs = gtrie.census_into(&graph, &mut counts);
+    CensusResults {
+        max_depth: gtrie.max_depth(),
+        total_subgraphs,
+        counts: gtrie
+            .reprs()
+            .into_iter()
+            .map(|repr| (repr.to_string(), counts.get(repr).copied().unwrap_or(0)))
+            .collect(),
+    }
+}
+
+#[cfg(test)]
+mod testing {
+    use super::*;
+    use crate::{bitgraph::Bitgraph, gtrie::Gtrie, io::load_numeric_graph};
+
+    #[test]
+    fn results_roundtrip_and_merge() {
+        let graph = load_numeric_graph("example/graphs/yeast.txt", true, false).unwrap();
+        let graph = Bitgraph::from_graph(&graph);
+        let mut gtrie = Gtrie::read_from_file("example/gtries/dir3.gt").unwrap();
+        gtrie.census(&graph);
+
+        let results = gtrie.extract_results();
+        assert_eq!(results.counts.len(), gtrie.reprs().len());
+        assert_eq!(results.total_subgraphs, gtrie.total_subgraphs());
+
+        let mut buffer = Vec::new();
+        results.write_to_buffer(&mut buffer).unwrap();
+        let decoded: CensusResults = rmp_serde::from_slice(&buffer).unwrap();
+        assert_eq!(decoded, results);
+
+        let mut merged = results.clone();
+        merged.merge(&decoded).unwrap();
+        assert_eq!(merged.total_subgraphs, 2 * results.total_subgraphs);
+        for (repr, count) in results.counts.iter() {
+            assert_eq!(merged.counts[repr], 2 * count);
+        }
+
+        let other = Gtrie::new(4).extract_results();
+        assert!(merged.merge(&other).is_err());
+    }
+
+    #[test]
+    fn shared_census_across_threads() {
+        let graph = load_numeric_graph("example/graphs/yeast.txt", true, false).unwrap();
+        let graph = Bitgraph::from_graph(&graph);
+        let mut gtrie = Gtrie::read_from_file("example/gtries/dir3.gt").unwrap();
+
+        let n_threads = 4;
+        let shared = SharedCensusResults::new(&gtrie);
+        std::thread::scope(|scope| {
+            for _ in 0..n_threads {
+                scope.spawn(|| gtrie.census_shared(&graph, &shared));
+            }
+        });
+
+        gtrie.census(&graph);
+        let expected = gtrie.extract_results();
+        let results = shared.to_results();
+        assert_eq!(
+            results.total_subgraphs,
+            n_threads * expected.total_subgraphs
+        );
+        for (repr, count) in expected.counts.iter() {
+            assert_eq!(results.counts[repr], n_threads * count);
+            assert_eq!(shared.get(repr), Some((n_threads * count) as u64));
+        }
+    }
+
+    #[test]
+    fn shared_census_sums_leaves_of_a_motif() {
+        let graph = load_numeric_graph("example/graphs/yeast.txt", true, false).unwrap();
+        let graph = Bitgraph::from_graph(&graph);
+
+        // An out-star inserted in both orientations under one repr, as
+        // `build --merge-reversals` does.
+        let motif: Graph<(), (), Directed> = Graph::from_edges([(0, 1), (0, 2)]);
+        let mut reversed = motif.clone();
+        reversed.reverse();
+        let mut gtrie = Gtrie::new(3);
+        let (repr, _) = gtrie.insert_canonical(&motif);
+        let (bgraph, canon) = crate::isomorphism::canonicalize(&reversed);
+        gtrie.insert_canonical_form(&bgraph, &canon, repr.clone());
+        assert_eq!(gtrie.reprs(), vec![repr.as_str()]);
+        assert_eq!(gtrie.n_leaves(), 2);
+
+        let shared = SharedCensusResults::new(&gtrie);
+        gtrie.census_shared(&graph, &shared);
+        gtrie.census(&graph);
+        let expected = gtrie.get_nonzero()[&repr] as u64;
+        assert_eq!(shared.get(&repr), Some(expected));
+        assert_eq!(shared.to_results().counts[&repr] as u64, expected);
+    }
+
+    #[test]
+    fn census_in_memory_edges() {
+        // The edges of example/graphs/example.txt, 0-indexed.
+        let edges = [
+            (0, 1),
+            (1, 2),
+            (2, 0),
+            (3, 0),
+            (0, 4),
+            (5, 1),
+            (1, 6),
+            (7, 2),
+            (2, 8),
+        ];
+        let gtrie = Gtrie::read_from_file("example/gtries/dir3.gt").unwrap();
+        let results = census_edges(&gtrie, &edges, true);
+        assert_eq!(results.total_subgraphs, 16);
+        let nonzero = results
+            .counts
+            .values()
+            .filter(|c| **c > 0)
+            .collect::<Vec<_>>();
+        assert_eq!(nonzero.len(), 4);
+        assert!(nonzero.iter().all(|c| [1, 3, 9].contains(*c)));
+
+        let graph = load_numeric_graph("example/graphs/example.txt", true, false).unwrap();
+        let mut expected = HashMap::new();
+        gtrie.census_into(&Bitgraph::from_graph(&graph), &mut expected);
+        for (repr, count) in results.counts.iter() {
+            assert_eq!(expected.get(repr).copied().unwrap_or(0), *count);
+        }
+
+        // Undirected edges only match symmetric motifs.
+        let triangle = census_edges(&gtrie, &[(0, 1), (1, 2), (2, 0)], false);
+        assert_eq!(triangle.total_subgraphs, 1);
+        let nonzero = triangle.counts.iter().find(|(_, c)| **c > 0).unwrap();
+        assert_eq!(
+            crate::io::undirected_repr(nonzero.0),
+            Some("Bw".to_string())
+        );
+    }
+
+    #[test]
+    fn provenance_fields() {
+        let path = "example/gtries/dir3.gt";
+        let gtrie = Gtrie::read_from_file(path).unwrap();
+        let command = ["graphtries", "significance", "--seed", "7"]
+            .map(String::from)
+            .to_vec();
+        let provenance = Provenance::new(command, path, &gtrie, "yeast.txt", Some(7));
+        let again = Provenance::new(
+            vec![],
+            path,
+            &Gtrie::read_from_file(path).unwrap(),
+            "",
+            None,
+        );
+        assert_eq!(provenance.gtrie_hash, again.gtrie_hash);
+        assert_eq!(provenance.gtrie_hash.len(), 16);
+
+        let json: serde_json::Value = serde_json::from_str(&provenance.to_json().unwrap()).unwrap();
+        for field in [
+            "version",
+            "command",
+            "gtrie",
+            "gtrie_hash",
+            "input",
+            "motif_size",
+            "seed",
+        ] {
+            assert!(json.get(field).is_some(), "missing {}", field);
+        }
+        assert_eq!(json["motif_size"], 3);
+        assert_eq!(json["seed"], 7);
+
+        let mut comments = Vec::new();
+        provenance.write_comments(&mut comments).unwrap();
+        let comments = String::from_utf8(comments).unwrap();
+        assert!(comments.lines().all(|line| line.starts_with("# ")));
+        assert!(comments.contains("# command: graphtries significance --seed 7\n"));
+        assert!(comments.contains(&format!("# gtrie_hash: {}\n", provenance.gtrie_hash)));
+    }
+
+    #[test]
+    fn participation_table_by_motif_index() {
+        let reprs = vec!["&BC_".to_string(), "&B?o".to_string(), "&BC_".to_string()];
+        let mut table = ParticipationTable::new(3, reprs);
+        assert_eq!(table.reprs(), ["&B?o", "&BC_"]);
+        let idx = table.motif_index("&BC_").unwrap();
+        table.add_occurrence(idx, &[0, 2]);
+        table.add_occurrence(idx, &[2, 1]);
+        assert_eq!(table.motif_index("&BW_"), None);
+        assert_eq!(
+            (0..3).map(|v| table.get("&BC_", v)).collect::<Vec<_>>(),
+            vec![1, 1, 2]
+        );
+        assert_eq!(table.get("&B?o", 2), 0);
+        assert_eq!(table.get("&BW_", 2), 0);
+
+        let mut buffer = Vec::new();
+        table.write_tsv(&mut buffer, true, |v| v).unwrap();
+        let tsv = String::from_utf8(buffer).unwrap();
+        assert_eq!(tsv, "vertex\t&B?o\t&BC_\n0\t0\t1\n1\t0\t1\n2\t0\t2\n");
+
+        // Only the symmetric motifs are kept for undirected graphs.
+        let reprs = vec!["&AW".to_string(), "&AG".to_string()];
+        let mut table = ParticipationTable::new(2, reprs);
+        table.add_occurrence(table.motif_index("&AW").unwrap(), &[1]);
+        let mut buffer = Vec::new();
+        table.write_tsv(&mut buffer, false, |v| v + 1).unwrap();
+        let tsv = String::from_utf8(buffer).unwrap();
+        assert_eq!(tsv, "vertex\tA_\n1\t0\n2\t1\n");
+    }
+}
@@ -0,0 +1,95 @@
+use crate::{random::XorShift, results::SampledEstimate};
+use anyhow::{bail, Result};
+use hashbrown::HashMap;
+
+/// The state of a sampled census, see `census::match_child_sampled`.
+///
+/// Every vertex matched at a trie node of depth `d` is kept independently with
+/// probability `probs[d - 1]`, so an occurrence of size `k` is found with the
+/// product of the first `k` probabilities. Two occurrences of a motif sharing
+/// their first `j` vertices share the draws keeping them, and so are found
+/// together more often than independent ones. These pairs are tracked to
+/// estimate the variance of the frequencies.
+#[derive(Debug, Clone)]
+pub struct Sampler {
+    rng: XorShift,
+    probs: Vec<f64>,
+
+    /// Probability of reaching each depth, starting at 1 for depth 0.
+    reach: Vec<f64>,
+
+    /// Identifier of the draw keeping the current partial match at each depth.
+    draws: Vec<u64>,
+    n_draws: u64,
+
+    motifs: HashMap<String, MotifPairs>,
+}
+
+/// The occurrences of a motif found under the current partial match at each
+/// depth, and the sum over ordered pairs of occurrences sharing draws of
+/// `1 - P(both draws kept)` relative to independent draws.
+#[derive(Debug, Clone, Default)]
+struct MotifPairs {
+    found_under: Vec<(u64, usize)>,
+    pair_sum: f64,
+}
+
+impl Sampler {
+    /// Creates a sampler with one probability in `(0, 1]` per depth.
+    pub fn new(probs: &[f64], rng: XorShift) -> Result<Self> {
+        if let Some(p) = probs.iter().find(|p| !(**p > 0.0 && **p <= 1.0)) {
+            bail!("ERROR: Sampling probability {} is not in (0, 1]", p);
+        }
+        let mut reach = vec![1.0];
+        for p in probs {
+            reach.push(reach[reach.len() - 1] * p);
+        }
+        Ok(Self {
+            rng,
+            probs: probs.to_vec(),
+            reach,
+            draws: vec![0; probs.len()],
+            n_draws: 0,
+            motifs: HashMap::new(),
+        })
+    }
+
+    /// Draws whether to keep a vertex matched at a node of `depth`.
+    pub(crate) fn keep(&mut self, depth: usize) -> bool {
+        let p = self.probs[depth - 1];
+        if p < 1.0 && !self.rng.chance(p) {
+            return false;
+        }
+        self.n_draws += 1;
+        self.draws[depth - 1] = self.n_draws;
+        true
+    }
+
+    /// Records an occurrence of the motif `repr` of `size` found under the
+    /// current partial match.
+    pub(crate) fn record(&mut self, repr: &str, size: usize) {
+        if !self.motifs.contains_key(repr) {
+            self.motifs.insert(repr.to_string(), MotifPairs::default());
+        }
+        let pairs = self.motifs.get_mut(repr).unwrap();
+        pairs.found_under.resize(size - 1, (0, 0));
+
+        // A pair sharing its first `j` vertices has `1 - reach[j]`, the sum of
+        // `reach[d - 1] - reach[d]` over the depths `d` it shares.
+        for (d, (draw, n_found)) in pairs.found_under.iter_mut().enumerate() {
+            if *draw != self.draws[d] {
+                *draw = self.draws[d];
+                *n_found = 0;
+            }
+            pairs.pair_sum += 2.0 * *n_found as f64 * (self.reach[d] - self.reach[d + 1]);
+            *n_found += 1;
+        }
+    }
+
+    /// Estimates the frequency of the motif `repr` of `size` from its
+    /// `sampled` occurrences.
+    pub fn estimate(&self, repr: &str, size: usize, sampled: usize) -> SampledEstimate {
+        let pair_sum = self.motifs.get(repr).map_or(0.0, |pairs| pairs.pair_sum);
+        SampledEstimate::new(repr, sampled, self.reach[size], pair_sum)
+    }
+}
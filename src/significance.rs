@@ -0,0 +1,233 @@
+use crate::{bitgraph::Bitgraph, gtrie::Gtrie, random::splitmix64};
+use hashbrown::HashMap;
+use rayon::prelude::*;
+
+/// The null models randomizing the observed graph, and their generator.
+pub use crate::random::{
+    configuration, edge_swap, edge_swap_n, erdos_renyi, NullModel, XorShift, DEFAULT_SWAPS_PER_EDGE,
+};
+
+/// Over-representation of a motif in a graph relative to a null model.
+#[derive(Debug, Clone)]
+pub struct MotifSignificance {
+    /// The graph6 representation of the motif.
+    pub repr: String,
+
+    /// Number of occurrences in the observed graph.
+    pub count: usize,
+
+    /// Mean number of occurrences across the randomized graphs.
+    pub mean: f64,
+
+    /// Sample standard deviation of the occurrences across the randomized graphs.
+    pub std: f64,
+
+    /// `(count - mean) / std`, which is infinite or NaN if the randomized
+    /// graphs all share the same count.
+    pub z_score: f64,
+
+    /// Fraction of randomized graphs with at least as many occurrences as
+    /// the observed graph, counting the observed graph itself.
+    pub p_value: f64,
+}
+
+/// Computes the significance of each motif of the gtrie in the graph against
+/// `n_random` graphs drawn from the null model.
+///
+/// The randomized graphs are generated and censused in parallel on the current
+/// rayon thread pool. Each one draws from its own generator seeded from `seed`
+/// and its index, so the results do not depend on the number of threads.
+///
+/// Motifs are reported in the order of `Gtrie::reprs`.
+pub fn significance<F>(
+    gtrie: &Gtrie,
+    graph: &Bitgraph,
+    null_model: F,
+    n_random: usize,
+    seed: u64,
+) -> Vec<MotifSignificance>
+where
+    F: Fn(&Bitgraph, &mut XorShift) -> Bitgraph + Sync,
+{
+    let mut observed = HashMap::new();
+    gtrie.census_into(graph, &mut observed);
+
+    let random_counts = (0..n_random)
+        .into_par_iter()
+        .map(|idx| {
+            let mut rng = task_rng(seed, idx);
+            let random = null_model(graph, &mut rng);
+            let mut counts = HashMap::new();
+            gtrie.census_into(&random, &mut counts);
+            counts
+        })
+        .collect::<Vec<_>>();
+
+    gtrie
+        .reprs()
+        .into_iter()
+        .map(|repr| {
+            let count = observed.get(repr).copied().unwrap_or(0);
+            let samples = random_counts
+                .iter()
+                .map(|counts| counts.get(repr).copied().unwrap_or(0))
+                .collect::<Vec<_>>();
+            summarize(repr, count, &samples)
+        })
+        .collect()
+}
+
+/// Normalizes the z-scores of the motifs to unit length, giving the
+/// significance profile of the graph ordered by repr.
+///
+/// Ordered like `Gtrie::fingerprint`, so that the profiles of graphs analysed
+/// with the same trie are aligned and comparable regardless of their size.
+/// Motifs without a finite z-score contribute zero.
+pub fn significance_profile(stats: &[MotifSignificance]) -> Vec<(String, f64)> {
+    let mut profile = stats
+        .iter()
+        .map(|stats| {
+            let z_score = if stats.z_score.is_finite() {
+                stats.z_score
+            } else {
+                0.0
+            };
+            (stats.repr.clone(), z_score)
+        })
+        .collect::<Vec<_>>();
+    profile.sort_unstable_by(|a, b| a.0.cmp(&b.0));
+    let norm = profile.iter().map(|(_, z)| z * z).sum::<f64>().sqrt();
+    if norm > 0.0 {
+        profile.iter_mut().for_each(|(_, z)| *z /= norm);
+    }
+    profile
+}
+
+/// Derives the generator of the randomized graph at `idx` from the master seed.
+fn task_rng(seed: u64, idx: usize) -> XorShift {
+    XorShift::new(splitmix64(seed, idx as u64))
+}
+
+/// Summarizes the randomized counts of a motif against its observed count.
+fn summarize(repr: &str, count: usize, samples: &[usize]) -> MotifSignificance {
+    let n = samples.len() as f64;
+    let mean = samples.iter().sum::<usize>() as f64 / n.max(1.0);
+    let variance = samples
+        .iter()
+        .map(|x| (*x as f64 - mean).powi(2))
+        .sum::<f64>()
+        / (n - 1.0).max(1.0);
+    let std = variance.sqrt();
+    let n_extreme = samples.iter().filter(|x| **x >= count).count();
+    MotifSignificance {
+        repr: repr.to_string(),
+        count,
+        mean,
+        std,
+        z_score: (count as f64 - mean) / std,
+        p_value: (n_extreme + 1) as f64 / (n + 1.0),
+    }
+}
+
+#[cfg(test)]
+mod testing {
+    use super::*;
+    use crate::{io::load_numeric_graph, random};
+
+    #[test]
+    fn summarize_statistics() {
+        let stats = summarize("&AG", 10, &[2, 4, 6]);
+        assert_eq!(stats.mean, 4.0);
+        assert_eq!(stats.std, 2.0);
+        assert_eq!(stats.z_score, 3.0);
+        assert_eq!(stats.p_value, 0.25);
+
+        let stats = summarize("&AG", 4, &[2, 4, 6]);
+        assert_eq!(stats.z_score, 0.0);
+        assert_eq!(stats.p_value, 0.75);
+    }
+
+    #[test]
+    fn significance_is_reproducible() {
+        let graph = load_numeric_graph("example/graphs/yeast.txt", true, false).unwrap();
+        let graph = Bitgraph::from_graph(&graph);
+        let gtrie = Gtrie::read_from_file("example/gtries/dir3.gt").unwrap();
+
+        let a = significance(&gtrie, &graph, random::edge_swap, 3, 1);
+        let b = significance(&gtrie, &graph, random::edge_swap, 3, 1);
+        assert_eq!(a.len(), gtrie.reprs().len());
+        for (a, b) in a.iter().zip(b.iter()) {
+            assert_eq!(a.repr, b.repr);
+            assert_eq!(a.mean, b.mean);
+        }
+
+        // Observed counts are those of a plain census.
+        let mut observed = HashMap::new();
+        gtrie.census_into(&graph, &mut observed);
+        for stats in a {
+            assert_eq!(stats.count, observed.get(&stats.repr).copied().unwrap_or(0));
+        }
+    }
+
+    #[test]
+    fn significance_independent_of_threads() {
+        let graph = load_numeric_graph("example/graphs/yeast.txt", true, false).unwrap();
+        let graph = Bitgraph::from_graph(&graph);
+        let gtrie = Gtrie::read_from_file("example/gtries/dir3.gt").unwrap();
+
+        let z_scores = |threads: usize| {
+            let pool = rayon::ThreadPoolBuilder::new()
+                .num_threads(threads)
+                .build()
+                .unwrap();
+            pool.install(|| significance(&gtrie, &graph, random::edge_swap, 8, 42))
+                .into_iter()
+                .map(|stats| (stats.repr, stats.z_score.to_bits()))
+                .collect::<Vec<_>>()
+        };
+        assert_eq!(z_scores(1), z_scores(4));
+    }
+
+    #[test]
+    fn unswapped_null_model_matches_observed() {
+        let graph = load_numeric_graph("example/graphs/yeast.txt", true, false).unwrap();
+        let graph = Bitgraph::from_graph(&graph);
+        let gtrie = Gtrie::read_from_file("example/gtries/dir3.gt").unwrap();
+
+        // Without swaps every randomized graph is the observed graph.
+        let null_model = |graph: &Bitgraph, rng: &mut XorShift| random::edge_swap_n(graph, rng, 0);
+        for stats in significance(&gtrie, &graph, null_model, 3, 0) {
+            assert_eq!(stats.mean, stats.count as f64);
+            assert_eq!(stats.std, 0.0);
+            assert_eq!(stats.p_value, 1.0);
+        }
+    }
+
+    #[test]
+    fn profile_has_unit_norm() {
+        let graph = load_numeric_graph("example/graphs/yeast.txt", true, false).unwrap();
+        let graph = Bitgraph::from_graph(&graph);
+        let mut gtrie = Gtrie::read_from_file("example/gtries/dir3.gt").unwrap();
+
+        let stats = significance(&gtrie, &graph, random::edge_swap, 8, 42);
+        let profile = significance_profile(&stats);
+        let norm = profile.iter().map(|(_, z)| z * z).sum::<f64>().sqrt();
+        assert!((norm - 1.0).abs() < 1e-9);
+
+        // Aligned with the fingerprint: same motifs in the same order.
+        gtrie.census(&graph);
+        let fingerprint = gtrie.fingerprint(false);
+        assert_eq!(profile.len(), fingerprint.len());
+        let mut reprs = gtrie.reprs();
+        reprs.sort_unstable();
+        assert!(profile.iter().map(|(repr, _)| repr.as_str()).eq(reprs));
+        for ((repr, _), freq) in profile.iter().zip(fingerprint) {
+            assert_eq!(
+                gtrie.get_nonzero().get(repr).copied().unwrap_or(0) as f64,
+                freq
+            );
+        }
+
+        assert!(significance_profile(&[]).is_empty());
+    }
+}
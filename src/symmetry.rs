@@ -1,5 +1,6 @@
+use anyhow::{anyhow, bail};
 use serde::{Deserialize, Serialize};
-use std::fmt::Display;
+use std::{fmt::Display, str::FromStr};
 
 /// A struct to represent the orbit-fixing conditions of a graph
 /// Both are vertex indices with the expectation that the first
@@ -14,6 +15,23 @@ impl Display for Condition {
         write!(f, "{}<{}", self.u, self.v)
     }
 }
+impl FromStr for Condition {
+    type Err = anyhow::Error;
+
+    /// Parses a condition written as `u<v`.
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        let (u, v) = s
+            .trim()
+            .split_once('<')
+            .ok_or_else(|| anyhow!("Invalid condition `{}`: expected `u<v`", s))?;
+        let u = u.trim().parse::<usize>()?;
+        let v = v.trim().parse::<usize>()?;
+        if u >= v {
+            bail!("Invalid condition `{}`: the first index must be smaller", s);
+        }
+        Ok(Condition::new(u, v))
+    }
+}
 impl Condition {
     pub fn new(u: usize, v: usize) -> Self {
         assert!(u < v);
@@ -62,11 +80,43 @@ impl Display for Conditions {
         write!(f, "{}", s)
     }
 }
+impl FromStr for Conditions {
+    type Err = anyhow::Error;
+
+    /// Parses comma or whitespace separated conditions, optionally enclosed in
+    /// `|` as they are displayed (e.g. `|0<1 1<2|`).
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        let s = s.trim();
+        let s = s
+            .strip_prefix('|')
+            .and_then(|s| s.strip_suffix('|'))
+            .unwrap_or(s);
+        let conditions = s
+            .split(|c: char| c == ',' || c.is_whitespace())
+            .filter(|c| !c.is_empty())
+            .map(Condition::from_str)
+            .collect::<Result<Vec<_>, _>>()?;
+        Ok(Conditions { conditions })
+    }
+}
 impl Conditions {
     pub fn from_vec(conditions: Vec<Condition>) -> Self {
         Conditions { conditions }
     }
 
+    /// Creates the conditions from `(u, v)` pairs, each requiring `u < v`.
+    #[allow(dead_code)]
+    pub fn from_pairs(pairs: &[(usize, usize)]) -> Self {
+        let conditions = pairs.iter().map(|(u, v)| Condition::new(*u, *v)).collect();
+        Conditions { conditions }
+    }
+
+    /// Returns the conditions as `(u, v)` pairs.
+    #[allow(dead_code)]
+    pub fn as_pairs(&self) -> Vec<(usize, usize)> {
+        self.conditions.iter().map(|c| (c.u, c.v)).collect()
+    }
+
     pub fn iter(&self) -> impl Iterator<Item = &Condition> {
         self.conditions.iter()
     }
@@ -173,4 +223,42 @@ mod testing {
             assert_eq!(conditions.respects_all(d1, d2, u, v), expected);
         }
     }
+
+    #[test]
+    fn conditions_pairs() {
+        let pairs = vec![(0, 1), (1, 2), (0, 3)];
+        let conditions = Conditions::from_pairs(&pairs);
+        assert_eq!(conditions.len(), 3);
+        assert_eq!(conditions.as_pairs(), pairs);
+        assert!(conditions.contains(&Condition::new(1, 2)));
+    }
+
+    #[test]
+    fn conditions_serde_roundtrip() {
+        let conditions = Conditions::from_pairs(&[(0, 1), (1, 2), (2, 3)]);
+
+        let bytes = rmp_serde::to_vec(&conditions).unwrap();
+        let decoded: Conditions = rmp_serde::from_slice(&bytes).unwrap();
+        assert_eq!(decoded.as_pairs(), conditions.as_pairs());
+
+        let json = serde_json::to_string(&conditions).unwrap();
+        let decoded: Conditions = serde_json::from_str(&json).unwrap();
+        assert_eq!(decoded.as_pairs(), conditions.as_pairs());
+    }
+
+    #[test]
+    fn conditions_from_str() {
+        let conditions = Conditions::from_pairs(&[(0, 1), (1, 2), (0, 3)]);
+        let decoded = conditions.to_string().parse::<Conditions>().unwrap();
+        assert_eq!(decoded.as_pairs(), conditions.as_pairs());
+
+        let decoded = "0<1, 1<2,0<3".parse::<Conditions>().unwrap();
+        assert_eq!(decoded.as_pairs(), conditions.as_pairs());
+        assert_eq!("2<5".parse::<Condition>().unwrap(), Condition::new(2, 5));
+
+        assert!("1<0".parse::<Condition>().is_err());
+        assert!("1<1".parse::<Condition>().is_err());
+        assert!("0>1".parse::<Condition>().is_err());
+        assert!("0<1 a<2".parse::<Conditions>().is_err());
+    }
 }
@@ -0,0 +1,174 @@
+use anyhow::{bail, Result};
+use hashbrown::HashMap;
+use std::{
+    fs::File,
+    io::{BufRead, BufReader},
+};
+
+use crate::bitgraph::{Bitgraph, Neighborhood};
+
+/// Weights of the directed edges of a graph.
+///
+/// Edges without an explicit weight have a weight of 1.
+#[derive(Debug, Clone, Default)]
+pub struct EdgeWeights {
+    weights: HashMap<(usize, usize), f64>,
+}
+impl EdgeWeights {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Loads weights from a 1-indexed whitespace delimited `u v w` edge list.
+    pub fn from_file(path: &str) -> Result<Self> {
+        let mut reader = File::open(path).map(BufReader::new)?;
+        Self::from_buffer(&mut reader)
+    }
+
+    /// Loads weights from a buffer, see [`EdgeWeights::from_file`].
+    pub fn from_buffer<B: BufRead>(buffer: &mut B) -> Result<Self> {
+        let mut weights = Self::new();
+        for (idx, line) in buffer.lines().enumerate() {
+            let line = line?;
+            let fields = line.split_whitespace().collect::<Vec<_>>();
+            let [u, v, w] = fields[..] else {
+                bail!("Line {}: expected `u v w`, found `{}`", idx + 1, line);
+            };
+            let (u, v, w) = (u.parse::<usize>()?, v.parse::<usize>()?, w.parse::<f64>()?);
+            if u == 0 || v == 0 {
+                bail!("ERROR: Found a node index: 0; Please use 1-indexed node indices.");
+            }
+            weights.insert(u - 1, v - 1, w)?;
+        }
+        Ok(weights)
+    }
+
+    /// Sets the weight of the edge `u -> v`.
+    pub fn insert(&mut self, u: usize, v: usize, w: f64) -> Result<()> {
+        if !(w.is_finite() && w >= 0.0) {
+            bail!(
+                "Weight of edge {} -> {} is not a finite non-negative number: {}",
+                u + 1,
+                v + 1,
+                w
+            );
+        }
+        self.weights.insert((u, v), w);
+        Ok(())
+    }
+
+    /// Gives every edge `u -> v` without a weight of its own the weight of
+    /// `v -> u`, as for a graph whose edges were symmetrized.
+    pub fn symmetrize(&mut self) {
+        let reversed = self
+            .weights
+            .iter()
+            .map(|((u, v), w)| ((*v, *u), *w))
+            .collect::<Vec<_>>();
+        for (edge, w) in reversed {
+            self.weights.entry(edge).or_insert(w);
+        }
+    }
+
+    /// Returns the weight of the edge `u -> v`.
+    pub fn get(&self, u: usize, v: usize) -> f64 {
+        self.weights.get(&(u, v)).copied().unwrap_or(1.0)
+    }
+
+    /// The weight of the occurrence induced by `used`: the product of the
+    /// weights of the edges of the graph between its vertices.
+    pub fn occurrence_weight(&self, graph: &Bitgraph, used: &[usize]) -> f64 {
+        let mut weight = 1.0;
+        for u in used {
+            for v in used.iter().filter(|v| *v != u) {
+                if graph.is_connected(*u, *v) {
+                    weight *= self.get(*u, *v);
+                }
+            }
+        }
+        weight
+    }
+
+    /// Moves the weights to new vertex ids, `map(u)` being the id of `u` or
+    /// `None` to drop the weights of its edges.
+    pub fn map_vertices<F: Fn(usize) -> Option<usize>>(&self, map: F) -> Self {
+        let weights = self
+            .weights
+            .iter()
+            .filter_map(|((u, v), w)| Some(((map(*u)?, map(*v)?), *w)))
+            .collect();
+        Self { weights }
+    }
+}
+
+/// A view of a weighted graph whose occurrences may only use edges with a
+/// weight of at least `min_weight`.
+pub(crate) struct HeavyEdges<'a> {
+    graph: &'a Bitgraph,
+    min_weight: f64,
+}
+impl<'a> HeavyEdges<'a> {
+    pub(crate) fn new(graph: &'a Bitgraph, min_weight: f64) -> Self {
+        Self { graph, min_weight }
+    }
+}
+impl Neighborhood for HeavyEdges<'_> {
+    fn is_connected(&self, u: usize, v: usize) -> bool {
+        self.graph.is_connected(u, v)
+    }
+
+    fn neighbors(&self, u: usize) -> &[usize] {
+        self.graph.neighbors(u)
+    }
+
+    fn n_nodes(&self) -> usize {
+        self.graph.n_nodes()
+    }
+
+    fn admits_edge(&self, u: usize, v: usize) -> bool {
+        self.graph.edge_weight(u, v) >= self.min_weight
+    }
+}
+
+#[cfg(test)]
+mod testing {
+    use super::*;
+    use petgraph::{Directed, Graph};
+
+    #[test]
+    fn edge_weights_from_buffer() {
+        let mut buffer = "1 2 2.5\n2 3 4\n".as_bytes();
+        let weights = EdgeWeights::from_buffer(&mut buffer).unwrap();
+        assert_eq!(weights.get(0, 1), 2.5);
+        assert_eq!(weights.get(2, 0), 1.0);
+
+        let graph: Graph<(), (), Directed> = Graph::from_edges([(0, 1), (1, 2), (2, 0)]);
+        let graph = Bitgraph::from_graph(&graph);
+        assert_eq!(weights.occurrence_weight(&graph, &[0, 1, 2]), 10.0);
+        assert_eq!(weights.occurrence_weight(&graph, &[2, 0]), 1.0);
+
+        assert!(EdgeWeights::from_buffer(&mut "1 2 -1\n".as_bytes()).is_err());
+        assert!(EdgeWeights::from_buffer(&mut "1 2\n".as_bytes()).is_err());
+        assert!(EdgeWeights::from_buffer(&mut "0 2 1\n".as_bytes()).is_err());
+    }
+
+    #[test]
+    fn mapped_edge_weights() {
+        let mut buffer = "1 2 0.5\n2 3 2\n3 1 4\n".as_bytes();
+        let weights = EdgeWeights::from_buffer(&mut buffer).unwrap();
+        let mapped = weights.map_vertices(|u| [Some(1), Some(0), None][u]);
+        assert_eq!(mapped.get(1, 0), 0.5);
+        assert_eq!(mapped.get(0, 1), 1.0);
+        assert_eq!(mapped.get(0, 2), 1.0);
+    }
+
+    #[test]
+    fn symmetrized_edge_weights() {
+        let mut buffer = "1 2 3\n2 3 4\n3 2 0.5\n".as_bytes();
+        let mut weights = EdgeWeights::from_buffer(&mut buffer).unwrap();
+        weights.symmetrize();
+        assert_eq!(weights.get(1, 0), 3.0);
+        assert_eq!(weights.get(1, 2), 4.0);
+        assert_eq!(weights.get(2, 1), 0.5);
+    }
+}